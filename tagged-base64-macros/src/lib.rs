@@ -1,11 +1,16 @@
 // Copyright (c) 2022 Espresso Systems (espressosys.com)
 #![no_std]
 
+extern crate alloc;
 extern crate proc_macro;
 
+use alloc::{format, string::ToString, vec::Vec};
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, AttributeArgs, Item, Meta, NestedMeta};
+use syn::{
+    ext::IdentExt, parse_macro_input, AttributeArgs, Data, DeriveInput, Fields, Item, Lit, Meta,
+    NestedMeta,
+};
 
 #[proc_macro_attribute]
 pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -68,12 +73,99 @@ pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(not(feature = "serde"))]
     let struct_def = &input;
 
+    #[cfg(feature = "rocket")]
+    let rocket_impls = {
+        // Rocket's traits take a lifetime or a URI part, which go before the
+        // type's own generic parameters.
+        let mut param_generics = generics.clone();
+        param_generics.params.insert(0, syn::parse_quote!('__a));
+        let (param_impl_generics, _, _) = param_generics.split_for_impl();
+        let mut part_generics = generics.clone();
+        part_generics
+            .params
+            .push(syn::parse_quote!(__P: rocket::http::uri::fmt::Part));
+        let (part_impl_generics, _, _) = part_generics.split_for_impl();
+        let mut ref_generics = part_generics.clone();
+        ref_generics.params.insert(0, syn::parse_quote!('__x));
+        let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+        quote! {
+            impl #param_impl_generics rocket::request::FromParam<'__a> for #name #ty_generics
+            #where_clause
+            {
+                type Error = tagged_base64::Tb64Error;
+                fn from_param(param: &'__a str) -> Result<Self, Self::Error> {
+                    use core::convert::TryFrom;
+                    Self::try_from(tagged_base64::TaggedBase64::parse_untrusted(param)?)
+                }
+            }
+
+            #[rocket::async_trait]
+            impl #param_impl_generics rocket::form::FromFormField<'__a> for #name #ty_generics
+            #where_clause
+            {
+                fn from_value(
+                    field: rocket::form::ValueField<'__a>,
+                ) -> rocket::form::Result<'__a, Self> {
+                    use core::convert::TryFrom;
+                    tagged_base64::TaggedBase64::parse_untrusted(field.value)
+                        .and_then(Self::try_from)
+                        .map_err(|err| {
+                            rocket::form::Error::validation(
+                                ark_std::string::ToString::to_string(&err),
+                            )
+                            .into()
+                        })
+                }
+            }
+
+            impl #part_impl_generics rocket::http::uri::fmt::UriDisplay<__P> for #name #ty_generics
+            #where_clause
+            {
+                fn fmt(
+                    &self,
+                    f: &mut rocket::http::uri::fmt::Formatter<'_, __P>,
+                ) -> ark_std::fmt::Result {
+                    f.write_value(ark_std::string::ToString::to_string(self))
+                }
+            }
+
+            impl #part_impl_generics rocket::http::uri::fmt::FromUriParam<__P, #name #ty_generics>
+                for #name #ty_generics
+            #where_clause
+            {
+                type Target = #name #ty_generics;
+                fn from_uri_param(param: Self::Target) -> Self::Target {
+                    param
+                }
+            }
+
+            impl #ref_impl_generics rocket::http::uri::fmt::FromUriParam<__P, &'__x #name #ty_generics>
+                for #name #ty_generics
+            #where_clause
+            {
+                type Target = &'__x #name #ty_generics;
+                fn from_uri_param(param: Self::Target) -> Self::Target {
+                    param
+                }
+            }
+        }
+    };
+    #[cfg(not(feature = "rocket"))]
+    let rocket_impls = quote!();
+
+    // A literal tag is checked at compile time. An expression may not be
+    // constant, so it is left to fail when used.
+    let tag_expr = match args.first() {
+        Some(NestedMeta::Lit(Lit::Str(_))) => quote!(tagged_base64::const_tag!(#tag)),
+        _ => quote!(#tag),
+    };
+
     let output = quote! {
         #struct_def
 
         impl #impl_generics tagged_base64::Tagged for #name #ty_generics #where_clause {
             fn tag() -> ark_std::string::String {
-                ark_std::string::String::from(#tag)
+                ark_std::string::String::from(#tag_expr)
             }
         }
 
@@ -134,8 +226,91 @@ pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
             type Err = tagged_base64::Tb64Error;
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 use core::convert::TryFrom;
+                // Keep the error from the conversion, so that a string with
+                // the wrong tag is reported as such.
                 Self::try_from(tagged_base64::TaggedBase64::from_str(s)?)
-                    .map_err(|_| tagged_base64::Tb64Error::InvalidData)
+            }
+        }
+
+        #rocket_impls
+    };
+    output.into()
+}
+
+// The same as `tagged_base64::TB64_MAX_TAG_LEN`, which this crate can't
+// depend on.
+const MAX_TAG_LEN: usize = 64;
+
+#[proc_macro_derive(AsTagged)]
+pub fn derive_as_tagged(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => panic!("`AsTagged` can only be derived for enums"),
+    };
+    let variants = variants
+        .iter()
+        .map(|variant| {
+            if !matches!(variant.fields, Fields::Unit) {
+                panic!("`AsTagged` requires fieldless variants");
+            }
+            let ident = &variant.ident;
+            let tag = ident.unraw().to_string();
+            if !tag.is_ascii() {
+                panic!("`AsTagged` requires ASCII variant names, found {}", tag);
+            }
+            if tag.len() > MAX_TAG_LEN {
+                panic!(
+                    "`AsTagged` requires variant names of at most {} bytes, found {} of {}",
+                    MAX_TAG_LEN,
+                    tag,
+                    tag.len()
+                );
+            }
+            (ident, tag)
+        })
+        .collect::<Vec<_>>();
+    let tag_arms = variants
+        .iter()
+        .map(|(ident, tag)| quote!(#name::#ident => #tag));
+    let value_arms = variants
+        .iter()
+        .map(|(ident, _)| quote!(#name::#ident => #name::#ident as u8));
+    // Discriminants are only known once the enum is compiled, so they are
+    // checked by const assertions rather than here, so that `as u8` never
+    // truncates.
+    let range_checks = variants.iter().map(|(ident, _)| {
+        let message = format!(
+            "`AsTagged` requires discriminants from 0 to 255, but {}::{} is out of range",
+            name, ident
+        );
+        quote! {
+            assert!(
+                (#name::#ident as i128) >= 0 && (#name::#ident as i128) <= 255,
+                #message
+            );
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let output = quote! {
+        const _: () = {
+            #(#range_checks)*
+        };
+
+        impl #impl_generics tagged_base64::AsTagged for #name #ty_generics #where_clause {
+            fn tag(&self) -> &'static str {
+                match self {
+                    #(#tag_arms,)*
+                }
+            }
+
+            fn to_tagged(&self) -> tagged_base64::TaggedBase64 {
+                let discriminant = match self {
+                    #(#value_arms,)*
+                };
+                tagged_base64::TaggedBase64::new(self.tag(), &[discriminant]).unwrap()
             }
         }
     };
@@ -0,0 +1,105 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Node.js bindings for [tagged_base64], built as a native addon with
+//! napi-rs.
+//!
+//! These are for Node environments where the output of `wasm-pack` is
+//! awkward to load, such as workers and some bundler configurations. They
+//! wrap the same Rust implementation, so strings are interchangeable with
+//! those of the Rust crate and the wasm bindings.
+//!
+//! Build with `npm run build`, which produces `index.js` and `index.d.ts`:
+//!
+//! ```js
+//! const { TaggedBase64, encode } = require('tagged-base64-node');
+//! const tb64 = TaggedBase64.parse('TX~Zm9vYmFy1w');
+//! tb64.tag;                          // 'TX'
+//! tb64.value;                        // <Buffer 66 6f 6f 62 61 72>
+//! encode('TX', Buffer.from('foobar')); // 'TX~Zm9vYmFy1w'
+//! ```
+
+use napi::{bindgen_prelude::Buffer, Error, Result, Status};
+use napi_derive::napi;
+use tagged_base64::Tb64Error;
+
+/// Converts an error to a JavaScript `Error` whose message begins with the
+/// [code](Tb64Error::code) of the error, such as `INVALID_CHECKSUM`.
+fn to_napi_error(err: Tb64Error) -> Error {
+    Error::new(Status::InvalidArg, format!("{}: {}", err.code(), err))
+}
+
+/// A tagged base64 value, exported to JavaScript as `TaggedBase64`.
+#[napi(js_name = "TaggedBase64")]
+pub struct JsTaggedBase64 {
+    tb64: tagged_base64::TaggedBase64,
+}
+
+#[napi]
+impl JsTaggedBase64 {
+    /// Constructs a value from a tag and bytes, throwing if the tag is
+    /// invalid.
+    #[napi(constructor)]
+    pub fn new(tag: String, value: Buffer) -> Result<Self> {
+        let tb64 = tagged_base64::TaggedBase64::new(&tag, &value).map_err(to_napi_error)?;
+        Ok(JsTaggedBase64 { tb64 })
+    }
+
+    /// Parses a string of the form tag~value, throwing if it is invalid.
+    #[napi(factory)]
+    pub fn parse(tb64: String) -> Result<Self> {
+        let tb64 = tagged_base64::TaggedBase64::parse(&tb64).map_err(to_napi_error)?;
+        Ok(JsTaggedBase64 { tb64 })
+    }
+
+    /// The tag.
+    #[napi(getter)]
+    pub fn tag(&self) -> String {
+        self.tb64.tag()
+    }
+
+    /// The value.
+    #[napi(getter)]
+    pub fn value(&self) -> Buffer {
+        self.tb64.value().into()
+    }
+
+    /// Formats the value as a string of the form tag~value.
+    #[napi]
+    pub fn encode(&self) -> String {
+        self.tb64.to_string()
+    }
+
+    /// Formats the value as a string of the form tag~value.
+    #[napi(js_name = "toString")]
+    pub fn to_js_string(&self) -> String {
+        self.tb64.to_string()
+    }
+
+    /// Formats the value as a string for `JSON.stringify`.
+    #[napi(js_name = "toJSON")]
+    pub fn to_json(&self) -> String {
+        self.tb64.to_string()
+    }
+
+    /// Returns true if both values have the same tag and value.
+    #[napi]
+    pub fn equals(&self, other: &JsTaggedBase64) -> bool {
+        self.tb64 == other.tb64
+    }
+}
+
+/// Encodes a tag and bytes as a string of the form tag~value, throwing if
+/// the tag is invalid.
+#[napi]
+pub fn encode(tag: String, value: Buffer) -> Result<String> {
+    tagged_base64::TaggedBase64::new(&tag, &value)
+        .map(|tb64| tb64.to_string())
+        .map_err(to_napi_error)
+}
+
+/// Checks a string of the form tag~value without constructing a value,
+/// throwing if it is invalid.
+#[napi]
+pub fn validate(tb64: String) -> Result<()> {
+    tagged_base64::TaggedBase64::validate(&tb64).map_err(to_napi_error)
+}
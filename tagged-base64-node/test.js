@@ -0,0 +1,24 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+// Tests of the native addon. Build it first with `npm run build`, then run
+//    npm test
+
+const assert = require('assert');
+const { TaggedBase64, encode, validate } = require('./index.js');
+
+const tb64 = TaggedBase64.parse('TX~Zm9vYmFy1w');
+assert.strictEqual(tb64.tag, 'TX');
+assert.deepStrictEqual(tb64.value, Buffer.from('foobar'));
+assert.strictEqual(tb64.encode(), 'TX~Zm9vYmFy1w');
+assert.strictEqual(`${tb64}`, 'TX~Zm9vYmFy1w');
+assert.strictEqual(JSON.stringify({ id: tb64 }), '{"id":"TX~Zm9vYmFy1w"}');
+assert.ok(tb64.equals(new TaggedBase64('TX', Buffer.from('foobar'))));
+
+assert.strictEqual(encode('TX', Buffer.from('foobar')), 'TX~Zm9vYmFy1w');
+validate('TX~Zm9vYmFy1w');
+
+assert.throws(() => TaggedBase64.parse('TY~Zm9vYmFy1w'), /^Error: INVALID_CHECKSUM/);
+assert.throws(() => new TaggedBase64('T~X', Buffer.from('')), /^Error: INVALID_TAG_CHAR/);
+assert.throws(() => validate('TX'), /^Error: MISSING_DELIMITER/);
+
+console.log('ok');
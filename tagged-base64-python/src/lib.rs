@@ -0,0 +1,91 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Python bindings for [tagged_base64], built as an extension module with
+//! pyo3.
+//!
+//! The module wraps the Rust implementation, so the checksum and the
+//! string form always agree with the Rust crate. Build and install it with
+//! `maturin develop` or `pip install .` in this directory:
+//!
+//! ```python
+//! from tagged_base64 import TaggedBase64
+//! tb64 = TaggedBase64.from_str("TX~Zm9vYmFy1w")
+//! tb64.tag                               # 'TX'
+//! bytes(tb64)                            # b'foobar'
+//! str(TaggedBase64("TX", b"foobar"))     # 'TX~Zm9vYmFy1w'
+//! ```
+
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*, types::PyBytes};
+
+create_exception!(
+    tagged_base64,
+    Tb64Error,
+    PyValueError,
+    "Raised for an invalid tag or string. The message begins with the code of the error, such as INVALID_CHECKSUM."
+);
+
+/// Converts an error to a [Tb64Error] exception whose message begins with
+/// the [code](tagged_base64::Tb64Error::code) of the error.
+fn to_py_err(err: tagged_base64::Tb64Error) -> PyErr {
+    Tb64Error::new_err(format!("{}: {}", err.code(), err))
+}
+
+/// A tagged base64 value, exported to Python as `TaggedBase64`.
+#[pyclass(name = "TaggedBase64", module = "tagged_base64", eq, frozen, hash)]
+#[derive(PartialEq, Eq, Hash)]
+pub struct PyTaggedBase64 {
+    tb64: tagged_base64::TaggedBase64,
+}
+
+#[pymethods]
+impl PyTaggedBase64 {
+    /// Constructs a value from a tag and bytes, raising Tb64Error if the
+    /// tag is invalid.
+    #[new]
+    fn new(tag: &str, value: &[u8]) -> PyResult<Self> {
+        let tb64 = tagged_base64::TaggedBase64::new(tag, value).map_err(to_py_err)?;
+        Ok(PyTaggedBase64 { tb64 })
+    }
+
+    /// Parses a string of the form tag~value, raising Tb64Error if it is
+    /// invalid.
+    #[staticmethod]
+    fn from_str(tb64: &str) -> PyResult<Self> {
+        let tb64 = tagged_base64::TaggedBase64::parse(tb64).map_err(to_py_err)?;
+        Ok(PyTaggedBase64 { tb64 })
+    }
+
+    /// The tag.
+    #[getter]
+    fn tag(&self) -> &str {
+        self.tb64.tag_ref()
+    }
+
+    /// The value.
+    #[getter]
+    fn value<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.tb64.value_ref())
+    }
+
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        self.value(py)
+    }
+
+    fn __str__(&self) -> String {
+        self.tb64.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("TaggedBase64.from_str({:?})", self.tb64.to_string())
+    }
+}
+
+/// Tagged base64, an encoding for binary data for use in URLs, GUIs,
+/// emails, text messages, etc.
+#[pymodule]
+#[pyo3(name = "tagged_base64")]
+fn tagged_base64_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTaggedBase64>()?;
+    m.add("Tb64Error", m.py().get_type::<Tb64Error>())?;
+    Ok(())
+}
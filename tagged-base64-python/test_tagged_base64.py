@@ -0,0 +1,33 @@
+# Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+# Tests of the extension module. Install it first with `maturin develop`,
+# then run
+#    python -m unittest test_tagged_base64
+
+import unittest
+
+from tagged_base64 import TaggedBase64, Tb64Error
+
+
+class TestTaggedBase64(unittest.TestCase):
+    def test_round_trip(self):
+        tb64 = TaggedBase64.from_str("TX~Zm9vYmFy1w")
+        self.assertEqual(tb64.tag, "TX")
+        self.assertEqual(tb64.value, b"foobar")
+        self.assertEqual(bytes(tb64), b"foobar")
+        self.assertEqual(str(tb64), "TX~Zm9vYmFy1w")
+        self.assertEqual(tb64, TaggedBase64("TX", b"foobar"))
+        self.assertEqual(eval(repr(tb64)), tb64)
+        self.assertEqual(len({tb64, TaggedBase64("TX", b"foobar")}), 1)
+
+    def test_errors(self):
+        with self.assertRaisesRegex(Tb64Error, "^INVALID_CHECKSUM"):
+            TaggedBase64.from_str("TY~Zm9vYmFy1w")
+        with self.assertRaisesRegex(Tb64Error, "^INVALID_TAG_CHAR"):
+            TaggedBase64("T~X", b"")
+        with self.assertRaises(ValueError):
+            TaggedBase64.from_str("TX")
+
+
+if __name__ == "__main__":
+    unittest.main()
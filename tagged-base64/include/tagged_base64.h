@@ -0,0 +1,143 @@
+/* Copyright (c) 2022 Espresso Systems (espressosys.com) */
+
+#ifndef TAGGED_BASE64_H
+#define TAGGED_BASE64_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of a call. Every [Tb64Error] has its own status, so clients can
+// tell errors apart as Rust callers can.
+typedef enum Tb64Status {
+  TB64_STATUS_OK = 0,
+  // A required pointer argument was null.
+  TB64_STATUS_NULL_POINTER = 1,
+  // A string argument was not valid UTF-8.
+  TB64_STATUS_INVALID_UTF8 = 2,
+  TB64_STATUS_INVALID_TAG = 3,
+  TB64_STATUS_INVALID_TAG_CHAR = 4,
+  TB64_STATUS_MISSING_DELIMITER = 5,
+  TB64_STATUS_MISSING_CHECKSUM = 6,
+  TB64_STATUS_BASE64 = 7,
+  TB64_STATUS_INVALID_CHECKSUM = 8,
+  TB64_STATUS_INVALID_DATA = 9,
+  TB64_STATUS_NON_ASCII_TAG = 10,
+  TB64_STATUS_TAG_NOT_ALLOWED = 11,
+  TB64_STATUS_INVALID_LENGTH = 12,
+  TB64_STATUS_EMPTY_VALUE = 13,
+  TB64_STATUS_EMPTY_TAG = 14,
+  TB64_STATUS_UNSUPPORTED_VERSION = 15,
+  TB64_STATUS_IO = 16,
+  TB64_STATUS_TAG_TOO_LONG = 17,
+  TB64_STATUS_VALUE_TOO_LONG = 18,
+  TB64_STATUS_WRONG_CHECKSUM_KIND = 19,
+  TB64_STATUS_INVALID_SEGMENT = 20,
+  TB64_STATUS_INVALID_ELEMENT = 21,
+  TB64_STATUS_INVALID_BASE32_CHAR = 22,
+  TB64_STATUS_UNSUPPORTED_MULTIBASE = 23,
+  TB64_STATUS_UNKNOWN_CODEC = 24,
+  TB64_STATUS_INVALID_DELIMITER = 25,
+  TB64_STATUS_EMPTY_TAG_SEGMENT = 26,
+  TB64_STATUS_WRONG_VALUE_LENGTH = 28,
+  TB64_STATUS_MISSING_QUERY_PARAM = 29,
+  TB64_STATUS_INVALID_PERCENT_ENCODING = 30,
+  TB64_STATUS_DECODED_TOO_LONG = 31,
+} Tb64Status;
+
+// A structure holding a string tag, vector of bytes, and a checksum
+// covering the tag and the bytes.
+//
+// Values are compared by tag and value bytes only. The checksum is derived
+// from those, so a stale checksum does not make two values unequal; use
+// [verify_checksum](TaggedBase64::verify_checksum) to check it. The one
+// exception is that a plain value and an extended value with the same bytes
+// are unequal, since they display differently.
+//
+// Values are ordered by tag, then by value bytes, as by
+// [canonical_cmp](TaggedBase64::canonical_cmp), with plain values before
+// extended ones with the same bytes.
+typedef struct TaggedBase64 TaggedBase64;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses a NUL-terminated string of the form tag~value, storing the value
+// in `*out`, or null on failure.
+//
+// The string is parsed with the limits of
+// [parse_untrusted](TaggedBase64::parse_untrusted), so a value longer than
+// [TB64_UNTRUSTED_MAX_DECODED_LEN](crate::TB64_UNTRUSTED_MAX_DECODED_LEN)
+// fails with [Tb64Status::DecodedTooLong].
+//
+// # Safety
+// `s` must be null or a NUL-terminated string, and `out` must be null or
+// valid for writes.
+enum Tb64Status tb64_parse(const char *s, struct TaggedBase64 **out);
+
+// Constructs a value from a NUL-terminated tag and `len` bytes at `value`,
+// storing it in `*out`, or null on failure. `value` may be null if `len`
+// is 0.
+//
+// # Safety
+// `tag` must be null or a NUL-terminated string, `value` must be valid for
+// reads of `len` bytes unless `len` is 0, and `out` must be null or valid
+// for writes.
+enum Tb64Status tb64_new(const char *tag,
+                         const uint8_t *value,
+                         size_t len,
+                         struct TaggedBase64 **out);
+
+// Formats a value as a string of the form tag~value, to be released with
+// [tb64_string_free]. Returns null if `tb64` is null.
+//
+// # Safety
+// `tb64` must be null or a value returned by this library and not yet
+// freed.
+char *tb64_encode(const struct TaggedBase64 *tb64);
+
+// Gets the tag of a value, to be released with [tb64_string_free]. Returns
+// null if `tb64` is null.
+//
+// # Safety
+// `tb64` must be null or a value returned by this library and not yet
+// freed.
+char *tb64_tag(const struct TaggedBase64 *tb64);
+
+// Gets the value bytes, storing their number in `*len`. The bytes belong
+// to `tb64` and remain valid until it is freed. Returns null, with a
+// length of 0, if `tb64` is null.
+//
+// # Safety
+// `tb64` must be null or a value returned by this library and not yet
+// freed, and `len` must be null or valid for writes.
+const uint8_t *tb64_value(const struct TaggedBase64 *tb64, size_t *len);
+
+// Releases a value. Does nothing if `tb64` is null.
+//
+// # Safety
+// `tb64` must be null or a value returned by this library and not yet
+// freed.
+void tb64_free(struct TaggedBase64 *tb64);
+
+// Releases a string returned by this library. Does nothing if `s` is null.
+//
+// # Safety
+// `s` must be null or a string returned by this library and not yet
+// freed.
+void tb64_string_free(char *s);
+
+// Describes a status as a static NUL-terminated string, which must not be
+// freed.
+const char *tb64_status_message(enum Tb64Status status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TAGGED_BASE64_H */
@@ -0,0 +1,83 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Generators for property tests with [quickcheck].
+//!
+//! [TaggedBase64] implements [Arbitrary], producing valid values with random
+//! tags, including extended values with a timestamp or a stronger checksum.
+//! [CorruptedString] produces strings which look like tagged base64 but fail
+//! to parse, for testing error paths.
+
+use crate::{ChecksumKind, TaggedBase64, TB64_MAX_TAG_LEN};
+use quickcheck::{Arbitrary, Gen};
+use std::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Characters allowed in a tag.
+const TAG_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Characters substituted into a string to corrupt it: the tag characters,
+/// the delimiter, and a few which are never valid.
+const CORRUPT_CHARS: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_~+/=. ";
+
+impl Arbitrary for TaggedBase64 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let tag_len = usize::arbitrary(g) % (g.size().min(TB64_MAX_TAG_LEN) + 1);
+        let tag: String = (0..tag_len)
+            .map(|_| *g.choose(TAG_CHARS).unwrap() as char)
+            .collect();
+        let value = Vec::<u8>::arbitrary(g);
+        // Mostly plain values, with some of each extended kind.
+        match u8::arbitrary(g) % 8 {
+            0 => TaggedBase64::new_timestamped(&tag, &value, u64::arbitrary(g)),
+            1 => TaggedBase64::new_with_checksum(&tag, &value, ChecksumKind::Crc16),
+            2 => TaggedBase64::new_with_checksum(&tag, &value, ChecksumKind::Crc32c),
+            _ => TaggedBase64::new(&tag, &value),
+        }
+        .unwrap()
+    }
+
+    /// Shrinks the tag and the payload. The result is always a plain value.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let tag = self.tag.clone();
+        let payload = self.payload().to_vec();
+        let shorter_tags = (0..tag.len())
+            .rev()
+            .map(|len| TaggedBase64::new(&tag[..len], &payload).unwrap())
+            .collect::<Vec<_>>();
+        let smaller_values = payload
+            .shrink()
+            .map(move |value| TaggedBase64::new(&tag, &value).unwrap());
+        Box::new(shorter_tags.into_iter().chain(smaller_values))
+    }
+}
+
+/// A string made by changing one character of a valid tagged base64 string,
+/// which does not parse.
+///
+/// The checksum catches any single substitution in the tag or value, but a
+/// substituted delimiter can occasionally produce another valid string.
+/// Such strings are discarded and generated again.
+#[derive(Clone, Debug)]
+pub struct CorruptedString(pub String);
+
+impl Arbitrary for CorruptedString {
+    fn arbitrary(g: &mut Gen) -> Self {
+        loop {
+            let mut s = TaggedBase64::arbitrary(g).to_string().into_bytes();
+            let i = usize::arbitrary(g) % s.len();
+            let c = *g.choose(CORRUPT_CHARS).unwrap();
+            if s[i] == c {
+                continue;
+            }
+            s[i] = c;
+            let s = String::from_utf8(s).unwrap();
+            if TaggedBase64::parse(&s).is_err() {
+                return CorruptedString(s);
+            }
+        }
+    }
+}
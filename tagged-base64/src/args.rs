@@ -0,0 +1,104 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Command line arguments with [clap].
+//!
+//! [TaggedBase64] implements [ValueParserFactory], so an argument of type
+//! [TaggedBase64] is parsed and validated by clap, which reports a bad value
+//! with the reason it was rejected. [TaggedBase64Parser::with_tag] also
+//! checks the tag.
+
+use crate::TaggedBase64;
+use clap::{
+    builder::{TypedValueParser, ValueParserFactory},
+    error::ErrorKind,
+    Arg, Command, Error,
+};
+use std::{
+    ffi::OsStr,
+    format,
+    string::{String, ToString},
+};
+
+/// Value parser for [TaggedBase64] arguments, optionally requiring a tag.
+///
+/// ```
+/// use clap::Parser;
+/// use tagged_base64::{TaggedBase64, TaggedBase64Parser};
+///
+/// #[derive(Parser)]
+/// struct Opt {
+///     /// Any tagged base64 value.
+///     #[arg(long)]
+///     value: TaggedBase64,
+///
+///     /// A transaction identifier.
+///     #[arg(long, value_parser = TaggedBase64Parser::with_tag("TX"))]
+///     tx: TaggedBase64,
+/// }
+///
+/// let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+/// let value = TaggedBase64::new("ANY", b"").unwrap();
+/// let opt = Opt::try_parse_from(["app", "--tx", &tx.to_string(), "--value", &value.to_string()])
+///     .unwrap();
+/// assert_eq!(opt.tx, tx);
+/// assert!(Opt::try_parse_from(["app", "--tx", &value.to_string(), "--value", &tx.to_string()])
+///     .is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TaggedBase64Parser {
+    tag: Option<String>,
+}
+
+impl TaggedBase64Parser {
+    /// Accepts any valid tagged base64 value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts valid tagged base64 values with the tag `tag`.
+    pub fn with_tag(tag: &str) -> Self {
+        Self {
+            tag: Some(tag.into()),
+        }
+    }
+}
+
+impl TypedValueParser for TaggedBase64Parser {
+    type Value = TaggedBase64;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, Error> {
+        let invalid = |value: &str, reason: String| {
+            let arg = arg.map_or_else(|| "...".into(), |arg| arg.to_string());
+            cmd.clone().error(
+                ErrorKind::ValueValidation,
+                format!("invalid value '{}' for '{}': {}", value, arg, reason),
+            )
+        };
+        let value = value
+            .to_str()
+            .ok_or_else(|| cmd.clone().error(ErrorKind::InvalidUtf8, "invalid UTF-8"))?;
+        let tb64 = TaggedBase64::parse(value).map_err(|err| invalid(value, format!("{}", err)))?;
+        if let Some(tag) = &self.tag {
+            if tb64.tag_ref() != tag {
+                return Err(invalid(
+                    value,
+                    format!("expected tag {:?}, found {:?}", tag, tb64.tag_ref()),
+                ));
+            }
+        }
+        Ok(tb64)
+    }
+}
+
+impl ValueParserFactory for TaggedBase64 {
+    type Parser = TaggedBase64Parser;
+
+    fn value_parser() -> Self::Parser {
+        TaggedBase64Parser::new()
+    }
+}
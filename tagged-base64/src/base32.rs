@@ -0,0 +1,60 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Base32 coding with the bech32 character set, used by
+//! [TaggedBase64::to_string_base32](crate::TaggedBase64::to_string_base32).
+//!
+//! Each character carries five bits, most significant first. The final
+//! character is padded with zero bits, and a decoder rejects padding which
+//! is nonzero or long enough to hold another whole byte, so every byte
+//! string has exactly one encoding.
+
+use crate::Tb64Error;
+use ark_std::{string::String, vec::Vec};
+
+/// The bech32 character set, which omits `1`, `b`, `i`, and `o`.
+pub(crate) const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Appends the encoding of `data` to `out`.
+pub(crate) fn encode(data: &[u8], out: &mut String) {
+    let mut acc = 0u16;
+    let mut bits = 0;
+    for &b in data {
+        acc = (acc << 8) | b as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(CHARSET[((acc >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(CHARSET[((acc << (5 - bits)) & 31) as usize] as char);
+    }
+}
+
+/// Decodes lowercase characters from [CHARSET]. `offset` is the position
+/// of `s` in the whole string, for reporting errors.
+pub(crate) fn decode(s: &str, offset: usize) -> Result<Vec<u8>, Tb64Error> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut acc = 0u16;
+    let mut bits = 0;
+    for (i, c) in s.char_indices() {
+        let value =
+            CHARSET
+                .iter()
+                .position(|&x| x as char == c)
+                .ok_or(Tb64Error::InvalidBase32Char {
+                    offset: offset + i,
+                    character: c,
+                })?;
+        acc = (acc << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(Tb64Error::InvalidLength);
+    }
+    Ok(out)
+}
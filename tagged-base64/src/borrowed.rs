@@ -0,0 +1,57 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Parsing without copying the tag out of the input.
+
+use crate::{TaggedBase64, Tb64Error, BASE64, PARSE_LIMITS};
+use ark_std::{borrow::Cow, vec::Vec};
+
+/// A parsed value whose tag borrows from the string it was parsed from.
+///
+/// The value has to be decoded, so it is always owned, but the tag is
+/// usually a slice of the input. This avoids an allocation per value when
+/// parsing many values out of one large buffer. Only a tag written in hex
+/// by [to_string_hex_tag](TaggedBase64::to_string_hex_tag) is decoded into
+/// an owned string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedBase64Ref<'a> {
+    tag: Cow<'a, str>,
+    value: Vec<u8>,
+    checksum: u8,
+    extended: bool,
+}
+
+impl<'a> TaggedBase64Ref<'a> {
+    /// Parses a string as by [TaggedBase64::parse], borrowing the tag.
+    pub fn parse(tb64: &'a str) -> Result<Self, Tb64Error> {
+        let mut value = Vec::new();
+        let (tag, checksum, extended) =
+            TaggedBase64::decode_with(tb64, &BASE64, PARSE_LIMITS, &mut value)?;
+        Ok(TaggedBase64Ref {
+            tag,
+            value,
+            checksum,
+            extended,
+        })
+    }
+
+    /// Gets the tag.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Gets the value.
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Converts this into a [TaggedBase64], copying the tag but not the
+    /// value.
+    pub fn into_owned(self) -> TaggedBase64 {
+        TaggedBase64 {
+            tag: self.tag.into_owned().into(),
+            value: self.value.into(),
+            checksum: self.checksum,
+            extended: self.extended,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Incremental construction of a [TaggedBase64] value.
+
+use crate::{TaggedBase64, Tb64Error};
+use ark_std::{
+    io,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Builds a [TaggedBase64] from a tag and a value assembled in pieces.
+///
+/// The tag is validated and the checksum computed once, in
+/// [build](Self::build). The builder is also a writer, so serialized
+/// components can be written straight into the value.
+///
+/// ```
+/// # use tagged_base64::{TaggedBase64, TaggedBase64Builder};
+/// let tb64 = TaggedBase64Builder::new()
+///     .tag("TX")
+///     .push_byte(1)
+///     .extend_value(b"payload")
+///     .build()?;
+/// assert_eq!(tb64, TaggedBase64::new("TX", b"\x01payload")?);
+/// # Ok::<(), tagged_base64::Tb64Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TaggedBase64Builder {
+    tag: String,
+    value: Vec<u8>,
+}
+
+impl TaggedBase64Builder {
+    /// Creates a builder with an empty tag and an empty value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tag.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = tag.to_string();
+        self
+    }
+
+    /// Appends bytes to the value.
+    pub fn extend_value(mut self, bytes: &[u8]) -> Self {
+        self.value.extend_from_slice(bytes);
+        self
+    }
+
+    /// Appends a single byte to the value.
+    pub fn push_byte(mut self, byte: u8) -> Self {
+        self.value.push(byte);
+        self
+    }
+
+    /// Validates the tag and produces the TaggedBase64.
+    pub fn build(self) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::validate_tag(&self.tag)?;
+        let checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+        Ok(TaggedBase64 {
+            tag: self.tag.into(),
+            value: self.value.into(),
+            checksum,
+            extended: false,
+        })
+    }
+}
+
+/// Appends written bytes to the value. Writing never fails.
+impl io::Write for TaggedBase64Builder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.value.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
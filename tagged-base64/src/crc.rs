@@ -0,0 +1,108 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Table-driven CRCs used for checksums, computed at compile time.
+//!
+//! - CRC-8 (poly 0x07, init 0, not reflected) for the checksum byte;
+//! - CRC-16/ARC (poly 0x8005, init 0, reflected) and
+//! - CRC-32C (Castagnoli, poly 0x1EDC6F41, init and final XOR 0xFFFFFFFF,
+//!   reflected) for extended values.
+//!
+//! Each function continues a CRC over more data, so a CRC of a
+//! concatenation can be computed piecewise.
+
+/// Lookup table for CRC-8.
+const CRC8_TABLE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Lookup table for CRC-16/ARC.
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Lookup table for CRC-32C.
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continues the CRC-8 `crc` over `data`. Start from 0.
+pub(crate) const fn crc8(mut crc: u8, data: &[u8]) -> u8 {
+    let mut i = 0;
+    while i < data.len() {
+        crc = CRC8_TABLE[(crc ^ data[i]) as usize];
+        i += 1;
+    }
+    crc
+}
+
+/// Continues the CRC-16/ARC `crc` over `data`. Start from 0.
+pub(crate) const fn crc16(mut crc: u16, data: &[u8]) -> u16 {
+    let mut i = 0;
+    while i < data.len() {
+        crc = (crc >> 8) ^ CRC16_TABLE[((crc ^ data[i] as u16) & 0xFF) as usize];
+        i += 1;
+    }
+    crc
+}
+
+/// Continues the CRC-32C `crc` over `data`. Start from 0.
+pub(crate) const fn crc32c(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    let mut i = 0;
+    while i < data.len() {
+        crc = (crc >> 8) ^ CRC32C_TABLE[((crc ^ data[i] as u32) & 0xFF) as usize];
+        i += 1;
+    }
+    !crc
+}
+
+// The standard check value of CRC-32C, over the ASCII digits 1 to 9.
+const _: () = assert!(crc32c(0, b"123456789") == 0xE306_9283);
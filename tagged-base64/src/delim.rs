@@ -0,0 +1,103 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! String forms with a delimiter other than [TB64_DELIM].
+
+use crate::{TaggedBase64, Tb64Error, TB64_DELIM};
+use ark_std::string::{String, ToString};
+use base64::DecodeError;
+
+/// Writes and parses the string form with a custom delimiter, for host
+/// formats which reserve `~`.
+///
+/// Apart from the delimiter, which also doubles to mark an extended value,
+/// the string form is unchanged. The default format uses [TB64_DELIM].
+///
+/// ```
+/// # use tagged_base64::{TaggedBase64, Tb64Format};
+/// let format = Tb64Format::new('!')?;
+/// let tb64 = TaggedBase64::new("TX", b"foobar")?;
+/// assert_eq!(format.format(&tb64), "TX!Zm9vYmFy1w");
+/// assert_eq!(format.parse("TX!Zm9vYmFy1w")?, tb64);
+/// assert!(format.parse("TX~Zm9vYmFy1w").is_err());
+/// # Ok::<(), tagged_base64::Tb64Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Tb64Format {
+    delim: char,
+}
+
+impl Default for Tb64Format {
+    fn default() -> Self {
+        Tb64Format { delim: TB64_DELIM }
+    }
+}
+
+impl Tb64Format {
+    /// Creates a format using `delim` as the delimiter.
+    ///
+    /// The delimiter must be ASCII punctuation other than `-` and `_`,
+    /// which are base64 characters, and `.`, which marks a tag written in
+    /// hex. Otherwise this fails with
+    /// [InvalidDelimiter](Tb64Error::InvalidDelimiter).
+    pub fn new(delim: char) -> Result<Self, Tb64Error> {
+        if !delim.is_ascii_punctuation()
+            || TaggedBase64::is_safe_base64_ascii(delim)
+            || delim == '.'
+        {
+            return Err(Tb64Error::InvalidDelimiter { delim });
+        }
+        Ok(Tb64Format { delim })
+    }
+
+    /// Gets the delimiter.
+    pub fn delim(&self) -> char {
+        self.delim
+    }
+
+    /// Writes the string form of `tb64` with this delimiter.
+    pub fn format(&self, tb64: &TaggedBase64) -> String {
+        let s = tb64.to_string();
+        if self.delim == TB64_DELIM {
+            return s;
+        }
+        // Neither the tag nor the value contains the standard delimiter.
+        s.replace(TB64_DELIM, self.delim.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Parses a string with this delimiter, like
+    /// [parse](TaggedBase64::parse).
+    ///
+    /// Unless it is the delimiter, `~` is not allowed, and is reported like
+    /// any other invalid character.
+    pub fn parse(&self, s: &str) -> Result<TaggedBase64, Tb64Error> {
+        if self.delim == TB64_DELIM {
+            return TaggedBase64::parse(s);
+        }
+        // Exchanging the two delimiters keeps byte offsets, since both are
+        // ASCII, so errors only need the character exchanged back.
+        let swapped: String = s.chars().map(|c| self.swap(c)).collect();
+        TaggedBase64::parse(&swapped).map_err(|err| match err {
+            Tb64Error::InvalidTagChar { offset, character } => Tb64Error::InvalidTagChar {
+                offset,
+                character: self.swap(character),
+            },
+            Tb64Error::Base64 {
+                error: DecodeError::InvalidByte(offset, byte),
+            } => Tb64Error::Base64 {
+                error: DecodeError::InvalidByte(offset, self.swap(byte.into()) as u8),
+            },
+            err => err,
+        })
+    }
+
+    /// Exchanges this delimiter and the standard one.
+    fn swap(&self, c: char) -> char {
+        if c == self.delim {
+            TB64_DELIM
+        } else if c == TB64_DELIM {
+            self.delim
+        } else {
+            c
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Axum support.
+//!
+//! [TaggedBase64] and types declared with the [macro@crate::tagged] macro
+//! deserialize from strings, so they already work with axum's `Path` and
+//! `Query` extractors. [TaggedBase64Path] additionally reports why a value
+//! was rejected, for instance that it has the wrong tag.
+
+use crate::{Tagged, TaggedBase64, Tb64Error};
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{format, string::String};
+
+/// Extractor for a route with a single path parameter holding a value of a
+/// type declared with the [macro@crate::tagged] macro.
+///
+/// The value is parsed with
+/// [parse_untrusted](TaggedBase64::parse_untrusted). A value which does not
+/// parse, including one which is too long, or whose tag is not the tag of
+/// `T`, is rejected with 400 Bad Request and a message giving the reason.
+///
+/// ```
+/// # use ark_serialize::*;
+/// # use tagged_base64::{tagged, TaggedBase64Path};
+/// #[tagged("TX")]
+/// #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+/// struct TransactionId(Vec<u8>);
+///
+/// async fn get_tx(TaggedBase64Path(id): TaggedBase64Path<TransactionId>) -> String {
+///     id.to_string()
+/// }
+///
+/// let app: axum::Router = axum::Router::new().route("/tx/{id}", axum::routing::get(get_tx));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedBase64Path<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for TaggedBase64Path<T>
+where
+    S: Send + Sync,
+    T: Tagged + TryFrom<TaggedBase64, Error = Tb64Error>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(s) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let bad_request = |message: String| (StatusCode::BAD_REQUEST, message).into_response();
+        let tb64 =
+            TaggedBase64::parse_untrusted(&s).map_err(|err| bad_request(format!("{}", err)))?;
+        if tb64.tag_ref() != T::tag() {
+            return Err(bad_request(format!(
+                "expected tag {:?}, found {:?}",
+                T::tag(),
+                tb64.tag_ref()
+            )));
+        }
+        let value = T::try_from(tb64).map_err(|err| bad_request(format!("{}", err)))?;
+        Ok(TaggedBase64Path(value))
+    }
+}
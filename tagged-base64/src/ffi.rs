@@ -0,0 +1,289 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! C interface, for linking the canonical implementation into clients
+//! written in other languages.
+//!
+//! Values are opaque `TaggedBase64` pointers created by [tb64_parse] or
+//! [tb64_new] and released with [tb64_free]. Strings returned by the library
+//! are released with [tb64_string_free]. Functions which can fail return a
+//! [Tb64Status], and [tb64_status_message] describes it.
+//!
+//! The header `include/tagged_base64.h` is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/tagged_base64.h`.
+
+use crate::{TaggedBase64, Tb64Error};
+use core::{ffi::c_char, ptr, slice};
+use std::{
+    boxed::Box,
+    ffi::{CStr, CString},
+    string::ToString,
+};
+
+/// Result of a call. Every [Tb64Error] has its own status, so clients can
+/// tell errors apart as Rust callers can.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tb64Status {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 2,
+    InvalidTag = 3,
+    InvalidTagChar = 4,
+    MissingDelimiter = 5,
+    MissingChecksum = 6,
+    Base64 = 7,
+    InvalidChecksum = 8,
+    InvalidData = 9,
+    NonAsciiTag = 10,
+    TagNotAllowed = 11,
+    InvalidLength = 12,
+    EmptyValue = 13,
+    EmptyTag = 14,
+    UnsupportedVersion = 15,
+    Io = 16,
+    TagTooLong = 17,
+    ValueTooLong = 18,
+    WrongChecksumKind = 19,
+    InvalidSegment = 20,
+    InvalidElement = 21,
+    InvalidBase32Char = 22,
+    UnsupportedMultibase = 23,
+    UnknownCodec = 24,
+    InvalidDelimiter = 25,
+    EmptyTagSegment = 26,
+    // 27 was UnknownTag, now reported as TagNotAllowed.
+    WrongValueLength = 28,
+    MissingQueryParam = 29,
+    InvalidPercentEncoding = 30,
+    DecodedTooLong = 31,
+}
+
+impl From<Tb64Error> for Tb64Status {
+    fn from(err: Tb64Error) -> Self {
+        match err {
+            Tb64Error::InvalidTag => Tb64Status::InvalidTag,
+            Tb64Error::InvalidTagChar { .. } => Tb64Status::InvalidTagChar,
+            Tb64Error::MissingDelimiter => Tb64Status::MissingDelimiter,
+            Tb64Error::MissingChecksum => Tb64Status::MissingChecksum,
+            Tb64Error::Base64 { .. } => Tb64Status::Base64,
+            Tb64Error::InvalidChecksum => Tb64Status::InvalidChecksum,
+            Tb64Error::InvalidData => Tb64Status::InvalidData,
+            Tb64Error::NonAsciiTag => Tb64Status::NonAsciiTag,
+            Tb64Error::TagNotAllowed { .. } => Tb64Status::TagNotAllowed,
+            Tb64Error::InvalidLength => Tb64Status::InvalidLength,
+            Tb64Error::EmptyValue => Tb64Status::EmptyValue,
+            Tb64Error::EmptyTag => Tb64Status::EmptyTag,
+            Tb64Error::UnsupportedVersion { .. } => Tb64Status::UnsupportedVersion,
+            Tb64Error::Io { .. } => Tb64Status::Io,
+            Tb64Error::TagTooLong { .. } => Tb64Status::TagTooLong,
+            Tb64Error::ValueTooLong { .. } => Tb64Status::ValueTooLong,
+            Tb64Error::WrongChecksumKind { .. } => Tb64Status::WrongChecksumKind,
+            Tb64Error::InvalidSegment { .. } => Tb64Status::InvalidSegment,
+            Tb64Error::InvalidElement { .. } => Tb64Status::InvalidElement,
+            Tb64Error::InvalidBase32Char { .. } => Tb64Status::InvalidBase32Char,
+            Tb64Error::UnsupportedMultibase { .. } => Tb64Status::UnsupportedMultibase,
+            Tb64Error::UnknownCodec { .. } => Tb64Status::UnknownCodec,
+            Tb64Error::InvalidDelimiter { .. } => Tb64Status::InvalidDelimiter,
+            Tb64Error::EmptyTagSegment { .. } => Tb64Status::EmptyTagSegment,
+            Tb64Error::WrongValueLength { .. } => Tb64Status::WrongValueLength,
+            Tb64Error::MissingQueryParam { .. } => Tb64Status::MissingQueryParam,
+            Tb64Error::InvalidPercentEncoding { .. } => Tb64Status::InvalidPercentEncoding,
+            Tb64Error::DecodedTooLong { .. } => Tb64Status::DecodedTooLong,
+        }
+    }
+}
+
+/// Borrows a C string argument as UTF-8.
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string which outlives the
+/// result.
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, Tb64Status> {
+    if s.is_null() {
+        return Err(Tb64Status::NullPointer);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| Tb64Status::InvalidUtf8)
+}
+
+/// Stores a new value in `out`, or null on failure.
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn store(
+    result: Result<TaggedBase64, Tb64Status>,
+    out: *mut *mut TaggedBase64,
+) -> Tb64Status {
+    if out.is_null() {
+        return Tb64Status::NullPointer;
+    }
+    match result {
+        Ok(tb64) => {
+            *out = Box::into_raw(Box::new(tb64));
+            Tb64Status::Ok
+        }
+        Err(status) => {
+            *out = ptr::null_mut();
+            status
+        }
+    }
+}
+
+/// Parses a NUL-terminated string of the form tag~value, storing the value
+/// in `*out`, or null on failure.
+///
+/// The string is parsed with the limits of
+/// [parse_untrusted](TaggedBase64::parse_untrusted), so a value longer than
+/// [TB64_UNTRUSTED_MAX_DECODED_LEN](crate::TB64_UNTRUSTED_MAX_DECODED_LEN)
+/// fails with [Tb64Status::DecodedTooLong].
+///
+/// # Safety
+/// `s` must be null or a NUL-terminated string, and `out` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_parse(s: *const c_char, out: *mut *mut TaggedBase64) -> Tb64Status {
+    let result = str_arg(s).and_then(|s| Ok(TaggedBase64::parse_untrusted(s)?));
+    store(result, out)
+}
+
+/// Constructs a value from a NUL-terminated tag and `len` bytes at `value`,
+/// storing it in `*out`, or null on failure. `value` may be null if `len`
+/// is 0.
+///
+/// # Safety
+/// `tag` must be null or a NUL-terminated string, `value` must be valid for
+/// reads of `len` bytes unless `len` is 0, and `out` must be null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_new(
+    tag: *const c_char,
+    value: *const u8,
+    len: usize,
+    out: *mut *mut TaggedBase64,
+) -> Tb64Status {
+    let result = str_arg(tag).and_then(|tag| {
+        let value = match (value.is_null(), len) {
+            (_, 0) => &[][..],
+            (true, _) => return Err(Tb64Status::NullPointer),
+            (false, _) => slice::from_raw_parts(value, len),
+        };
+        Ok(TaggedBase64::new(tag, value)?)
+    });
+    store(result, out)
+}
+
+/// Formats a value as a string of the form tag~value, to be released with
+/// [tb64_string_free]. Returns null if `tb64` is null.
+///
+/// # Safety
+/// `tb64` must be null or a value returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_encode(tb64: *const TaggedBase64) -> *mut c_char {
+    match tb64.as_ref() {
+        // The string form is base64 and tag characters, never NUL.
+        Some(tb64) => CString::new(tb64.to_string()).unwrap().into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Gets the tag of a value, to be released with [tb64_string_free]. Returns
+/// null if `tb64` is null.
+///
+/// # Safety
+/// `tb64` must be null or a value returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_tag(tb64: *const TaggedBase64) -> *mut c_char {
+    match tb64.as_ref() {
+        Some(tb64) => CString::new(tb64.tag()).unwrap().into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Gets the value bytes, storing their number in `*len`. The bytes belong
+/// to `tb64` and remain valid until it is freed. Returns null, with a
+/// length of 0, if `tb64` is null.
+///
+/// # Safety
+/// `tb64` must be null or a value returned by this library and not yet
+/// freed, and `len` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_value(tb64: *const TaggedBase64, len: *mut usize) -> *const u8 {
+    let value = tb64.as_ref().map_or(&[][..], TaggedBase64::value_ref);
+    if !len.is_null() {
+        *len = value.len();
+    }
+    if tb64.is_null() {
+        ptr::null()
+    } else {
+        value.as_ptr()
+    }
+}
+
+/// Releases a value. Does nothing if `tb64` is null.
+///
+/// # Safety
+/// `tb64` must be null or a value returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_free(tb64: *mut TaggedBase64) {
+    if !tb64.is_null() {
+        drop(Box::from_raw(tb64));
+    }
+}
+
+/// Releases a string returned by this library. Does nothing if `s` is null.
+///
+/// # Safety
+/// `s` must be null or a string returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn tb64_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Describes a status as a static NUL-terminated string, which must not be
+/// freed.
+#[no_mangle]
+pub extern "C" fn tb64_status_message(status: Tb64Status) -> *const c_char {
+    let message: &CStr = match status {
+        Tb64Status::Ok => c"ok",
+        Tb64Status::NullPointer => c"null pointer argument",
+        Tb64Status::InvalidUtf8 => c"string argument is not valid UTF-8",
+        Tb64Status::InvalidTag => c"invalid tag",
+        Tb64Status::InvalidTagChar => c"invalid character in tag",
+        Tb64Status::MissingDelimiter => c"missing delimiter",
+        Tb64Status::MissingChecksum => c"missing checksum",
+        Tb64Status::Base64 => c"invalid base 64",
+        Tb64Status::InvalidChecksum => c"invalid checksum",
+        Tb64Status::InvalidData => c"invalid data",
+        Tb64Status::NonAsciiTag => c"non-ASCII tag",
+        Tb64Status::TagNotAllowed => c"tag not allowed",
+        Tb64Status::InvalidLength => c"invalid length",
+        Tb64Status::EmptyValue => c"empty value",
+        Tb64Status::EmptyTag => c"empty tag",
+        Tb64Status::UnsupportedVersion => c"unsupported format version",
+        Tb64Status::Io => c"read failed",
+        Tb64Status::TagTooLong => c"tag too long",
+        Tb64Status::ValueTooLong => c"value too long",
+        Tb64Status::WrongChecksumKind => c"wrong checksum kind",
+        Tb64Status::InvalidSegment => c"invalid segment",
+        Tb64Status::InvalidElement => c"invalid element",
+        Tb64Status::InvalidBase32Char => c"invalid base 32 character",
+        Tb64Status::UnsupportedMultibase => c"unsupported multibase encoding",
+        Tb64Status::UnknownCodec => c"unknown multicodec code",
+        Tb64Status::InvalidDelimiter => c"invalid delimiter",
+        Tb64Status::EmptyTagSegment => c"empty segment in tag",
+        Tb64Status::WrongValueLength => c"wrong value length",
+        Tb64Status::MissingQueryParam => c"missing query parameter",
+        Tb64Status::InvalidPercentEncoding => c"invalid percent-encoding",
+        Tb64Status::DecodedTooLong => c"value longer than the limit",
+    };
+    message.as_ptr()
+}
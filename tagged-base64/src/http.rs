@@ -0,0 +1,49 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Rocket support, so values can be used directly as path segments, query
+//! parameters, and form fields, and in `uri!`.
+//!
+//! A segment or field which does not parse fails to match, like any other
+//! parameter of the wrong type; Rocket responds with 422 if no other route
+//! matches. To handle the error in the route instead, declare the parameter
+//! as `Result<TaggedBase64, Tb64Error>`. Types declared with the
+//! [macro@crate::tagged] macro get the same implementations when the
+//! `rocket` feature is enabled.
+//!
+//! Parameters come from clients, so they are parsed with
+//! [parse_untrusted](TaggedBase64::parse_untrusted), and values longer
+//! than [TB64_UNTRUSTED_MAX_DECODED_LEN](crate::TB64_UNTRUSTED_MAX_DECODED_LEN)
+//! fail to match.
+
+use crate::{TaggedBase64, Tb64Error};
+use core::fmt;
+use rocket::{
+    form::{self, FromFormField, ValueField},
+    http::uri::fmt::{Formatter, Part, UriDisplay},
+    request::FromParam,
+};
+use std::string::ToString;
+
+impl<'a> FromParam<'a> for TaggedBase64 {
+    type Error = Tb64Error;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        TaggedBase64::parse_untrusted(param)
+    }
+}
+
+#[rocket::async_trait]
+impl<'v> FromFormField<'v> for TaggedBase64 {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        TaggedBase64::parse_untrusted(field.value)
+            .map_err(|err| form::Error::validation(err.to_string()).into())
+    }
+}
+
+impl<P: Part> UriDisplay<P> for TaggedBase64 {
+    fn fmt(&self, f: &mut Formatter<'_, P>) -> fmt::Result {
+        f.write_value(self.to_string())
+    }
+}
+
+rocket::http::impl_from_uri_param_identity!(TaggedBase64);
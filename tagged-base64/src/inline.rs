@@ -0,0 +1,341 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Storage for the tag and value of a [TaggedBase64](crate::TaggedBase64)
+//! which keeps short ones inline.
+//!
+//! Most values are hashes or keys of at most 64 bytes, with tags of a few
+//! characters, so constructing and cloning these allocates nothing. Longer
+//! tags and values are kept on the heap as usual.
+
+#[cfg(feature = "ark-serialize")]
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::{string::String, vec::Vec};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+#[cfg(feature = "rkyv")]
+use rkyv::{
+    rancor::{Fallible, Source},
+    ser::{Allocator, Writer},
+    string::{ArchivedString, StringResolver},
+    traits::SerializeUnsized,
+    vec::{ArchivedVec, VecResolver},
+    Archive, Place, Serialize,
+};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Longest value kept inline.
+pub(crate) const INLINE_VALUE_LEN: usize = 64;
+
+/// Longest tag kept inline.
+pub(crate) const INLINE_TAG_LEN: usize = 22;
+
+/// Storage for values.
+pub(crate) type InlineValue = InlineBytes<INLINE_VALUE_LEN>;
+
+/// Bytes stored inline when there are at most `N` of them.
+#[derive(Clone)]
+pub(crate) enum InlineBytes<const N: usize> {
+    Inline { len: u8, buf: [u8; N] },
+    Heap(Vec<u8>),
+}
+
+impl<const N: usize> InlineBytes<N> {
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        match self {
+            InlineBytes::Inline { len, buf } => &buf[..*len as usize],
+            InlineBytes::Heap(v) => v,
+        }
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<u8> {
+        match self {
+            InlineBytes::Inline { .. } => self.as_slice().to_vec(),
+            InlineBytes::Heap(v) => v,
+        }
+    }
+}
+
+impl<const N: usize> Default for InlineBytes<N> {
+    fn default() -> Self {
+        InlineBytes::Inline {
+            len: 0,
+            buf: [0; N],
+        }
+    }
+}
+
+impl<const N: usize> Deref for InlineBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for InlineBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> From<&[u8]> for InlineBytes<N> {
+    fn from(bytes: &[u8]) -> Self {
+        if bytes.len() > N {
+            return InlineBytes::Heap(bytes.to_vec());
+        }
+        let mut buf = [0; N];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        InlineBytes::Inline {
+            len: bytes.len() as u8,
+            buf,
+        }
+    }
+}
+
+/// Moves short vectors inline, so that clones do not allocate.
+impl<const N: usize> From<Vec<u8>> for InlineBytes<N> {
+    fn from(bytes: Vec<u8>) -> Self {
+        if bytes.len() > N {
+            InlineBytes::Heap(bytes)
+        } else {
+            InlineBytes::from(bytes.as_slice())
+        }
+    }
+}
+
+impl<const N: usize> PartialEq for InlineBytes<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<const N: usize> Eq for InlineBytes<N> {}
+
+impl<const N: usize> PartialOrd for InlineBytes<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const N: usize> Ord for InlineBytes<N> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<const N: usize> Hash for InlineBytes<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(self.as_slice(), state)
+    }
+}
+
+impl<const N: usize> fmt::Debug for InlineBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+/// Wipes the whole inline buffer, not only the bytes in use.
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Zeroize for InlineBytes<N> {
+    fn zeroize(&mut self) {
+        match self {
+            InlineBytes::Inline { len, buf } => {
+                len.zeroize();
+                buf.zeroize();
+            }
+            InlineBytes::Heap(v) => v.zeroize(),
+        }
+    }
+}
+
+/// Serialized exactly as a `Vec<u8>`.
+#[cfg(feature = "ark-serialize")]
+impl<const N: usize> CanonicalSerialize for InlineBytes<N> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.as_slice().serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.as_slice().serialized_size(compress)
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl<const N: usize> Valid for InlineBytes<N> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl<const N: usize> CanonicalDeserialize for InlineBytes<N> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Vec::<u8>::deserialize_with_mode(reader, compress, validate).map(Self::from)
+    }
+}
+
+/// Archived exactly as a `Vec<u8>`.
+#[cfg(feature = "rkyv")]
+impl<const N: usize> Archive for InlineBytes<N> {
+    type Archived = ArchivedVec<u8>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_slice(self.as_slice(), resolver, out);
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<S, const N: usize> Serialize<S> for InlineBytes<N>
+where
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedVec::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+/// A string stored inline when it is at most [INLINE_TAG_LEN] bytes.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct InlineTag(InlineBytes<INLINE_TAG_LEN>);
+
+impl InlineTag {
+    pub(crate) fn as_str(&self) -> &str {
+        // Only ever constructed from strings.
+        core::str::from_utf8(self.0.as_slice()).unwrap()
+    }
+
+    pub(crate) fn into_string(self) -> String {
+        // Only ever constructed from strings.
+        String::from_utf8(self.0.into_vec()).unwrap()
+    }
+}
+
+impl Deref for InlineTag {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for InlineTag {
+    fn from(s: &str) -> Self {
+        InlineTag(s.as_bytes().into())
+    }
+}
+
+impl From<String> for InlineTag {
+    fn from(s: String) -> Self {
+        InlineTag(s.into_bytes().into())
+    }
+}
+
+impl PartialEq<str> for InlineTag {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<String> for InlineTag {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for InlineTag {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for InlineTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for InlineTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for InlineTag {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+/// Serialized exactly as a `String`.
+#[cfg(feature = "ark-serialize")]
+impl CanonicalSerialize for InlineTag {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.0.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.0.serialized_size(compress)
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl Valid for InlineTag {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl CanonicalDeserialize for InlineTag {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        String::deserialize_with_mode(reader, compress, validate).map(Self::from)
+    }
+}
+
+/// Archived exactly as a `String`.
+#[cfg(feature = "rkyv")]
+impl Archive for InlineTag {
+    type Archived = ArchivedString;
+    type Resolver = StringResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedString::resolve_from_str(self.as_str(), resolver, out);
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<S: Fallible + ?Sized> Serialize<S> for InlineTag
+where
+    S::Error: Source,
+    str: SerializeUnsized<S>,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedString::serialize_from_str(self.as_str(), serializer)
+    }
+}
@@ -26,7 +26,8 @@
 //!
 //! Note: It is allowed for the tag to be the empty string. The base64
 //! portion cannot be empty; at a minimum, it will encode a single
-//! byte checksum.
+//! byte checksum. For an empty value, that byte is
+//! [TaggedBase64::tag_checksum] of the tag.
 //!
 //! The tag and delimiter help to avoid problems with binary values
 //! that happen to parse as numbers. Large binary values don't fit
@@ -39,35 +40,132 @@
 //! which is corruption.  Therefore, values are encoded in base64 to
 //! allow safe transit to- and from JavaScript, including in URLs, as
 //! well as display and input in a user interface.
+//!
+//! The crate is `no_std` and only requires `alloc`. The default `std`
+//! feature enables the standard library support of its dependencies. To
+//! use the crate without it, disable default features and build for a
+//! target without a standard library, such as `thumbv7em-none-eabi`.
+//!
+//! The core API is plain Rust and reports errors as [Tb64Error]. The
+//! JavaScript bindings, `JsTaggedBase64` and the `#[wasm_bindgen]` exports,
+//! are only built for `wasm32` targets with the `wasm-bindgen` feature, so
+//! native builds pull in no WASM dependencies.
 
 #![no_std]
 #![allow(clippy::unused_unit)]
+#[cfg(feature = "std")]
+extern crate std;
 #[cfg(feature = "ark-serialize")]
 use ark_serialize::*;
+#[cfg(feature = "schemars")]
+use ark_std::vec;
 use base64::{
-    alphabet::URL_SAFE,
-    engine::{general_purpose::NO_PAD, Engine, GeneralPurpose},
+    alphabet::{Alphabet, URL_SAFE},
+    engine::{
+        general_purpose::{NO_PAD, STANDARD},
+        DecodePaddingMode, Engine, GeneralPurpose, GeneralPurposeConfig,
+    },
 };
+use core::cmp::Ordering;
 use core::fmt;
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
 use core::fmt::Display;
+use core::fmt::Write as _;
+use core::hash::{Hash, Hasher};
+use core::mem;
+use core::ops::{Index, Range};
+use core::slice::SliceIndex;
 use core::str::FromStr;
-use crc_any::CRC;
+use inline::{InlineTag, InlineValue};
+#[cfg(feature = "schemars")]
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
 #[cfg(feature = "serde")]
 use serde::{
-    de::{Deserialize, Deserializer, Error as DeError},
-    ser::{Error as SerError, Serialize, Serializer},
+    de::{Deserialize, Deserializer, Error as DeError, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeTuple, Serializer},
 };
 use snafu::Snafu;
+#[cfg(feature = "subtle")]
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use ark_std::{
+    borrow::Cow,
+    boxed::Box,
     format,
     string::{String, ToString},
     vec::Vec,
 };
 
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
-use wasm_bindgen::prelude::*;
+use wasm_bindgen::{prelude::*, JsCast};
+
+#[cfg(feature = "quickcheck")]
+mod arbitrary;
+#[cfg(feature = "clap")]
+mod args;
+mod base32;
+mod borrowed;
+mod builder;
+mod crc;
+mod delim;
+#[cfg(feature = "axum")]
+mod extract;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "hmac")]
+mod hmac;
+#[cfg(feature = "rocket")]
+mod http;
+mod inline;
+mod multi;
+mod multibase;
+mod nested;
+#[cfg(feature = "qr")]
+mod qr;
+#[cfg(feature = "rand")]
+mod random;
+mod registry;
+mod rle;
+mod sensitive;
+#[cfg(feature = "serde")]
+pub mod serde_bytes;
+#[cfg(feature = "diesel")]
+mod sql;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+#[cfg(feature = "url")]
+pub mod urls;
+mod words;
+
+#[cfg(feature = "quickcheck")]
+pub use arbitrary::CorruptedString;
+#[cfg(feature = "clap")]
+pub use args::TaggedBase64Parser;
+pub use borrowed::TaggedBase64Ref;
+pub use builder::TaggedBase64Builder;
+pub use delim::Tb64Format;
+#[cfg(feature = "axum")]
+pub use extract::TaggedBase64Path;
+#[cfg(feature = "hmac")]
+pub use hmac::AUTH_TAG_LEN;
+pub use multi::TaggedBase64MultiChecked;
+#[cfg(feature = "rand")]
+pub use random::TaggedBase64Distribution;
+pub use registry::TagRegistry;
+pub use sensitive::SensitiveTaggedBase64;
+#[cfg(feature = "diesel")]
+pub use sql::TaggedColumns;
+#[cfg(feature = "std")]
+pub use stream::{StreamingDecoder, TaggedBase64Reader, TaggedBase64Writer};
+pub use words::CHECKSUM_WORDS;
 
 /// Derive serdes for a type which serializes as a binary blob.
 ///
@@ -80,7 +178,8 @@ use wasm_bindgen::prelude::*;
 /// readable encodings.
 ///
 /// This macro takes at least one arguments:
-/// * The first argument should be the tag, as a string literal or expression.
+/// * The first argument should be the tag, as a string literal or expression. A string literal
+///   is checked with [const_tag], so an invalid tag fails compilation.
 /// * By default, the derived implementation invokes `CanonicalSerialize` and `CanonicalDeserialize`
 ///   with `uncompressed` and `unchecked` flags.
 /// * If `compressed` and/or `checked` flags are presented, the derived implementation will behave
@@ -98,6 +197,11 @@ use wasm_bindgen::prelude::*;
 ///   blob types to be conveniently displayed and read to and from user interfaces in a manner
 ///   consistent with how they are serialized.
 ///
+/// Parsing a string or deserializing a value with a different tag fails with
+/// [InvalidTag](Tb64Error::InvalidTag), so ID types which share a
+/// representation, such as several newtypes over `Vec<u8>`, cannot be mixed
+/// up.
+///
 /// Usage example:
 ///
 /// ```
@@ -140,27 +244,339 @@ use wasm_bindgen::prelude::*;
 /// "PRIM~8oaujwbov8h4eEq7HFpqW6mIXhVbtJGxLUgiKrGpMCoJ".
 pub use tagged_base64_macros::tagged;
 
+/// Derives [AsTagged](trait@AsTagged) for a fieldless enum.
+///
+/// Every discriminant must fit in a byte, or compilation fails.
+///
+/// ```compile_fail
+/// # use tagged_base64::AsTagged;
+/// #[derive(AsTagged)]
+/// enum Status {
+///     Ok = 200,
+///     NotFound = 404,
+/// }
+/// ```
+///
+/// Variant names become tags, so they can be at most [TB64_MAX_TAG_LEN]
+/// bytes long.
+///
+/// ```compile_fail
+/// # use tagged_base64::AsTagged;
+/// #[derive(AsTagged)]
+/// enum Kind {
+///     ThisVariantNameIsFarTooLongToBeUsedAsATagBecauseTagsAreLimitedTo64Bytes,
+/// }
+/// ```
+pub use tagged_base64_macros::AsTagged;
+
+/// Checks a tag at compile time, evaluating to the tag as a `&'static str`.
+///
+/// The argument must be a constant expression. An invalid tag, as judged by
+/// [TaggedBase64::is_valid_tag], fails compilation rather than panicking at
+/// run time, so a typo in a `const` tag is caught by the build.
+///
+/// ```
+/// # use tagged_base64::{const_tag, TaggedBase64};
+/// const TX: &str = const_tag!("TX");
+/// assert_eq!(TaggedBase64::new(TX, b"payload")?.tag(), "TX");
+/// # Ok::<(), tagged_base64::Tb64Error>(())
+/// ```
+///
+/// ```compile_fail
+/// # use tagged_base64::const_tag;
+/// const TX: &str = const_tag!("T~X");
+/// ```
+#[macro_export]
+macro_rules! const_tag {
+    ($tag:expr) => {{
+        const TAG: &str = $tag;
+        const _: () = assert!($crate::TaggedBase64::is_valid_tag(TAG), "invalid tag");
+        TAG
+    }};
+}
+
 /// Separator that does not appear in URL-safe base64 encoding and can
 /// appear in URLs without percent-encoding.
 pub const TB64_DELIM: char = '~';
 
+// Parsing splits at the first delimiter, which is only correct as long as
+// neither tags nor base64 values can contain it.
+const _: () = assert!(!TaggedBase64::is_safe_base64_ascii(TB64_DELIM));
+
+/// Separator between the values of a sequence written by [encode_seq].
+///
+/// Like [TB64_DELIM], it is not a base64 character and can appear in URLs
+/// without percent-encoding, so a whole sequence fits in one query
+/// parameter. No string form of a single value uses it, so any of them can
+/// be part of a sequence.
+pub const TB64_SEQ_DELIM: char = ':';
+
+const _: () = assert!(!TaggedBase64::is_safe_base64_ascii(TB64_SEQ_DELIM));
+
+/// Separator between the namespace and the kind of a namespaced tag, such
+/// as `ESPRESSO_TX`.
+///
+/// Unlike the delimiters, it is an ordinary tag character, so namespaced
+/// tags are written in the flat format and parse everywhere flat tags do.
+pub const TB64_NAMESPACE_SEP: char = '_';
+
+const _: () = assert!(TaggedBase64::is_safe_base64_ascii(TB64_NAMESPACE_SEP));
+
+/// Longest tag accepted by default, in bytes.
+///
+/// Tags are meant to be short mnemonics, so this rejects absurd input such
+/// as a base64 blob pasted in place of a tag.
+pub const TB64_MAX_TAG_LEN: usize = 64;
+
+/// Longest value accepted by [TaggedBase64::parse_untrusted], in decoded
+/// bytes: 64 KiB, plenty for keys, hashes and signatures.
+pub const TB64_UNTRUSTED_MAX_DECODED_LEN: usize = 64 * 1024;
+
+/// Longest value [TaggedBase64::parse] expands a run-length coded or
+/// compressed string to, in bytes: 1 MiB.
+///
+/// A plain value is never longer than its string, but an expanded one can
+/// be tens or hundreds of times longer, so even trusting callers need a
+/// bound.
+pub const TB64_MAX_EXPANDED_LEN: usize = 1 << 20;
+
+/// Limits on the size of a parsed value, checked before the value is
+/// decoded, for [TaggedBase64::parse_with_limits].
+///
+/// The default limits are those of [TaggedBase64::parse_untrusted]:
+/// [TB64_MAX_TAG_LEN], and [TB64_UNTRUSTED_MAX_DECODED_LEN] for every
+/// value. Plain [parse](TaggedBase64::parse) limits the tag, and only
+/// limits run-length coded and compressed values, to
+/// [TB64_MAX_EXPANDED_LEN].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseLimits {
+    /// Longest tag accepted, in bytes.
+    pub max_tag_len: usize,
+    /// Longest value accepted, in bytes after decoding and any expansion.
+    pub max_decoded_len: usize,
+    /// Longest value accepted once a run-length coded or compressed value
+    /// is expanded, in bytes. Such a value must also be within
+    /// `max_decoded_len`.
+    pub max_expanded_len: usize,
+}
+
+impl ParseLimits {
+    /// Checks the length a value will have once expanded, before expanding
+    /// it, against both limits on the value.
+    fn check_expanded_len(&self, len: usize) -> Result<(), Tb64Error> {
+        let max = self.max_decoded_len.min(self.max_expanded_len);
+        if len > max {
+            return Err(Tb64Error::DecodedTooLong { len, max });
+        }
+        Ok(())
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_tag_len: TB64_MAX_TAG_LEN,
+            max_decoded_len: TB64_UNTRUSTED_MAX_DECODED_LEN,
+            max_expanded_len: TB64_UNTRUSTED_MAX_DECODED_LEN,
+        }
+    }
+}
+
+/// Limits applied by [TaggedBase64::parse].
+const PARSE_LIMITS: ParseLimits = ParseLimits {
+    max_tag_len: TB64_MAX_TAG_LEN,
+    max_decoded_len: usize::MAX,
+    max_expanded_len: TB64_MAX_EXPANDED_LEN,
+};
+
+/// Shortest value given a CRC-16 by [ChecksumKind::for_len].
+pub const CRC16_MIN_LEN: usize = 256;
+
+/// Shortest value given a CRC-32C by [ChecksumKind::for_len].
+pub const CRC32_MIN_LEN: usize = 4096;
+
 /// Base 64 engine configured for TaggedBase64.
 pub const BASE64: GeneralPurpose = GeneralPurpose::new(&URL_SAFE, NO_PAD);
 
+/// Length from which [TaggedBase64::encode_raw] and
+/// [TaggedBase64::decode_raw] use vectorized base64 with the `simd`
+/// feature. Below this, setting up the vectorized coder costs more than it
+/// saves.
+#[cfg(feature = "simd")]
+pub const SIMD_MIN_LEN: usize = 1024;
+
+/// The version of the base64 crate used by this crate, for configuring
+/// engines to pass to [TaggedBase64::parse_with_engine] and
+/// [TaggedBase64::to_string_with_engine].
+pub use base64;
+
+/// Offset applied to the checksum of extended values.
+///
+/// Extended values, such as timestamped ones, begin with a header byte
+/// describing the layout of the rest of the value. They are written with a
+/// doubled delimiter (`tag~~value`) and their checksum is XORed with this
+/// mask, so an extended value can never be mistaken for a plain one, either
+/// in memory or when parsed.
+const EXTENDED_CHECKSUM_MASK: u8 = 0x80;
+
+/// Version of the extended format, stored in the upper bits of the header
+/// byte so that later versions can be recognized and rejected.
+///
+/// Plain values carry no header and so no version; they remain readable
+/// whatever the version of the extended format.
+const FORMAT_VERSION: u8 = 0;
+
+/// Position of the format version within the header byte. The lower bits
+/// hold flags.
+const VERSION_SHIFT: u32 = 4;
+
+/// Header flag of an extended value whose payload is preceded by an 8-byte
+/// big-endian Unix timestamp.
+const FLAG_TIMESTAMP: u8 = 0x01;
+
+/// Header flag of an extended value which ends with a big-endian CRC-16 of
+/// the tag and the rest of the value.
+const FLAG_CRC16: u8 = 0x04;
+
+/// Header flag of an extended value which ends with a big-endian CRC-32C of
+/// the tag and the rest of the value.
+const FLAG_CRC32: u8 = 0x08;
+
+/// Header flag marking an encoded value whose bytes after the header are
+/// run-length coded. Only appears in strings produced by
+/// [TaggedBase64::to_string_compact]; parsing expands the value and clears
+/// the flag.
+const FLAG_RLE: u8 = 0x02;
+
+/// Header of an encoded value whose bytes after the header are deflated.
+/// Only appears in strings produced by [TaggedBase64::to_string_compressed];
+/// parsing inflates the value.
+///
+/// No value carries both a CRC-16 and a CRC-32C, so that combination of
+/// flags is free to mark compression. The original header, or 0 for a plain
+/// value, follows, then the length of the rest of the value as an unsigned
+/// LEB128 varint, then the raw deflate stream of the rest of the value.
+const FLAG_DEFLATE: u8 = FLAG_CRC16 | FLAG_CRC32;
+
+/// Prefix marking a value written as lowercase hex by
+/// [TaggedBase64::to_hex_string]. Like [HEX_TAG_MARKER], it cannot appear in
+/// base64. The two share a character, but one begins the tag and the other
+/// follows the delimiter, so they cannot be confused, and neither is
+/// [TB64_SEQ_DELIM].
+const HEX_VALUE_MARKER: char = '.';
+
+/// Separator between the tag and the value in the form written by
+/// [TaggedBase64::to_string_base32]. Like bech32, this uses `1`, which is not
+/// in the character set.
+const BASE32_SEPARATOR: char = '1';
+
+/// Separator after the tag and before the checksum in the form written by
+/// [TaggedBase64::to_ordered_string]. It sorts before every tag and value
+/// character, so shorter tags and values sort first.
+const ORDERED_SEPARATOR: char = '!';
+
+/// The URL-safe base64 characters in ASCII order, so that encoded values
+/// sort in the same order as the bytes.
+const ORDERED_ALPHABET: Alphabet =
+    match Alphabet::new("-0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz") {
+        Ok(alphabet) => alphabet,
+        Err(_) => panic!("invalid alphabet"),
+    };
+
+/// Base 64 engine for [TaggedBase64::to_ordered_string].
+const BASE64_ORDERED: GeneralPurpose = GeneralPurpose::new(&ORDERED_ALPHABET, NO_PAD);
+
+/// Prefix marking a tag written as lowercase hex by
+/// [TaggedBase64::to_string_hex_tag].
+const HEX_TAG_MARKER: char = '.';
+
+/// Base 64 engine which also accepts padding and non-zero trailing bits,
+/// used to recognize decodable but non-canonical encodings.
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
 /// A structure holding a string tag, vector of bytes, and a checksum
 /// covering the tag and the bytes.
+///
+/// Values are compared by tag and value bytes only. The checksum is derived
+/// from those, so a stale checksum does not make two values unequal; use
+/// [verify_checksum](TaggedBase64::verify_checksum) to check it. The one
+/// exception is that a plain value and an extended value with the same bytes
+/// are unequal, since they display differently.
+///
+/// Values are ordered by tag, then by value bytes, as by
+/// [canonical_cmp](TaggedBase64::canonical_cmp), with plain values before
+/// extended ones with the same bytes.
 #[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone)]
 #[cfg_attr(
-    feature = "ark-serialize",
-    derive(CanonicalSerialize, CanonicalDeserialize)
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Text)
 )]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize))]
 pub struct TaggedBase64 {
-    tag: String,
-    value: Vec<u8>,
+    tag: InlineTag,
+    value: InlineValue,
     checksum: u8,
+    // Whether the checksum is masked for an extended value, kept so that
+    // comparing and hashing do not recompute the checksum.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    extended: bool,
+}
+
+/// Serialized as a `(tag, value, checksum)` tuple.
+#[cfg(feature = "ark-serialize")]
+impl CanonicalSerialize for TaggedBase64 {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.tag.serialize_with_mode(&mut writer, compress)?;
+        self.value.serialize_with_mode(&mut writer, compress)?;
+        self.checksum.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.tag.serialized_size(compress)
+            + self.value.serialized_size(compress)
+            + self.checksum.serialized_size(compress)
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl Valid for TaggedBase64 {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+/// Reads the fields written by [CanonicalSerialize] without verifying the
+/// checksum; see [verify_checksum](TaggedBase64::verify_checksum).
+#[cfg(feature = "ark-serialize")]
+impl CanonicalDeserialize for TaggedBase64 {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let tag = InlineTag::deserialize_with_mode(&mut reader, compress, validate)?;
+        let value = InlineValue::deserialize_with_mode(&mut reader, compress, validate)?;
+        let checksum = u8::deserialize_with_mode(reader, compress, validate)?;
+        Ok(TaggedBase64::from_stored_checksum(tag, value, checksum))
+    }
 }
 
+/// Serializes as a string in human-readable formats such as JSON. Binary
+/// formats such as bincode get a `(tag, value, checksum)` tuple instead,
+/// with the value as raw bytes, so it is neither base64 encoded nor wrapped
+/// in another length-prefixed encoding.
 #[cfg(feature = "serde")]
 impl Serialize for TaggedBase64 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -172,14 +588,20 @@ impl Serialize for TaggedBase64 {
             // tagged base 64 as a string.
             Serialize::serialize(&self.to_string(), serializer)
         } else {
-            // For binary formats, convert to bytes (using CanonicalSerialize) and write the bytes.
-            let mut bytes = Vec::new();
-            CanonicalSerialize::serialize_compressed(self, &mut bytes).map_err(S::Error::custom)?;
-            Serialize::serialize(&bytes, serializer)
+            // The checksum costs one byte, and lets deserialization catch corruption and tell
+            // extended values from plain ones, as parsing a string does.
+            let mut tuple = serializer.serialize_tuple(3)?;
+            tuple.serialize_element(self.tag_ref())?;
+            tuple.serialize_element(&RawBytes(self.value_ref()))?;
+            tuple.serialize_element(&self.checksum)?;
+            tuple.end()
         }
     }
 }
 
+/// Reads what [Serialize] writes. Human-readable formats may also give a
+/// map such as `{"tag": "TX", "value": [102, 111, 111]}` or
+/// `{"tag": "TX", "base64": "Zm9v"}`, which has no checksum to verify.
 #[cfg(feature = "serde")]
 impl<'a> Deserialize<'a> for TaggedBase64 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -188,263 +610,3561 @@ impl<'a> Deserialize<'a> for TaggedBase64 {
     {
         if deserializer.is_human_readable() {
             // If we are deserializing a human-readable format, the serializer would have written
-            // the tagged base 64 as a string, so deserialize a string and then parse it. We need to
-            // explicitly deserialize as an owned `String` before parsing. If we just did
-            // `Self::from_str(&Deserialize::deserialize(...)?)`, the type for deserialization would
-            // be inferred as `str`, and serde would try to borrow from the input, since `str` is
-            // not a `Sized` type. Not all inputs support borrowing. For instance, this makes it
-            // impossible to deserialize from a `serde_json::Value`.
-            let s: String = Deserialize::deserialize(deserializer)?;
-            Self::from_str(&s).map_err(D::Error::custom)
+            // the tagged base 64 as a string. Other producers may write a map instead, so let the
+            // format say which it has. The visitor accepts borrowed and owned strings alike, since
+            // not all inputs support borrowing, such as a `serde_json::Value`.
+            deserializer.deserialize_any(HumanReadableVisitor)
         } else {
-            // Otherwise, this is a binary format; deserialize bytes and then convert the bytes to
-            // TaggedBase64 using CanonicalDeserialize.
-            let bytes = <Vec<u8> as Deserialize>::deserialize(deserializer)?;
-            CanonicalDeserialize::deserialize_compressed_unchecked(bytes.as_slice())
-                .map_err(D::Error::custom)
+            // Otherwise, this is a binary format, and the serializer wrote a tuple. Check the tag
+            // and checksum as parsing a string would.
+            let (tag, RawByteBuf(value), checksum): (String, RawByteBuf, u8) =
+                Deserialize::deserialize(deserializer)?;
+            TaggedBase64::validate_tag(&tag).map_err(D::Error::custom)?;
+            let tb64 = TaggedBase64::from_stored_checksum(tag.into(), value.into(), checksum);
+            if !tb64.verify_checksum() {
+                return Err(D::Error::custom(Tb64Error::InvalidChecksum));
+            }
+            Ok(tb64)
         }
     }
 }
 
-/// JavaScript-compatible wrapper for TaggedBase64
-///
-/// The primary difference is that JsTaggedBase64 returns errors
-/// of type JsValue.
-#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct JsTaggedBase64 {
-    tb64: TaggedBase64,
-}
+/// Deserializes the string form, or a map with a `tag` and either a `value`
+/// holding the bytes or a `base64` string holding their URL-safe base64
+/// encoding, as written by other producers of tagged base64 JSON. A map has
+/// no checksum; the tag is checked as [new](TaggedBase64::new) does.
+#[cfg(feature = "serde")]
+struct HumanReadableVisitor;
 
-#[derive(Debug, Snafu)]
-pub enum Tb64Error {
-    /// An invalid character was found in the tag.
-    InvalidTag,
-    /// Missing delimiter.
-    MissingDelimiter,
-    /// Missing checksum in value.
-    MissingChecksum,
-    #[snafu(display("invalid base 64: {message}"))]
-    Base64 { message: String },
-    /// The checksum was truncated or did not match.
-    InvalidChecksum,
-    /// The data did not encode the expected type.
-    InvalidData,
-}
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for HumanReadableVisitor {
+    type Value = TaggedBase64;
 
-impl From<base64::DecodeError> for Tb64Error {
-    fn from(err: base64::DecodeError) -> Self {
-        Self::Base64 {
-            message: err.to_string(),
-        }
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tagged base64 string or a map with a tag and a value or base64")
     }
-}
 
-/// Converts a TaggedBase64 value to a String.
-#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
-pub fn to_string(tb64: &TaggedBase64) -> String {
-    let value = &mut tb64.value.clone();
-    value.push(tb64.checksum);
-    format!(
-        "{}{}{}",
-        tb64.tag,
-        TB64_DELIM,
-        TaggedBase64::encode_raw(value)
-    )
-}
+    fn visit_str<E: DeError>(self, v: &str) -> Result<TaggedBase64, E> {
+        TaggedBase64::parse(v).map_err(E::custom)
+    }
 
-impl From<&TaggedBase64> for String {
-    fn from(tb64: &TaggedBase64) -> Self {
-        to_string(tb64)
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TaggedBase64, A::Error> {
+        const FIELDS: &[&str] = &["tag", "value", "base64"];
+        let mut tag: Option<String> = None;
+        let mut value: Option<Vec<u8>> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "tag" if tag.is_some() => return Err(A::Error::duplicate_field("tag")),
+                "tag" => tag = Some(map.next_value()?),
+                "value" | "base64" if value.is_some() => {
+                    return Err(A::Error::custom(
+                        "only one of value and base64 may be given",
+                    ))
+                }
+                "value" => value = Some(map.next_value::<RawByteBuf>()?.0),
+                "base64" => {
+                    let b64: String = map.next_value()?;
+                    value = Some(TaggedBase64::decode_raw(&b64).map_err(A::Error::custom)?);
+                }
+                _ => return Err(A::Error::unknown_field(&key, FIELDS)),
+            }
+        }
+        let tag = tag.ok_or_else(|| A::Error::missing_field("tag"))?;
+        let value = value.ok_or_else(|| A::Error::missing_field("value"))?;
+        TaggedBase64::new(&tag, &value).map_err(A::Error::custom)
     }
 }
 
-/// Produces the string of a TaggedBase64 value by concatenating the
-/// tag, a delimeter, and the base64 encoding of the value and
-/// checksum.
-impl fmt::Display for TaggedBase64 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", to_string(self))
+/// Bytes serialized with `serialize_bytes`, which binary formats write
+/// compactly, rather than as a sequence of integers.
+#[cfg(feature = "serde")]
+struct RawBytes<'a>(&'a [u8]);
+
+#[cfg(feature = "serde")]
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
     }
 }
 
-impl FromStr for TaggedBase64 {
-    type Err = Tb64Error;
+/// Bytes deserialized from whichever representation the format uses for
+/// [RawBytes].
+#[cfg(feature = "serde")]
+struct RawByteBuf(Vec<u8>);
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::parse(s)
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RawByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawByteBufVisitor;
+
+        impl<'de> Visitor<'de> for RawByteBufVisitor {
+            type Value = RawByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E: DeError>(self, v: &[u8]) -> Result<RawByteBuf, E> {
+                Ok(RawByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: DeError>(self, v: Vec<u8>) -> Result<RawByteBuf, E> {
+                Ok(RawByteBuf(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawByteBuf, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(RawByteBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(RawByteBufVisitor)
     }
 }
 
-/// Produces the string of a TaggedBase64 value by concatenating the
-/// tag, a delimeter, and the base64 encoding of the value and
-/// checksum.
-impl fmt::Display for JsTaggedBase64 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.tb64)
-    }
+/// Pattern matching the human-readable serialization: the tag, one or two
+/// delimiters, and the base64 value. The value always includes at least the
+/// checksum byte, which encodes to two characters.
+#[cfg(any(feature = "schemars", feature = "utoipa"))]
+fn schema_pattern() -> String {
+    format!(
+        "^[A-Za-z0-9_-]{{0,{}}}~~?[A-Za-z0-9_-]{{2,}}$",
+        TB64_MAX_TAG_LEN
+    )
 }
 
-impl PartialEq<TaggedBase64> for JsTaggedBase64 {
-    fn eq(&self, other: &TaggedBase64) -> bool {
-        self.tb64 == *other
+#[cfg(any(feature = "schemars", feature = "utoipa"))]
+const SCHEMA_FORMAT: &str = "tagged-base64";
+
+#[cfg(any(feature = "schemars", feature = "utoipa"))]
+const SCHEMA_DESCRIPTION: &str =
+    "Tagged base64: a tag, `~`, and the URL-safe base64 encoding of the value and a checksum.";
+
+#[cfg(any(feature = "schemars", feature = "utoipa"))]
+const SCHEMA_EXAMPLE: &str = "TX~Zm9vYmFy1w";
+
+/// Describes the human-readable serialization as a string with the tag, one
+/// or two delimiters, and the base64 value.
+#[cfg(feature = "schemars")]
+impl JsonSchema for TaggedBase64 {
+    fn schema_name() -> String {
+        "TaggedBase64".to_string()
     }
-}
 
-impl TaggedBase64 {
-    /// Constructs a TaggedBase64 from a tag and array of bytes. The tag
-    /// must be URL-safe (alphanumeric with hyphen and underscore). The
-    /// byte values are unconstrained.
-    ///    ```ignored
-    ///    use TaggedBase64;
-    ///    let tb64 = TaggedBase64::new("TAG-YOURE-IT", b"datadatadata");
-    ///    ```
-    pub fn new(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
-        if TaggedBase64::is_safe_base64_tag(tag) {
-            let cs = TaggedBase64::calc_checksum(tag, value);
-            Ok(TaggedBase64 {
-                tag: tag.to_string(),
-                value: value.to_vec(),
-                checksum: cs,
-            })
-        } else {
-            Err(Tb64Error::InvalidTag)
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some(SCHEMA_FORMAT.to_string()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some(schema_pattern()),
+                ..Default::default()
+            })),
+            metadata: Some(Box::new(Metadata {
+                description: Some(SCHEMA_DESCRIPTION.to_string()),
+                examples: vec![SCHEMA_EXAMPLE.into()],
+                ..Default::default()
+            })),
+            ..Default::default()
         }
+        .into()
     }
+}
 
-    /// Parses a string of the form tag~value into a TaggedBase64 value.
-    ///
-    /// The tag is restricted to URL-safe base64 ASCII characters. The tag
-    /// may be empty. The delimiter is required.
-    ///
-    /// The value is a base64-encoded string, using the URL-safe character
-    /// set, and no padding is used.
-    pub fn parse(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
-        // Would be convenient to use split_first() here. Alas, not stable yet.
-        let delim_pos = tb64.find(TB64_DELIM).ok_or(Tb64Error::MissingDelimiter)?;
-        let (tag, delim_b64) = tb64.split_at(delim_pos);
+/// Describes the human-readable serialization as a string, with the same
+/// pattern as the `JsonSchema` implementation.
+#[cfg(feature = "utoipa")]
+impl utoipa::PartialSchema for TaggedBase64 {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        use utoipa::openapi::schema::{ObjectBuilder, SchemaFormat, Type};
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .format(Some(SchemaFormat::Custom(SCHEMA_FORMAT.to_string())))
+            .pattern(Some(schema_pattern()))
+            .description(Some(SCHEMA_DESCRIPTION))
+            .examples([SCHEMA_EXAMPLE])
+            .into()
+    }
+}
 
-        if !TaggedBase64::is_safe_base64_tag(tag) {
-            return Err(Tb64Error::InvalidTag);
-        }
+#[cfg(feature = "utoipa")]
+impl utoipa::ToSchema for TaggedBase64 {}
 
-        // Remove the delimiter.
-        let mut iter = delim_b64.chars();
-        iter.next();
-        let value = iter.as_str();
-        if value.is_empty() {
-            return Err(Tb64Error::MissingChecksum);
+/// Exposes values as a GraphQL scalar holding the string form. Strings
+/// which do not parse are rejected as input errors, with the [Tb64Error] as
+/// the message.
+#[cfg(feature = "async-graphql")]
+#[async_graphql::Scalar(name = "TaggedBase64")]
+impl async_graphql::ScalarType for TaggedBase64 {
+    fn parse(value: async_graphql::Value) -> async_graphql::InputValueResult<Self> {
+        match &value {
+            async_graphql::Value::String(s) => Ok(TaggedBase64::parse(s)?),
+            _ => Err(async_graphql::InputValueError::expected_type(value)),
         }
+    }
 
-        // Note: 'printf' debugging is possible like this:
-        //    use web_sys;
-        //    web_sys::console::log_1(&format!("+ {}", &tb64).into());
+    fn to_value(&self) -> async_graphql::Value {
+        async_graphql::Value::String(self.to_string())
+    }
+}
 
-        // Base64 decode the value.
-        let bytes = TaggedBase64::decode_raw(value)?;
-        let penultimate = bytes.len() - 1;
-        let cs = bytes[penultimate];
-        if cs == TaggedBase64::calc_checksum(tag, &bytes[..penultimate]) {
-            Ok(TaggedBase64 {
-                tag: tag.to_string(),
-                value: bytes[..penultimate].to_vec(),
-                checksum: cs,
-            })
-        } else {
-            Err(Tb64Error::InvalidChecksum)
-        }
+/// Stores values in text columns, in the string form.
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> sqlx::Type<DB> for TaggedBase64
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
     }
 
-    fn calc_checksum(tag: &str, value: &[u8]) -> u8 {
-        let mut crc8 = CRC::crc8();
-        crc8.digest(&tag);
-        crc8.digest(&value);
-        (crc8.get_crc() as u8) ^ (value.len() as u8)
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
     }
+}
 
-    /// Returns true for characters permitted in URL-safe base64 encoding,
-    /// and false otherwise.
-    pub fn is_safe_base64_ascii(c: char) -> bool {
-        c.is_ascii_alphanumeric() || (c == '-') || (c == '_')
+#[cfg(feature = "sqlx")]
+impl<'q, DB: sqlx::Database> sqlx::Encode<'q, DB> for TaggedBase64
+where
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut DB::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.to_string().encode(buf)
     }
+}
 
-    /// Checks that an ASCII byte is safe for use in the tag of a
-    /// TaggedBase64. Because the tags are merely intended to be mnemonic,
-    /// there's no need to support a large and visually ambiguous
-    /// character set.
-    pub fn is_safe_base64_tag(tag: &str) -> bool {
-        tag.chars().all(TaggedBase64::is_safe_base64_ascii)
+/// Parses the text of a column with
+/// [parse_untrusted](TaggedBase64::parse_untrusted), failing if it is not a
+/// valid value or is too long.
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for TaggedBase64
+where
+    &'r str: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<DB>>::decode(value)?;
+        Ok(TaggedBase64::parse_untrusted(s)?)
     }
+}
 
-    /// Gets the tag of a TaggedBase64 instance.
-    pub fn tag(&self) -> String {
-        self.tag.clone()
+/// Writes the tag as a string, the value as bytes, and whether the value is
+/// extended, as a `bool`. The checksum is not written.
+#[cfg(feature = "borsh")]
+impl borsh::BorshSerialize for TaggedBase64 {
+    fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
+        borsh::BorshSerialize::serialize(self.tag_ref(), writer)?;
+        borsh::BorshSerialize::serialize(self.value_ref(), writer)?;
+        borsh::BorshSerialize::serialize(&self.is_extended(), writer)
     }
+}
 
-    /// Sets the tag of a TaggedBase64 instance.
-    pub fn set_tag(&mut self, tag: &str) {
-        assert!(TaggedBase64::is_safe_base64_tag(tag));
-        self.tag = tag.to_string();
-        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+/// Reads the fields written by [BorshSerialize](borsh::BorshSerialize) and
+/// recomputes the checksum, failing if the tag is invalid or an extended
+/// value has an invalid header.
+#[cfg(feature = "borsh")]
+impl borsh::BorshDeserialize for TaggedBase64 {
+    fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        let tag = String::deserialize_reader(reader)?;
+        let value = Vec::<u8>::deserialize_reader(reader)?;
+        let extended = bool::deserialize_reader(reader)?;
+        let invalid = |err: Tb64Error| {
+            borsh::io::Error::new(borsh::io::ErrorKind::InvalidData, err.to_string())
+        };
+        TaggedBase64::validate_tag(&tag).map_err(invalid)?;
+        let mut checksum = TaggedBase64::calc_checksum(&tag, &value);
+        if extended {
+            TaggedBase64::check_extended_layout(&tag, &value).map_err(invalid)?;
+            checksum ^= EXTENDED_CHECKSUM_MASK;
+        }
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
     }
+}
 
-    /// Gets the value of a TaggedBase64 instance.
-    pub fn value(&self) -> Vec<u8> {
-        self.value.clone()
+#[cfg(feature = "rkyv")]
+impl ArchivedTaggedBase64 {
+    /// The tag, checked to contain only URL-safe base64 characters.
+    ///
+    /// Archives are read in place without deserializing, so the tag is
+    /// checked here rather than when the archive is accessed.
+    pub fn tag(&self) -> Result<&str, Tb64Error> {
+        let tag = self.tag.as_str();
+        TaggedBase64::validate_tag(tag)?;
+        Ok(tag)
     }
 
-    /// Sets the value of a TaggedBase64 instance.
-    pub fn set_value(&mut self, value: &[u8]) {
-        self.value = value.to_vec();
-        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+    /// The value, borrowed from the archive.
+    pub fn value(&self) -> &[u8] {
+        self.value.as_slice()
     }
 
-    /// Wraps the underlying base64 encoder.
-    // WASM doesn't support the most general type.
-    //
-    // pub fn encode_raw<T: ?Sized + AsRef<[u8]>>(input: &T) -> String;
-    pub fn encode_raw(input: &[u8]) -> String {
-        BASE64.encode(input)
+    /// The checksum as archived.
+    pub fn checksum(&self) -> u8 {
+        self.checksum
     }
-    /// Wraps the underlying base64 decoder.
-    pub fn decode_raw(value: &str) -> Result<Vec<u8>, Tb64Error> {
-        Ok(BASE64.decode(value)?)
+
+    /// Returns true if the tag is valid and the archived checksum matches
+    /// the tag and value. See [TaggedBase64::verify_checksum].
+    pub fn verify_checksum(&self) -> bool {
+        let tag = self.tag.as_str();
+        let checksum = TaggedBase64::calc_checksum(tag, self.value());
+        TaggedBase64::validate_tag(tag).is_ok()
+            && (self.checksum == checksum
+                || (self.checksum == checksum ^ EXTENDED_CHECKSUM_MASK
+                    && TaggedBase64::check_extended_layout(tag, self.value()).is_ok()))
     }
 }
 
-impl AsRef<[u8]> for TaggedBase64 {
-    fn as_ref(&self) -> &[u8] {
-        &self.value
+/// Copies an archived value out of the archive, failing if the tag is
+/// invalid or the checksum does not match.
+#[cfg(feature = "rkyv")]
+impl<D> rkyv::Deserialize<TaggedBase64, D> for ArchivedTaggedBase64
+where
+    D: rkyv::rancor::Fallible + ?Sized,
+    D::Error: rkyv::rancor::Source,
+{
+    fn deserialize(&self, _: &mut D) -> Result<TaggedBase64, D::Error> {
+        let tag = self.tag().map_err(rkyv::rancor::Source::new)?;
+        if !self.verify_checksum() {
+            return Err(rkyv::rancor::Source::new(Tb64Error::InvalidChecksum));
+        }
+        Ok(TaggedBase64::from_stored_checksum(
+            tag.into(),
+            self.value().into(),
+            self.checksum,
+        ))
     }
 }
 
-/// Converts any object that supports the Display trait to a JsValue for
-/// passing to Javascript.
+/// JavaScript-compatible wrapper for TaggedBase64
 ///
-/// Note: Type parameters aren't supported by `wasm-pack` yet so this
-/// can't be included in the TaggedBase64 type implementation.
+/// The primary difference is that JsTaggedBase64 returns errors
+/// of type JsValue.
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
-pub fn to_jsvalue<D: Display>(d: D) -> JsValue {
-    JsValue::from_str(&format!("{}", d))
+#[wasm_bindgen]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsTaggedBase64 {
+    tb64: TaggedBase64,
 }
 
-#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
-impl From<Tb64Error> for JsValue {
-    fn from(error: Tb64Error) -> JsValue {
-        to_jsvalue(format!("{}", error))
-    }
-}
+#[derive(Debug, Snafu)]
+pub enum Tb64Error {
+    /// An invalid character was found in the tag.
+    InvalidTag,
+    /// The tag contains a character which is not allowed, at the given byte
+    /// offset into the tag.
+    #[snafu(display("invalid character {character:?} at offset {offset} in tag"))]
+    InvalidTagChar { offset: usize, character: char },
+    /// Missing delimiter.
+    MissingDelimiter,
+    /// Missing checksum in value.
+    MissingChecksum,
+    /// The value was not valid base 64. The decoder's error gives the
+    /// offset and value of an invalid byte, or the problem with the length.
+    #[snafu(display("invalid base 64: {error}"))]
+    Base64 { error: base64::DecodeError },
+    /// The checksum was truncated or did not match.
+    InvalidChecksum,
+    /// The data did not encode the expected type.
+    InvalidData,
+    /// The tag contains a non-ASCII character. Tags are ASCII-only by design.
+    NonAsciiTag,
+    /// The tag is not one of the allowed tags, or is not registered in the
+    /// [TagRegistry] used to parse.
+    #[snafu(display("tag {tag:?} is not allowed"))]
+    TagNotAllowed { tag: String },
+    /// The encoded data was truncated or had an impossible length.
+    InvalidLength,
+    /// The value was empty where a non-empty value was required.
+    EmptyValue,
+    /// The tag was empty where a non-empty tag was required.
+    EmptyTag,
+    /// The value is an extended value written in a later version of the
+    /// format.
+    #[snafu(display("unsupported format version {version}"))]
+    UnsupportedVersion { version: u8 },
+    /// Reading the input failed.
+    #[snafu(display("read failed: {message}"))]
+    Io { message: String },
+    /// The tag is longer than the maximum allowed length.
+    #[snafu(display("tag of {len} bytes is too long"))]
+    TagTooLong { len: usize },
+    /// The value would encode to a string longer than the maximum allowed
+    /// length.
+    #[snafu(display("value would encode to {len} characters, more than {max}"))]
+    ValueTooLong { len: usize, max: usize },
+    /// The value is not protected by the checksum the caller required.
+    #[snafu(display("expected a {expected:?} checksum, found {found:?}"))]
+    WrongChecksumKind {
+        expected: ChecksumKind,
+        found: ChecksumKind,
+    },
+    /// A segment of a [TaggedBase64MultiChecked] value was not valid base 64
+    /// or failed its checksum.
+    #[snafu(display("invalid segment {index}"))]
+    InvalidSegment { index: usize },
+    /// A value in a sequence read by [decode_seq] failed to parse.
+    #[snafu(display("invalid element {index}: {error}"))]
+    InvalidElement { index: usize, error: Box<Tb64Error> },
+    /// The base32 form contains a character outside the bech32 character
+    /// set, or mixes uppercase and lowercase.
+    #[snafu(display("invalid base 32 character {character:?} at offset {offset}"))]
+    InvalidBase32Char { offset: usize, character: char },
+    /// A multibase string uses an encoding which is not supported.
+    #[snafu(display("unsupported multibase encoding {prefix:?}"))]
+    UnsupportedMultibase { prefix: char },
+    /// A multicodec code has no tag in the caller's table.
+    #[snafu(display("unknown multicodec code {code:#x}"))]
+    UnknownCodec { code: u64 },
+    /// The character cannot be used as a delimiter, because it may appear
+    /// in the tag or value, or is not ASCII punctuation.
+    #[snafu(display("invalid delimiter {delim:?}"))]
+    InvalidDelimiter { delim: char },
+    /// A namespaced tag has an empty segment beginning at the given byte
+    /// offset.
+    #[snafu(display("empty segment at offset {offset} in tag"))]
+    EmptyTagSegment { offset: usize },
+    /// The payload does not have the length registered for its tag.
+    #[snafu(display("expected a value of {expected} bytes, found {found}"))]
+    WrongValueLength { expected: usize, found: usize },
+    /// A URL has no query parameter with the given name.
+    #[snafu(display("missing query parameter {key:?}"))]
+    MissingQueryParam { key: String },
+    /// A percent-encoded escape at the given byte offset is malformed, or
+    /// encodes a character which can't appear in a valid string.
+    #[snafu(display("invalid percent-encoding at offset {offset}"))]
+    InvalidPercentEncoding { offset: usize },
+    /// The value decodes to more bytes than the limit it was parsed with.
+    #[snafu(display("value of {len} bytes is longer than the limit of {max}"))]
+    DecodedTooLong { len: usize, max: usize },
+}
+
+/// The most likely reason a string failed to parse, as determined by
+/// [TaggedBase64::classify_failure].
+///
+/// Positions are byte offsets into the whole string, suitable for pointing
+/// the user at the offending character.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailureClass {
+    /// There is nothing after the delimiter, not even a checksum.
+    Empty,
+    /// There is no delimiter separating the tag from the value.
+    MissingDelimiter,
+    /// The tag contains a character which is not allowed.
+    BadTagChar(usize, char),
+    /// The value contains a byte which is not valid base64.
+    BadValueChar(usize, u8),
+    /// The value has a length which no base64 encoding can have, which
+    /// usually means it was truncated.
+    WrongLength,
+    /// The value decoded, but the checksum did not match, so the tag or the
+    /// value was altered.
+    ChecksumMismatch,
+    /// The value is an extended value with a header this version does not
+    /// understand.
+    UnsupportedFormat,
+}
+
+/// Integrity check used by [TaggedBase64::new_with_checksum] and expected
+/// by [TaggedBase64::parse_with_checksum].
+///
+/// Every value ends with a CRC-8 byte, which misses about one random
+/// corruption in 256. The stronger checks add a CRC-16 or CRC-32C to the
+/// value, recorded in an extended header so the decoder knows to verify it.
+///
+/// [None](Self::None) is for interoperating with plain base64: strings
+/// written by
+/// [to_string_without_checksum](TaggedBase64::to_string_without_checksum),
+/// with no checksum byte after the value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ChecksumKind {
+    /// No checksum in the string form. In memory, the value still has its
+    /// CRC-8 byte, so it can be written in the usual form too.
+    None,
+    /// Only the CRC-8 byte every value carries.
+    #[default]
+    Crc8,
+    /// A CRC-16/ARC in addition to the CRC-8 byte.
+    Crc16,
+    /// A CRC-32C (Castagnoli) in addition to the CRC-8 byte.
+    Crc32c,
+}
+
+/// Version of the wire format of a value, as reported by
+/// [TaggedBase64::format_version].
+///
+/// Parsing accepts every version listed here, so callers only need this to
+/// tell which form a string was written in, for instance to keep writing
+/// the form a peer understands.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum FormatVersion {
+    /// The original format, `tag~value`, protected by the CRC-8 byte alone.
+    V1,
+    /// The extended format, `tag~~value`, whose value begins with a header
+    /// byte. The upper bits of the header hold the revision of the format,
+    /// currently 0; a later revision is rejected with
+    /// [UnsupportedVersion](Tb64Error::UnsupportedVersion). The lower bits
+    /// flag optional fields: a timestamp, a stronger checksum, or
+    /// run-length coding.
+    V2,
+}
+
+impl FormatVersion {
+    /// Gets the version as a number: 1 for [V1](Self::V1) and 2 for
+    /// [V2](Self::V2).
+    pub fn number(self) -> u8 {
+        match self {
+            FormatVersion::V1 => 1,
+            FormatVersion::V2 => 2,
+        }
+    }
+}
+
+impl ChecksumKind {
+    /// Chooses a checksum whose strength grows with the length of the
+    /// value, as used by
+    /// [new_with_sized_checksum](TaggedBase64::new_with_sized_checksum).
+    ///
+    /// Values shorter than [CRC16_MIN_LEN] keep the CRC-8 byte alone, so
+    /// short identifiers stay short and in the original format. Longer
+    /// values get a CRC-16, and values of [CRC32_MIN_LEN] bytes or more a
+    /// CRC-32C.
+    pub fn for_len(len: usize) -> ChecksumKind {
+        if len >= CRC32_MIN_LEN {
+            ChecksumKind::Crc32c
+        } else if len >= CRC16_MIN_LEN {
+            ChecksumKind::Crc16
+        } else {
+            ChecksumKind::Crc8
+        }
+    }
+}
+
+impl Tb64Error {
+    /// Gets a stable code naming the kind of error, such as
+    /// `"INVALID_CHECKSUM"`, so programs can tell errors apart without
+    /// matching on messages, which may change.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Tb64Error::InvalidTag => "INVALID_TAG",
+            Tb64Error::InvalidTagChar { .. } => "INVALID_TAG_CHAR",
+            Tb64Error::MissingDelimiter => "MISSING_DELIMITER",
+            Tb64Error::MissingChecksum => "MISSING_CHECKSUM",
+            Tb64Error::Base64 { .. } => "BASE64",
+            Tb64Error::InvalidChecksum => "INVALID_CHECKSUM",
+            Tb64Error::InvalidData => "INVALID_DATA",
+            Tb64Error::NonAsciiTag => "NON_ASCII_TAG",
+            Tb64Error::TagNotAllowed { .. } => "TAG_NOT_ALLOWED",
+            Tb64Error::InvalidLength => "INVALID_LENGTH",
+            Tb64Error::EmptyValue => "EMPTY_VALUE",
+            Tb64Error::EmptyTag => "EMPTY_TAG",
+            Tb64Error::UnsupportedVersion { .. } => "UNSUPPORTED_VERSION",
+            Tb64Error::Io { .. } => "IO",
+            Tb64Error::TagTooLong { .. } => "TAG_TOO_LONG",
+            Tb64Error::ValueTooLong { .. } => "VALUE_TOO_LONG",
+            Tb64Error::WrongChecksumKind { .. } => "WRONG_CHECKSUM_KIND",
+            Tb64Error::InvalidSegment { .. } => "INVALID_SEGMENT",
+            Tb64Error::InvalidElement { .. } => "INVALID_ELEMENT",
+            Tb64Error::InvalidBase32Char { .. } => "INVALID_BASE32_CHAR",
+            Tb64Error::UnsupportedMultibase { .. } => "UNSUPPORTED_MULTIBASE",
+            Tb64Error::UnknownCodec { .. } => "UNKNOWN_CODEC",
+            Tb64Error::InvalidDelimiter { .. } => "INVALID_DELIMITER",
+            Tb64Error::EmptyTagSegment { .. } => "EMPTY_TAG_SEGMENT",
+            Tb64Error::WrongValueLength { .. } => "WRONG_VALUE_LENGTH",
+            Tb64Error::MissingQueryParam { .. } => "MISSING_QUERY_PARAM",
+            Tb64Error::InvalidPercentEncoding { .. } => "INVALID_PERCENT_ENCODING",
+            Tb64Error::DecodedTooLong { .. } => "DECODED_TOO_LONG",
+        }
+    }
+
+    /// Gets the byte offset of the offending character, if the error
+    /// points at one.
+    ///
+    /// The offset is relative to the part of the string the error is
+    /// about: the tag for [InvalidTagChar](Tb64Error::InvalidTagChar) and
+    /// [EmptyTagSegment](Tb64Error::EmptyTagSegment), the encoded value
+    /// for [Base64](Tb64Error::Base64), and the whole string for
+    /// [InvalidPercentEncoding](Tb64Error::InvalidPercentEncoding).
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Tb64Error::InvalidTagChar { offset, .. }
+            | Tb64Error::InvalidBase32Char { offset, .. }
+            | Tb64Error::EmptyTagSegment { offset }
+            | Tb64Error::InvalidPercentEncoding { offset } => Some(*offset),
+            Tb64Error::Base64 { error } => match error {
+                base64::DecodeError::InvalidByte(offset, _)
+                | base64::DecodeError::InvalidLastSymbol(offset, _)
+                | base64::DecodeError::InvalidLength(offset) => Some(*offset),
+                base64::DecodeError::InvalidPadding => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Gets the offending character, if the error points at one.
+    pub fn character(&self) -> Option<char> {
+        match self {
+            Tb64Error::InvalidTagChar { character, .. }
+            | Tb64Error::InvalidBase32Char { character, .. } => Some(*character),
+            Tb64Error::InvalidDelimiter { delim } => Some(*delim),
+            Tb64Error::UnsupportedMultibase { prefix } => Some(*prefix),
+            Tb64Error::Base64 {
+                error:
+                    base64::DecodeError::InvalidByte(_, byte)
+                    | base64::DecodeError::InvalidLastSymbol(_, byte),
+            } => Some(*byte as char),
+            _ => None,
+        }
+    }
+}
+
+impl From<base64::DecodeError> for Tb64Error {
+    fn from(err: base64::DecodeError) -> Self {
+        Self::Base64 { error: err }
+    }
+}
+
+/// Matches `text` against a glob `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen in the pattern, and the position in the
+    // text it is currently assumed to match up to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` absorb one more character and retry.
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Appends `n` as an unsigned LEB128 varint.
+pub(crate) fn push_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Reads an unsigned LEB128 varint from the start of `bytes`, returning it
+/// and the number of bytes it took. Truncated, overlong, and overflowing
+/// varints are rejected with [InvalidLength](Tb64Error::InvalidLength).
+pub(crate) fn read_varint(bytes: &[u8]) -> Result<(usize, usize), Tb64Error> {
+    let mut n: usize = 0;
+    let mut len = 0;
+    loop {
+        let b = *bytes.get(len).ok_or(Tb64Error::InvalidLength)?;
+        let shift = 7 * len as u32;
+        let bits = usize::from(b & 0x7f);
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            return Err(Tb64Error::InvalidLength);
+        }
+        n |= bits << shift;
+        len += 1;
+        if b & 0x80 == 0 {
+            if b == 0 && len > 1 {
+                return Err(Tb64Error::InvalidLength);
+            }
+            return Ok((n, len));
+        }
+    }
+}
+
+/// Appends the lowercase hex encoding of `bytes` to `out`.
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        // Writing to a String cannot fail.
+        write!(out, "{:02x}", b).unwrap();
+    }
+}
+
+/// Decodes hex digits of either case.
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Tb64Error> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(Tb64Error::InvalidLength);
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            // The string is ASCII, so every pair is a valid str.
+            let pair = core::str::from_utf8(pair).unwrap();
+            u8::from_str_radix(pair, 16).map_err(|_| Tb64Error::InvalidData)
+        })
+        .collect()
+}
+
+/// Returns the length of the CRC announced by an extended header.
+fn crc_len(header: u8) -> usize {
+    if header & FLAG_CRC32 != 0 {
+        4
+    } else if header & FLAG_CRC16 != 0 {
+        2
+    } else {
+        0
+    }
+}
+
+/// Checks the format version in the upper bits of an extended header.
+fn check_version(header: u8) -> Result<(), Tb64Error> {
+    match header >> VERSION_SHIFT {
+        FORMAT_VERSION => Ok(()),
+        version => Err(Tb64Error::UnsupportedVersion { version }),
+    }
+}
+
+/// Returns the lengths of the fields an extended header announces before
+/// the payload, including the header itself, and after it, or `None` if the
+/// header is not understood.
+fn extended_fields(header: u8) -> Option<(usize, usize)> {
+    let crc_flags = FLAG_CRC16 | FLAG_CRC32;
+    if header == 0 || header & !(FLAG_TIMESTAMP | crc_flags) != 0 || header & crc_flags == crc_flags
+    {
+        return None;
+    }
+    let prefix = if header & FLAG_TIMESTAMP != 0 { 9 } else { 1 };
+    Some((prefix, crc_len(header)))
+}
+
+/// Converts a TaggedBase64 value to a String.
+#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
+pub fn to_string(tb64: &TaggedBase64) -> String {
+    let mut s = String::with_capacity(tb64.display_len());
+    // Writing to a String cannot fail.
+    tb64.encode_to(&mut s).unwrap();
+    s
+}
+
+/// Encodes a value with a checksum, like a TaggedBase64 with an empty tag
+/// but without the delimiter.
+///
+/// The result is URL-safe base64 without padding, suitable for compact
+/// tokens where a tag adds nothing.
+pub fn encode_checksummed(value: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(value.len() + 1);
+    bytes.extend_from_slice(value);
+    bytes.push(TaggedBase64::calc_checksum("", value));
+    BASE64.encode(bytes)
+}
+
+/// Decodes a string produced by [encode_checksummed], verifying the
+/// checksum.
+pub fn decode_checksummed(s: &str) -> Result<Vec<u8>, Tb64Error> {
+    let mut bytes = BASE64.decode(s)?;
+    let cs = bytes.pop().ok_or(Tb64Error::MissingChecksum)?;
+    if cs != TaggedBase64::calc_checksum("", &bytes) {
+        return Err(Tb64Error::InvalidChecksum);
+    }
+    Ok(bytes)
+}
+
+/// Writes a sequence of values as a single string, separated by
+/// [TB64_SEQ_DELIM].
+///
+/// For instance, two values are written as `tag1~value1:tag2~value2`. An
+/// empty sequence is written as the empty string.
+pub fn encode_seq(values: &[TaggedBase64]) -> String {
+    let mut s = String::with_capacity(values.iter().map(|v| v.display_len() + 1).sum());
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            s.push(TB64_SEQ_DELIM);
+        }
+        // Writing to a String cannot fail.
+        value.encode_to(&mut s).unwrap();
+    }
+    s
+}
+
+/// Parses a sequence of values written by [encode_seq].
+///
+/// A value which fails to parse is reported as
+/// [InvalidElement](Tb64Error::InvalidElement) with its zero-based index.
+/// Each value may be in any form [parse_any](TaggedBase64::parse_any)
+/// accepts, such as one written in hex by
+/// [to_hex_string](TaggedBase64::to_hex_string) or with a tag written in hex
+/// by [to_string_hex_tag](TaggedBase64::to_string_hex_tag).
+pub fn decode_seq(s: &str) -> Result<Vec<TaggedBase64>, Tb64Error> {
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    s.split(TB64_SEQ_DELIM)
+        .enumerate()
+        .map(|(index, value)| {
+            TaggedBase64::parse_any(value).map_err(|error| Tb64Error::InvalidElement {
+                index,
+                error: Box::new(error),
+            })
+        })
+        .collect()
+}
+
+impl From<&TaggedBase64> for String {
+    fn from(tb64: &TaggedBase64) -> Self {
+        to_string(tb64)
+    }
+}
+
+/// Wipes the tag, value, and checksum.
+///
+/// No encoding of the value is cached, and the temporary buffers used while
+/// formatting are wiped as well. Strings produced from the value, by
+/// [to_string] or `Display`, belong to the caller and are not wiped; use
+/// [zeroize::Zeroizing] to wipe them.
+#[cfg(feature = "zeroize")]
+impl Zeroize for TaggedBase64 {
+    fn zeroize(&mut self) {
+        self.tag.zeroize();
+        self.value.zeroize();
+        self.checksum.zeroize();
+        self.extended.zeroize();
+    }
+}
+
+/// Wipes the value when it is dropped, for values holding secret material.
+#[cfg(feature = "zeroize")]
+impl Drop for TaggedBase64 {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for TaggedBase64 {}
+
+/// Produces the same string as the borrowed conversion.
+impl From<TaggedBase64> for String {
+    fn from(tb64: TaggedBase64) -> Self {
+        to_string(&tb64)
+    }
+}
+
+/// Hands back the value buffer, as [into_value](TaggedBase64::into_value)
+/// does.
+impl From<TaggedBase64> for Vec<u8> {
+    fn from(tb64: TaggedBase64) -> Self {
+        tb64.into_value()
+    }
+}
+
+/// Shows the tag, the length of the value, the checksum, and the base64
+/// encoding of the start of the value, so large values stay readable in
+/// logs. The alternate form `{:#?}` shows every byte of the value instead.
+impl fmt::Debug for TaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return f
+                .debug_struct("TaggedBase64")
+                .field("tag", &self.tag)
+                .field("value", &self.value)
+                .field("checksum", &self.checksum)
+                .finish();
+        }
+        // 12 bytes encode to 16 characters.
+        const PREVIEW_LEN: usize = 12;
+        let preview = &self.value[..self.value.len().min(PREVIEW_LEN)];
+        let ellipsis = if self.value.len() > PREVIEW_LEN {
+            "..."
+        } else {
+            ""
+        };
+        f.debug_struct("TaggedBase64")
+            .field("tag", &self.tag)
+            .field("len", &self.value.len())
+            .field("checksum", &format_args!("{:#04x}", self.checksum))
+            .field(
+                "preview",
+                &format_args!("{}{}", BASE64.encode(preview), ellipsis),
+            )
+            .finish()
+    }
+}
+
+impl PartialEq for TaggedBase64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.value == other.value && self.extended == other.extended
+    }
+}
+
+impl Eq for TaggedBase64 {}
+
+/// Compares values without branching on the contents of the tag or value,
+/// for authentication tokens and other secrets.
+///
+/// The lengths of the tags and values are not hidden. The stored checksums
+/// are compared rather than recomputed, so for values whose checksum
+/// [verifies](TaggedBase64::verify_checksum), as all values built by this
+/// crate do, the result agrees with `==`.
+/// ```
+/// # use tagged_base64::TaggedBase64;
+/// use subtle::ConstantTimeEq;
+///
+/// let token = TaggedBase64::new("TOKEN", b"secret")?;
+/// let presented: TaggedBase64 = token.to_string().parse()?;
+/// assert!(bool::from(token.ct_eq(&presented)));
+/// let guess = TaggedBase64::new("TOKEN", b"secreT")?;
+/// assert!(!bool::from(token.ct_eq(&guess)));
+/// # Ok::<(), tagged_base64::Tb64Error>(())
+/// ```
+#[cfg(feature = "subtle")]
+impl ConstantTimeEq for TaggedBase64 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.tag.as_bytes().ct_eq(other.tag.as_bytes())
+            & self.value.ct_eq(&other.value)
+            & self.checksum.ct_eq(&other.checksum)
+    }
+}
+
+impl Hash for TaggedBase64 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&self.tag, state);
+        Hash::hash(&self.value, state);
+        Hash::hash(&self.extended, state);
+    }
+}
+
+impl PartialOrd for TaggedBase64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TaggedBase64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical_cmp(other)
+            .then_with(|| self.extended.cmp(&other.extended))
+    }
+}
+
+/// Produces the string of a TaggedBase64 value by concatenating the
+/// tag, a delimeter, and the base64 encoding of the value and
+/// checksum.
+///
+/// The value is encoded in place, as by
+/// [encode_to](TaggedBase64::encode_to), so printing a large value does not
+/// copy it.
+///
+/// Width, fill, and alignment are honored, so `{:>20}` lines values up in
+/// columns; precision is ignored. The alternate form `{:#}` follows the
+/// string with the length of the value and the checksum, for instance
+/// `TX~Zm9vYmFy1w [6 bytes, checksum=0xd7]`.
+impl fmt::Display for TaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let suffix = f.alternate().then(|| {
+            format!(
+                " [{} bytes, checksum={:#04x}]",
+                self.value.len(),
+                self.checksum
+            )
+        });
+        let suffix = suffix.as_deref().unwrap_or("");
+        let Some(width) = f.width() else {
+            self.encode_to(f)?;
+            return f.write_str(suffix);
+        };
+        // Pad by hand rather than with `Formatter::pad`, which would need the
+        // whole string, and so a copy of the value, up front.
+        let padding = width.saturating_sub(self.display_len() + suffix.chars().count());
+        let (before, after) = match f.align() {
+            None | Some(fmt::Alignment::Left) => (0, padding),
+            Some(fmt::Alignment::Right) => (padding, 0),
+            Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        };
+        let fill = f.fill();
+        for _ in 0..before {
+            f.write_char(fill)?;
+        }
+        self.encode_to(f)?;
+        f.write_str(suffix)?;
+        for _ in 0..after {
+            f.write_char(fill)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indexes the bytes of the value, as [value_ref](TaggedBase64::value_ref)
+/// would.
+impl<I: SliceIndex<[u8]>> Index<I> for TaggedBase64 {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.value[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a TaggedBase64 {
+    type Item = &'a u8;
+    type IntoIter = core::slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Parses a string as by [TaggedBase64::parse], so that
+/// `"TX~...".parse::<TaggedBase64>()` works without the WASM bindings.
+impl FromStr for TaggedBase64 {
+    type Err = Tb64Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Copies a value of exactly `N` bytes, failing with
+/// [InvalidLength](Tb64Error::InvalidLength) otherwise.
+impl<const N: usize> TryFrom<&TaggedBase64> for [u8; N] {
+    type Error = Tb64Error;
+
+    fn try_from(tb64: &TaggedBase64) -> Result<Self, Self::Error> {
+        tb64.value
+            .as_slice()
+            .try_into()
+            .map_err(|_| Tb64Error::InvalidLength)
+    }
+}
+
+impl TryFrom<&str> for TaggedBase64 {
+    type Error = Tb64Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::parse(s)
+    }
+}
+
+/// Reuses the allocation of the string for the tag.
+impl TryFrom<String> for TaggedBase64 {
+    type Error = Tb64Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse_owned(s)
+    }
+}
+
+/// Parses a borrowed string without copying it, or reuses the allocation of
+/// an owned string for the tag.
+impl TryFrom<Cow<'_, str>> for TaggedBase64 {
+    type Error = Tb64Error;
+
+    fn try_from(s: Cow<'_, str>) -> Result<Self, Self::Error> {
+        match s {
+            Cow::Borrowed(s) => Self::parse(s),
+            Cow::Owned(s) => Self::parse_owned(s),
+        }
+    }
+}
+
+/// Produces the string of a TaggedBase64 value by concatenating the
+/// tag, a delimeter, and the base64 encoding of the value and
+/// checksum.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+impl fmt::Display for JsTaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tb64)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+impl PartialEq<TaggedBase64> for JsTaggedBase64 {
+    fn eq(&self, other: &TaggedBase64) -> bool {
+        self.tb64 == *other
+    }
+}
+
+impl TaggedBase64 {
+    /// Constructs a TaggedBase64 from a tag and array of bytes. The tag
+    /// must be URL-safe (alphanumeric with hyphen and underscore). The
+    /// byte values are unconstrained.
+    ///    ```ignored
+    ///    use TaggedBase64;
+    ///    let tb64 = TaggedBase64::new("TAG-YOURE-IT", b"datadatadata");
+    ///    ```
+    pub fn new(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        let cs = TaggedBase64::calc_checksum(tag, value);
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum: cs,
+            extended: false,
+        })
+    }
+
+    /// Constructs a TaggedBase64 like [new](Self::new), but allowing tags of
+    /// up to `max_tag_len` bytes instead of [TB64_MAX_TAG_LEN].
+    ///
+    /// The result can be parsed back with
+    /// [parse_with_max_tag_len](Self::parse_with_max_tag_len).
+    pub fn new_with_max_tag_len(
+        tag: &str,
+        value: &[u8],
+        max_tag_len: usize,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_tag(tag, max_tag_len)?;
+        let cs = TaggedBase64::calc_checksum(tag, value);
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum: cs,
+            extended: false,
+        })
+    }
+
+    /// Constructs a TaggedBase64 with the tag `namespace_kind`, joined by
+    /// [TB64_NAMESPACE_SEP].
+    ///
+    /// The namespace may itself contain separators, as in `ESPRESSO_L2`,
+    /// but neither it nor the kind may have an empty segment, and the kind
+    /// may not contain the separator.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let tb64 = TaggedBase64::new_namespaced("ESPRESSO", "TX", b"payload")?;
+    /// assert_eq!(tb64.tag_ref(), "ESPRESSO_TX");
+    /// assert_eq!(tb64.namespace(), Some("ESPRESSO"));
+    /// assert_eq!(tb64.kind(), "TX");
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn new_namespaced(
+        namespace: &str,
+        kind: &str,
+        value: &[u8],
+    ) -> Result<TaggedBase64, Tb64Error> {
+        if let Some(offset) = kind.find(TB64_NAMESPACE_SEP) {
+            return Err(Tb64Error::InvalidTagChar {
+                offset: namespace.len() + 1 + offset,
+                character: TB64_NAMESPACE_SEP,
+            });
+        }
+        let tag = format!("{}{}{}", namespace, TB64_NAMESPACE_SEP, kind);
+        TaggedBase64::validate_namespaced_tag(&tag)?;
+        TaggedBase64::new(&tag, value)
+    }
+
+    /// Constructs a TaggedBase64 like [new](Self::new), but rejects an
+    /// empty value.
+    pub fn new_nonempty(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        if value.is_empty() {
+            return Err(Tb64Error::EmptyValue);
+        }
+        TaggedBase64::new(tag, value)
+    }
+
+    /// Constructs a TaggedBase64 protected by the given checksum.
+    ///
+    /// With [ChecksumKind::Crc8] this is the same as [new](Self::new). The
+    /// stronger checksums produce an extended value; strings encoding plain
+    /// values continue to parse as before. With [ChecksumKind::None], the
+    /// value is also the same as with [new](Self::new), since the checksum
+    /// byte is only left out of the string, by
+    /// [to_string_without_checksum](Self::to_string_without_checksum).
+    pub fn new_with_checksum(
+        tag: &str,
+        value: &[u8],
+        checksum: ChecksumKind,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let header = match checksum {
+            ChecksumKind::None | ChecksumKind::Crc8 => return TaggedBase64::new(tag, value),
+            ChecksumKind::Crc16 => FLAG_CRC16,
+            ChecksumKind::Crc32c => FLAG_CRC32,
+        };
+        TaggedBase64::validate_tag(tag)?;
+        let mut bytes = Vec::with_capacity(5 + value.len());
+        bytes.push(header);
+        bytes.extend_from_slice(value);
+        let crc = TaggedBase64::calc_extra_checksum(header, tag, &bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes()[8 - crc_len(header)..]);
+        let cs = TaggedBase64::calc_checksum(tag, &bytes) ^ EXTENDED_CHECKSUM_MASK;
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: bytes.into(),
+            checksum: cs,
+            extended: true,
+        })
+    }
+
+    /// Constructs a TaggedBase64 protected by a checksum chosen for the
+    /// length of the value by [ChecksumKind::for_len].
+    ///
+    /// The parser detects the checksum from the format, so the result
+    /// parses like any other value.
+    /// ```
+    /// # use tagged_base64::{ChecksumKind, TaggedBase64};
+    /// let proof = TaggedBase64::new_with_sized_checksum("PROOF", &[7; 5000])?;
+    /// assert_eq!(proof.checksum_kind(), ChecksumKind::Crc32c);
+    /// assert_eq!(TaggedBase64::parse(&proof.to_string())?, proof);
+    ///
+    /// let id = TaggedBase64::new_with_sized_checksum("ID", &[7; 32])?;
+    /// assert_eq!(id, TaggedBase64::new("ID", &[7; 32])?);
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn new_with_sized_checksum(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::new_with_checksum(tag, value, ChecksumKind::for_len(value.len()))
+    }
+
+    /// Constructs a TaggedBase64 carrying a Unix timestamp alongside the
+    /// value.
+    ///
+    /// The timestamp is covered by the checksum. It can be read back with
+    /// [timestamp](Self::timestamp), and the original value with
+    /// [payload](Self::payload).
+    pub fn new_timestamped(
+        tag: &str,
+        value: &[u8],
+        unix_secs: u64,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        let mut bytes = Vec::with_capacity(9 + value.len());
+        bytes.push(FLAG_TIMESTAMP);
+        bytes.extend_from_slice(&unix_secs.to_be_bytes());
+        bytes.extend_from_slice(value);
+        let cs = TaggedBase64::calc_checksum(tag, &bytes) ^ EXTENDED_CHECKSUM_MASK;
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: bytes.into(),
+            checksum: cs,
+            extended: true,
+        })
+    }
+
+    /// Encodes each (tag, value) pair as a string, as if by
+    /// `TaggedBase64::new(tag, value)?.to_string()`.
+    ///
+    /// A single scratch buffer is reused for every item. Stops at the
+    /// first invalid tag and returns the error.
+    ///
+    /// With the `rayon` feature, the pairs are encoded in parallel, with a
+    /// scratch buffer per thread. If several tags are invalid, the error of
+    /// any one of them may be returned.
+    pub fn encode_batch(pairs: &[(&str, &[u8])]) -> Result<Vec<String>, Tb64Error> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            pairs
+                .par_iter()
+                .map_init(Vec::new, |scratch, (tag, value)| {
+                    TaggedBase64::encode_with_scratch(tag, value, scratch)
+                })
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut scratch = Vec::new();
+            pairs
+                .iter()
+                .map(|(tag, value)| TaggedBase64::encode_with_scratch(tag, value, &mut scratch))
+                .collect()
+        }
+    }
+
+    /// Encodes one item of [encode_batch](Self::encode_batch), building
+    /// the value and checksum in `scratch`.
+    fn encode_with_scratch(
+        tag: &str,
+        value: &[u8],
+        scratch: &mut Vec<u8>,
+    ) -> Result<String, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        scratch.clear();
+        scratch.extend_from_slice(value);
+        scratch.push(TaggedBase64::calc_checksum(tag, value));
+
+        let mut s = String::with_capacity(tag.len() + 1 + (scratch.len() * 4).div_ceil(3));
+        s.push_str(tag);
+        s.push(TB64_DELIM);
+        BASE64.encode_string(&scratch, &mut s);
+        Ok(s)
+    }
+
+    /// Parses each string as by [parse](Self::parse), returning a result
+    /// for every input in the same order.
+    ///
+    /// Unlike [decode_seq], an invalid string does not stop the batch. Its
+    /// error is reported as [InvalidElement](Tb64Error::InvalidElement)
+    /// with the index of the string, so errors collected apart from the
+    /// results still say which input they came from. With the `rayon`
+    /// feature, the strings are parsed in parallel.
+    ///
+    /// ```
+    /// # use tagged_base64::{TaggedBase64, Tb64Error};
+    /// let results = TaggedBase64::decode_batch(&["TX~Zm9vYmFy1w", "TX~Zm9vYmFy1x"]);
+    /// assert_eq!(results[0].as_ref().unwrap().value(), b"foobar");
+    /// assert!(matches!(results[1], Err(Tb64Error::InvalidElement { index: 1, .. })));
+    /// ```
+    pub fn decode_batch(strings: &[&str]) -> Vec<Result<TaggedBase64, Tb64Error>> {
+        let decode = |(index, s): (usize, &&str)| {
+            TaggedBase64::parse(s).map_err(|error| Tb64Error::InvalidElement {
+                index,
+                error: Box::new(error),
+            })
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            strings.par_iter().enumerate().map(decode).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            strings.iter().enumerate().map(decode).collect()
+        }
+    }
+
+    /// Parses a string of the form tag~value into a TaggedBase64 value.
+    ///
+    /// The tag is restricted to URL-safe base64 ASCII characters. The tag
+    /// may be empty. The delimiter is required.
+    ///
+    /// The value is a base64-encoded string, using the URL-safe character
+    /// set, and no padding is used.
+    pub fn parse(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse_with(tb64, &BASE64, PARSE_LIMITS)
+    }
+
+    /// Parses the string form from bytes, such as a network buffer or a
+    /// memory-mapped file, without first checking that they are UTF-8.
+    ///
+    /// This accepts exactly the strings [parse](Self::parse) accepts, with
+    /// the same errors. The value is decoded straight from the bytes, and
+    /// a byte which is not base64, including any non-ASCII byte, is
+    /// reported as [Base64](Tb64Error::Base64) with its
+    /// [offset](Tb64Error::offset) into the value. A tag which is not ASCII
+    /// is reported as [NonAsciiTag](Tb64Error::NonAsciiTag).
+    ///
+    /// ```
+    /// # use tagged_base64::{TaggedBase64, Tb64Error};
+    /// let tb64 = TaggedBase64::parse_bytes(b"TX~Zm9vYmFy1w")?;
+    /// assert_eq!(tb64.value(), b"foobar");
+    ///
+    /// let err = TaggedBase64::parse_bytes(b"TX~Zm9v\xffmFy1w").unwrap_err();
+    /// assert_eq!(err.offset(), Some(4));
+    /// # Ok::<(), Tb64Error>(())
+    /// ```
+    pub fn parse_bytes(tb64: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        let mut value = Vec::new();
+        let (tag, checksum, extended) =
+            TaggedBase64::decode_bytes_with(tb64, &BASE64, PARSE_LIMITS, &mut value)?;
+        Ok(TaggedBase64 {
+            tag: tag.into_owned().into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Parses a string like [parse](Self::parse), requiring the value to be
+    /// protected by the given checksum.
+    ///
+    /// [parse](Self::parse) detects and verifies whichever checksum a value
+    /// carries. This additionally rejects values with a different checksum,
+    /// for instance a plain value where a CRC-32C was expected, with
+    /// [WrongChecksumKind](Tb64Error::WrongChecksumKind). With
+    /// [ChecksumKind::None], the string is read as by
+    /// [parse_without_checksum](Self::parse_without_checksum).
+    pub fn parse_with_checksum(
+        tb64: &str,
+        expected: ChecksumKind,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        if expected == ChecksumKind::None {
+            return TaggedBase64::parse_without_checksum(tb64);
+        }
+        let parsed = TaggedBase64::parse(tb64)?;
+        let found = parsed.checksum_kind();
+        if found != expected {
+            return Err(Tb64Error::WrongChecksumKind { expected, found });
+        }
+        Ok(parsed)
+    }
+
+    /// Parses a string with whichever checksum it has, including none, and
+    /// reports which it was.
+    ///
+    /// A string which parses as by [parse](Self::parse) is reported with its
+    /// [checksum_kind](Self::checksum_kind). Failing that, one which parses
+    /// as by [parse_without_checksum](Self::parse_without_checksum) is
+    /// reported as [ChecksumKind::None], and one which parses neither way
+    /// fails with the error from `parse`. About one string in 256 without a
+    /// checksum happens to end in a valid one, and is misread, so use
+    /// [parse_with_checksum](Self::parse_with_checksum) when the kind is
+    /// known.
+    ///
+    /// ```
+    /// # use tagged_base64::{ChecksumKind, TaggedBase64, Tb64Error};
+    /// let (tb64, kind) = TaggedBase64::parse_detect_checksum("TX~Zm9vYmFy1w")?;
+    /// assert_eq!(kind, ChecksumKind::Crc8);
+    /// let (raw, kind) = TaggedBase64::parse_detect_checksum("TX~Zm9vYmFy")?;
+    /// assert_eq!(kind, ChecksumKind::None);
+    /// assert_eq!(raw, tb64);
+    /// # Ok::<(), Tb64Error>(())
+    /// ```
+    pub fn parse_detect_checksum(tb64: &str) -> Result<(TaggedBase64, ChecksumKind), Tb64Error> {
+        match TaggedBase64::parse(tb64) {
+            Ok(parsed) => {
+                let kind = parsed.checksum_kind();
+                Ok((parsed, kind))
+            }
+            Err(
+                err @ (Tb64Error::InvalidChecksum
+                | Tb64Error::MissingChecksum
+                | Tb64Error::InvalidLength),
+            ) => TaggedBase64::parse_without_checksum(tb64)
+                .map(|parsed| (parsed, ChecksumKind::None))
+                .map_err(|_| err),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Gets the tag of a string without decoding the value.
+    ///
+    /// Only the delimiter and the tag are checked, so a string whose value
+    /// is corrupt still yields its tag. This is for dispatching on the tag
+    /// before paying for a full [parse](Self::parse).
+    ///
+    /// The tag is borrowed from the string, unless it is written in hex as
+    /// by [to_string_hex_tag](Self::to_string_hex_tag).
+    pub fn peek_tag(tb64: &str) -> Result<Cow<'_, str>, Tb64Error> {
+        let delim_pos = tb64.find(TB64_DELIM).ok_or(Tb64Error::MissingDelimiter)?;
+        let tag = &tb64[..delim_pos];
+        let tag = match tag.strip_prefix(HEX_TAG_MARKER) {
+            Some(hex) => Cow::Owned(TaggedBase64::decode_hex_tag(hex)?),
+            None => Cow::Borrowed(tag),
+        };
+        TaggedBase64::check_tag(&tag, TB64_MAX_TAG_LEN)?;
+        Ok(tag)
+    }
+
+    /// Checks that a string would [parse](Self::parse), without
+    /// allocating.
+    ///
+    /// The tag, the delimiter, the base64 value, and the checksum are all
+    /// checked, decoding the value a block at a time on the stack. This
+    /// suits request guards and other places which reject bad input before
+    /// the value is needed. An error is the one [parse](Self::parse) would
+    /// return.
+    ///
+    /// Extended values, hex tags, and strings which are not valid base64
+    /// are handed to [parse](Self::parse), so these do allocate.
+    pub fn validate(tb64: &str) -> Result<(), Tb64Error> {
+        let delim_pos = tb64.find(TB64_DELIM).ok_or(Tb64Error::MissingDelimiter)?;
+        let (tag, delim_b64) = tb64.split_at(delim_pos);
+        let value = &delim_b64[1..];
+        if tag.starts_with(HEX_TAG_MARKER) || value.starts_with(TB64_DELIM) {
+            return TaggedBase64::parse(tb64).map(|_| ());
+        }
+        TaggedBase64::check_tag(tag, TB64_MAX_TAG_LEN)?;
+        if value.is_empty() {
+            return Err(Tb64Error::MissingChecksum);
+        }
+        if value.len() % 4 == 1 {
+            return Err(Tb64Error::InvalidLength);
+        }
+
+        // Feed every byte but the last, which is the checksum, to the CRC.
+        const BLOCK: usize = 64;
+        let mut buf = [0u8; BLOCK / 4 * 3];
+        let mut crc = crc::crc8(0, tag.as_bytes());
+        let mut len = 0usize;
+        let mut last = None;
+        for block in value.as_bytes().chunks(BLOCK) {
+            let Ok(n) = BASE64.decode_slice(block, &mut buf) else {
+                return TaggedBase64::parse(tb64).map(|_| ());
+            };
+            if let Some((&end, rest)) = buf[..n].split_last() {
+                if let Some(prev) = last.replace(end) {
+                    crc = crc::crc8(crc, &[prev]);
+                    len += 1;
+                }
+                crc = crc::crc8(crc, rest);
+                len += rest.len();
+            }
+        }
+        let expected = crc ^ (len as u8);
+        if last != Some(expected) {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(())
+    }
+
+    /// Parses a string of the form tag~value, requiring the tag to be one
+    /// of `allowed`.
+    ///
+    /// This is a shorthand for [TagRegistry::parse] with each tag
+    /// registered for a payload of any length.
+    pub fn try_from_allowed_tags(tb64: &str, allowed: &[&str]) -> Result<TaggedBase64, Tb64Error> {
+        allowed
+            .iter()
+            .fold(TagRegistry::new(), |registry, tag| registry.register(tag))
+            .parse(tb64)
+    }
+
+    /// Diagnoses why a string does not parse, or returns `None` if it does.
+    ///
+    /// This is intended to help user interfaces give targeted advice, such
+    /// as pointing out a stray character at a particular position.
+    pub fn classify_failure(tb64: &str) -> Option<FailureClass> {
+        let err = TaggedBase64::parse(tb64).err()?;
+        let Some((tag, value)) = tb64.split_once(TB64_DELIM) else {
+            return Some(if tb64.is_empty() {
+                FailureClass::Empty
+            } else {
+                FailureClass::MissingDelimiter
+            });
+        };
+        let value_start = tb64.len() - value.len();
+        let (value, value_start) = match value.strip_prefix(TB64_DELIM) {
+            Some(value) => (value, value_start + 1),
+            None => (value, value_start),
+        };
+        Some(match err {
+            Tb64Error::InvalidTag | Tb64Error::InvalidTagChar { .. } | Tb64Error::NonAsciiTag => {
+                // Hex tags are checked for hex digits after the marker.
+                let hex = tag.starts_with(HEX_TAG_MARKER);
+                let offset = usize::from(hex);
+                let ok = |c: char| {
+                    if hex {
+                        c.is_ascii_hexdigit()
+                    } else {
+                        TaggedBase64::is_safe_base64_ascii(c)
+                    }
+                };
+                let (pos, c) = tag[offset..]
+                    .char_indices()
+                    .map(|(i, c)| (i + offset, c))
+                    .find(|&(_, c)| !ok(c))
+                    .or_else(|| tag.chars().next().map(|c| (0, c)))
+                    .unwrap_or((0, TB64_DELIM));
+                FailureClass::BadTagChar(pos, c)
+            }
+            Tb64Error::MissingChecksum => FailureClass::Empty,
+            Tb64Error::InvalidChecksum => FailureClass::ChecksumMismatch,
+            Tb64Error::InvalidData | Tb64Error::UnsupportedVersion { .. } => {
+                FailureClass::UnsupportedFormat
+            }
+            _ => {
+                let bad = value
+                    .bytes()
+                    .position(|b| !(b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+                match bad {
+                    Some(i) => FailureClass::BadValueChar(value_start + i, value.as_bytes()[i]),
+                    None if value.len() % 4 == 1 => FailureClass::WrongLength,
+                    // The only remaining possibility is a final character
+                    // with bits set beyond the end of the data.
+                    None => FailureClass::BadValueChar(
+                        tb64.len() - 1,
+                        *value.as_bytes().last().unwrap_or(&0),
+                    ),
+                }
+            }
+        })
+    }
+
+    /// Computes the length of the value a string encodes from the length of
+    /// its base64 portion, without decoding it.
+    ///
+    /// This lets a caller reject oversized input before allocating. The
+    /// string is not otherwise validated, so it may still fail to parse. For
+    /// a string produced by [to_string_compact](Self::to_string_compact),
+    /// this is the length of the run-length coded bytes, not of the
+    /// expanded value, which parsing limits to [TB64_MAX_EXPANDED_LEN].
+    pub fn decoded_len(tb64: &str) -> Result<usize, Tb64Error> {
+        let delim_pos = tb64.find(TB64_DELIM).ok_or(Tb64Error::MissingDelimiter)?;
+        let value = &tb64[delim_pos + 1..];
+        let value = value.strip_prefix(TB64_DELIM).unwrap_or(value);
+        let (quads, rem) = (value.len() / 4, value.len() % 4);
+        if rem == 1 {
+            return Err(Tb64Error::InvalidLength);
+        }
+        // A trailing group of 2 or 3 characters encodes 1 or 2 bytes.
+        let bytes = quads * 3 + rem.saturating_sub(1);
+        bytes.checked_sub(1).ok_or(Tb64Error::MissingChecksum)
+    }
+
+    /// Checks whether a string is the canonical encoding of the value it
+    /// parses to.
+    ///
+    /// Some strings decode successfully but are not what this crate would
+    /// produce, for example because they include padding or set the unused
+    /// trailing bits of the last base64 character. Returns an error if the
+    /// string cannot be parsed at all.
+    pub fn is_canonical(tb64: &str) -> Result<bool, Tb64Error> {
+        let parsed = TaggedBase64::parse_with(tb64, &BASE64_LENIENT, PARSE_LIMITS)?;
+        Ok(parsed.to_string() == tb64)
+    }
+
+    /// Constructs a TaggedBase64 from a tag and the base64 portion of its
+    /// string form, that is, the encoding of the value followed by the
+    /// checksum.
+    ///
+    /// This is equivalent to parsing `tag~b64_value`, or `tag~~b64_value`
+    /// for an extended value, for callers which hold the two parts
+    /// separately, such as the tag and [encoded_value](Self::encoded_value).
+    /// The two are told apart by the checksum, which is masked for extended
+    /// values.
+    pub fn from_tag_and_b64(tag: &str, b64_value: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        match TaggedBase64::parse(&format!("{}{}{}", tag, TB64_DELIM, b64_value)) {
+            Err(Tb64Error::InvalidChecksum) => {
+                TaggedBase64::parse(&format!("{}{}{}{}", tag, TB64_DELIM, TB64_DELIM, b64_value))
+            }
+            parsed => parsed,
+        }
+    }
+
+    /// Constructs a TaggedBase64 from its parts, such as those returned by
+    /// [into_parts](Self::into_parts) and [checksum](Self::checksum),
+    /// checking that they are consistent.
+    ///
+    /// The checksum may be that of an extended value, in which case the
+    /// value must have a valid extended header.
+    pub fn from_parts(tag: &str, value: &[u8], checksum: u8) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        let tb64 = TaggedBase64::from_stored_checksum(tag.into(), value.into(), checksum);
+        if !tb64.verify_checksum() {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(tb64)
+    }
+
+    /// Constructs a TaggedBase64 holding the compressed serialization of
+    /// `value`.
+    ///
+    /// This is the one-off counterpart of the [macro@tagged] macro, for
+    /// values whose type has no tag of its own.
+    #[cfg(feature = "ark-serialize")]
+    pub fn from_canonical<T: CanonicalSerialize>(
+        tag: &str,
+        value: &T,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let mut bytes = Vec::with_capacity(value.compressed_size());
+        value
+            .serialize_compressed(&mut bytes)
+            .map_err(|_| Tb64Error::InvalidData)?;
+        TaggedBase64::new(tag, &bytes)
+    }
+
+    /// Deserializes the value as a compressed serialization of `T`, as
+    /// written by [from_canonical](Self::from_canonical), checking that it
+    /// is valid.
+    ///
+    /// The tag is not checked. Use
+    /// [to_canonical_tagged](Self::to_canonical_tagged) for types with a
+    /// tag of their own.
+    #[cfg(feature = "ark-serialize")]
+    pub fn to_canonical<T: CanonicalDeserialize>(&self) -> Result<T, Tb64Error> {
+        T::deserialize_compressed(&*self.value).map_err(|_| Tb64Error::InvalidData)
+    }
+
+    /// Deserializes the value like [to_canonical](Self::to_canonical),
+    /// after checking that the tag is the tag of `T`.
+    #[cfg(feature = "ark-serialize")]
+    pub fn to_canonical_tagged<T: Tagged + CanonicalDeserialize>(&self) -> Result<T, Tb64Error> {
+        if self.tag != T::tag() {
+            return Err(Tb64Error::InvalidTag);
+        }
+        self.to_canonical()
+    }
+
+    /// Constructs a value holding an integer as 8 big-endian bytes.
+    ///
+    /// Integers above 2^53 lose precision as JavaScript numbers, but
+    /// survive this form intact. [try_to_u64](Self::try_to_u64) reads the
+    /// integer back.
+    pub fn from_u64(tag: &str, n: u64) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::new(tag, &n.to_be_bytes())
+    }
+
+    /// Reads an integer written by [from_u64](Self::from_u64).
+    ///
+    /// The value must be exactly 8 bytes, or this fails with
+    /// [InvalidLength](Tb64Error::InvalidLength).
+    pub fn try_to_u64(&self) -> Result<u64, Tb64Error> {
+        <[u8; 8]>::try_from(self).map(u64::from_be_bytes)
+    }
+
+    /// Parses a string like [parse](Self::parse), but also accepts a value
+    /// with `=` padding or written in the standard base64 alphabet, with
+    /// `+` and `/` in place of `-` and `_`, and ignores ASCII whitespace
+    /// anywhere in the string, such as surrounding spaces or line breaks.
+    ///
+    /// This is meant for values pasted from other tools, emails, and
+    /// terminals. The parsed value still converts to the canonical,
+    /// URL-safe and unpadded string.
+    pub fn parse_lenient(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let stripped;
+        let tb64 = if tb64.contains(|c: char| c.is_ascii_whitespace()) {
+            stripped = tb64
+                .chars()
+                .filter(|c| !c.is_ascii_whitespace())
+                .collect::<String>();
+            &stripped
+        } else {
+            tb64
+        };
+        let Some(delim_pos) = tb64.find(TB64_DELIM) else {
+            return Err(Tb64Error::MissingDelimiter);
+        };
+        let (tag, value) = tb64.split_at(delim_pos);
+        if !value.contains(['+', '/']) {
+            return TaggedBase64::parse_with(tb64, &BASE64_LENIENT, PARSE_LIMITS);
+        }
+        let value: String = value
+            .chars()
+            .map(|c| match c {
+                '+' => '-',
+                '/' => '_',
+                c => c,
+            })
+            .collect();
+        TaggedBase64::parse_with(&format!("{}{}", tag, value), &BASE64_LENIENT, PARSE_LIMITS)
+    }
+
+    /// Parses a string like [parse](Self::parse), after reversing any
+    /// percent-encoding, such as `%7E` for the delimiter.
+    ///
+    /// Browsers, proxies and form serializers sometimes encode the
+    /// delimiter, and occasionally `-` and `_`, although no character of a
+    /// valid string needs it. An escape which is malformed, or decodes to a
+    /// character other than the delimiter or a URL-safe base64 character,
+    /// is rejected with
+    /// [InvalidPercentEncoding](Tb64Error::InvalidPercentEncoding).
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let tb64 = TaggedBase64::parse_percent_encoded("TX%7EZm9vYmFy1w")?;
+    /// assert_eq!(tb64.to_string(), "TX~Zm9vYmFy1w");
+    /// assert!(TaggedBase64::parse_percent_encoded("TX%7EZm9v%2BYmFy1w").is_err());
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn parse_percent_encoded(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        if !tb64.contains('%') {
+            return TaggedBase64::parse(tb64);
+        }
+        let mut decoded = String::with_capacity(tb64.len());
+        let mut pieces = tb64.split('%');
+        // The string is split at ASCII characters, so the offsets of the
+        // pieces are easy to track.
+        let first = pieces.next().unwrap();
+        decoded.push_str(first);
+        let mut offset = first.len();
+        for piece in pieces {
+            let escape = piece
+                .get(..2)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .map(char::from)
+                .filter(|&c| c == TB64_DELIM || TaggedBase64::is_safe_base64_ascii(c))
+                .ok_or(Tb64Error::InvalidPercentEncoding { offset })?;
+            decoded.push(escape);
+            decoded.push_str(&piece[2..]);
+            offset += 1 + piece.len();
+        }
+        TaggedBase64::parse(&decoded)
+    }
+
+    /// Parses a string like [parse](Self::parse), but ignoring the case of
+    /// the tag, which is converted to uppercase as by
+    /// [make_tag_uppercase](Self::make_tag_uppercase).
+    ///
+    /// The checksum covers the tag, so a tag retyped in another case, such
+    /// as `tx~Zm9vYmFy1w` for `TX~Zm9vYmFy1w`, does not match it. Such a
+    /// string is accepted if the checksum matches the tag as written or in
+    /// uppercase.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let tb64 = TaggedBase64::parse_ignore_tag_case("tx~Zm9vYmFy1w")?;
+    /// assert_eq!(tb64.to_string(), "TX~Zm9vYmFy1w");
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn parse_ignore_tag_case(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let mut parsed = match TaggedBase64::parse(tb64) {
+            Err(Tb64Error::InvalidChecksum) => {
+                // A checksum error means the delimiter was found.
+                let delim_pos = tb64.find(TB64_DELIM).unwrap();
+                let (tag, rest) = tb64.split_at(delim_pos);
+                TaggedBase64::parse(&format!("{}{}", tag.to_ascii_uppercase(), rest))
+                    .map_err(|_| Tb64Error::InvalidChecksum)?
+            }
+            parsed => parsed?,
+        };
+        parsed.make_tag_uppercase();
+        Ok(parsed)
+    }
+
+    /// Parses a string like [parse](Self::parse), but allowing tags of up
+    /// to `max_tag_len` bytes instead of [TB64_MAX_TAG_LEN].
+    pub fn parse_with_max_tag_len(
+        tb64: &str,
+        max_tag_len: usize,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let limits = ParseLimits {
+            max_tag_len,
+            ..PARSE_LIMITS
+        };
+        TaggedBase64::parse_with(tb64, &BASE64, limits)
+    }
+
+    /// Parses a string like [parse](Self::parse), but rejecting tags and
+    /// values longer than `limits`, before the value is decoded.
+    ///
+    /// A value which is too long fails with
+    /// [DecodedTooLong](Tb64Error::DecodedTooLong), and a tag with
+    /// [TagTooLong](Tb64Error::TagTooLong).
+    pub fn parse_with_limits(tb64: &str, limits: ParseLimits) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse_with(tb64, &BASE64, limits)
+    }
+
+    /// Parses a string from an untrusted source, such as a request to a
+    /// public endpoint, with the [default](ParseLimits::default) limits.
+    ///
+    /// [parse](Self::parse) decodes a value of any length, so a single
+    /// huge string can make it allocate as much memory as the string is
+    /// long, or up to [TB64_MAX_EXPANDED_LEN] for a run-length coded value.
+    /// This fails instead.
+    /// ```
+    /// # use tagged_base64::{TaggedBase64, Tb64Error};
+    /// let huge = TaggedBase64::new("TX", &vec![1; 1 << 20])?.to_string();
+    /// assert!(TaggedBase64::parse(&huge).is_ok());
+    /// assert!(matches!(
+    ///     TaggedBase64::parse_untrusted(&huge),
+    ///     Err(Tb64Error::DecodedTooLong { .. })
+    /// ));
+    /// # Ok::<(), Tb64Error>(())
+    /// ```
+    pub fn parse_untrusted(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse_with_limits(tb64, ParseLimits::default())
+    }
+
+    /// Parses a string like [parse](Self::parse), but decoding the value
+    /// with `engine` instead of [BASE64].
+    ///
+    /// This reads strings written by
+    /// [to_string_with_engine](Self::to_string_with_engine) with the same
+    /// engine, such as padded or standard alphabet base64.
+    pub fn parse_with_engine(tb64: &str, engine: &impl Engine) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse_with(tb64, engine, PARSE_LIMITS)
+    }
+
+    /// Produces the string form, encoding the value and checksum with
+    /// `engine` instead of [BASE64].
+    ///
+    /// The engine's alphabet must not include the delimiter, or the result
+    /// cannot be parsed. Only strings written with [BASE64] are canonical
+    /// and can be read by [parse](Self::parse).
+    pub fn to_string_with_engine(&self, engine: &impl Engine) -> String {
+        let mut bytes = Vec::with_capacity(self.value.len() + 1);
+        bytes.extend_from_slice(&self.value);
+        bytes.push(self.checksum);
+        let mut s = String::with_capacity(self.display_len() + 2);
+        s.push_str(&self.tag);
+        s.push(TB64_DELIM);
+        if self.is_extended() {
+            s.push(TB64_DELIM);
+        }
+        engine.encode_string(&bytes, &mut s);
+        s
+    }
+
+    /// Produces `tag~value` with the value in base64 but no checksum byte,
+    /// for systems which expect plain base64 after the delimiter.
+    ///
+    /// This form cannot be told apart from the checksummed one, so it is
+    /// only read back by [parse_without_checksum](Self::parse_without_checksum)
+    /// and offers no protection against typos. An extended value is written
+    /// with its header as part of the value, and reads back as a plain
+    /// value holding those bytes.
+    pub fn to_string_without_checksum(&self) -> String {
+        let mut s = String::with_capacity(self.tag.len() + 1 + (self.value.len() * 4).div_ceil(3));
+        s.push_str(&self.tag);
+        s.push(TB64_DELIM);
+        BASE64.encode_string(&self.value, &mut s);
+        s
+    }
+
+    /// Parses `tag~value` where the value is plain URL-safe, unpadded base64
+    /// of the bytes with no checksum byte, as produced by
+    /// [to_string_without_checksum](Self::to_string_without_checksum) or by
+    /// systems unaware of the checksum.
+    ///
+    /// The tag is checked as [parse](Self::parse) checks it, and the
+    /// checksum is computed afresh, so the result converts to the usual
+    /// checksummed string.
+    ///
+    /// ```
+    /// # use tagged_base64::{TaggedBase64, Tb64Error};
+    /// let tb64 = TaggedBase64::parse_without_checksum("TX~Zm9vYmFy")?;
+    /// assert_eq!(tb64.value(), b"foobar");
+    /// assert_eq!(tb64.to_string(), "TX~Zm9vYmFy1w");
+    /// # Ok::<(), Tb64Error>(())
+    /// ```
+    pub fn parse_without_checksum(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = tb64
+            .split_once(TB64_DELIM)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        TaggedBase64::validate_tag(tag)?;
+        let value = TaggedBase64::decode_raw(value)?;
+        let checksum = TaggedBase64::calc_checksum(tag, &value);
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended: false,
+        })
+    }
+
+    /// Produces a short rendering of the string form for user interfaces
+    /// and logs, such as `TX~Zm9v…ZXIq`.
+    ///
+    /// Strings of at most `max_chars` characters are returned unchanged.
+    /// Otherwise the tag and delimiter are kept whole, followed by the
+    /// start and end of the base64 value around an ellipsis, `…`, counted
+    /// as one character. At least one character of the value is kept on
+    /// each side, so the result exceeds `max_chars` if the limit is too
+    /// small for the tag.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let tb64 = TaggedBase64::new("TX", b"foobar baz qux")?;
+    /// assert_eq!(tb64.to_string(), "TX~Zm9vYmFyIGJheiBxdXgT");
+    /// assert_eq!(tb64.fmt_truncated(12), "TX~Zm9v…dXgT");
+    /// assert_eq!(tb64.fmt_truncated(100), tb64.to_string());
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn fmt_truncated(&self, max_chars: usize) -> String {
+        let s = self.to_string();
+        if s.len() <= max_chars {
+            return s;
+        }
+        // The string is ASCII, so byte offsets are character offsets.
+        let prefix_len = s.rfind(TB64_DELIM).unwrap() + 1;
+        let keep = max_chars.saturating_sub(prefix_len + 1).max(2);
+        let head = keep.div_ceil(2);
+        let tail = keep / 2;
+        let (prefix, value) = s.split_at(prefix_len);
+        if keep + 1 >= value.len() {
+            // Eliding fewer than two characters would not shorten it.
+            return s;
+        }
+        format!(
+            "{}{}…{}",
+            prefix,
+            &value[..head],
+            &value[value.len() - tail..]
+        )
+    }
+
+    /// Parses a string of the form tag~value, decoding the value into
+    /// `buf` and returning the tag.
+    ///
+    /// `buf` is cleared first, and on success holds exactly the value
+    /// bytes, without the checksum. Reusing the same buffer across calls
+    /// avoids allocating a new vector for every value parsed. If an error
+    /// is returned, the contents of `buf` are unspecified.
+    pub fn decode_into(tb64: &str, buf: &mut Vec<u8>) -> Result<String, Tb64Error> {
+        let (tag, ..) = TaggedBase64::decode_with(tb64, &BASE64, PARSE_LIMITS, buf)?;
+        Ok(tag.into_owned())
+    }
+
+    /// Parses a string like [decode_into](Self::decode_into), but also
+    /// writes the tag into a reused buffer.
+    ///
+    /// Both buffers are cleared first. With buffers which have grown large
+    /// enough, parsing allocates nothing. If an error is returned, the
+    /// contents of the buffers are unspecified.
+    pub fn decode_into_parts(
+        tb64: &str,
+        tag_out: &mut String,
+        value_out: &mut Vec<u8>,
+    ) -> Result<(), Tb64Error> {
+        let (tag, ..) = TaggedBase64::decode_with(tb64, &BASE64, PARSE_LIMITS, value_out)?;
+        tag_out.clear();
+        tag_out.push_str(&tag);
+        Ok(())
+    }
+
+    fn parse_with(
+        tb64: &str,
+        engine: &impl Engine,
+        limits: ParseLimits,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let mut value = Vec::new();
+        let (tag, checksum, extended) =
+            TaggedBase64::decode_with(tb64, engine, limits, &mut value)?;
+        Ok(TaggedBase64 {
+            tag: tag.into_owned().into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Parses an owned string, truncating it in place to produce the tag.
+    fn parse_owned(mut tb64: String) -> Result<TaggedBase64, Tb64Error> {
+        let mut value = Vec::new();
+        let (tag, checksum, extended) =
+            TaggedBase64::decode_with(&tb64, &BASE64, PARSE_LIMITS, &mut value)?;
+        let tag = match tag {
+            Cow::Borrowed(tag) => {
+                let len = tag.len();
+                tb64.truncate(len);
+                tb64
+            }
+            Cow::Owned(tag) => tag,
+        };
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Splits off and validates the tag, then decodes the value into `buf`
+    /// and verifies the checksum. Returns the tag, the checksum, and whether
+    /// the value is extended.
+    fn decode_with<'a>(
+        tb64: &'a str,
+        engine: &impl Engine,
+        limits: ParseLimits,
+        buf: &mut Vec<u8>,
+    ) -> Result<(Cow<'a, str>, u8, bool), Tb64Error> {
+        TaggedBase64::decode_bytes_with(tb64.as_bytes(), engine, limits, buf)
+    }
+
+    /// Like [decode_with](Self::decode_with), for a string which has not
+    /// been checked to be UTF-8. Only the tag is converted to a `str`; the
+    /// base64 decoder rejects any non-ASCII byte in the value.
+    fn decode_bytes_with<'a>(
+        tb64: &'a [u8],
+        engine: &impl Engine,
+        limits: ParseLimits,
+        buf: &mut Vec<u8>,
+    ) -> Result<(Cow<'a, str>, u8, bool), Tb64Error> {
+        let delim_pos = tb64
+            .iter()
+            .position(|&b| char::from(b) == TB64_DELIM)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        let (tag, delim_b64) = tb64.split_at(delim_pos);
+        let tag = core::str::from_utf8(tag).map_err(|_| Tb64Error::NonAsciiTag)?;
+        let tag = match tag.strip_prefix(HEX_TAG_MARKER) {
+            Some(hex) => Cow::Owned(TaggedBase64::decode_hex_tag(hex)?),
+            None => Cow::Borrowed(tag),
+        };
+
+        TaggedBase64::check_tag(&tag, limits.max_tag_len)?;
+
+        // Remove the delimiter. A second delimiter marks an extended value.
+        let value = &delim_b64[1..];
+        let (value, extended) = match value.strip_prefix(&[TB64_DELIM as u8]) {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        if value.is_empty() {
+            return Err(Tb64Error::MissingChecksum);
+        }
+        // A single character left over after whole groups of 4 cannot
+        // encode anything, so no decoder would accept it.
+        if value.len() % 4 == 1 {
+            return Err(Tb64Error::InvalidLength);
+        }
+        // Every 4 characters encode 3 bytes, the last of them the checksum.
+        let decoded_len = value.len() * 3 / 4 - 1;
+        if decoded_len > limits.max_decoded_len {
+            return Err(Tb64Error::DecodedTooLong {
+                len: decoded_len,
+                max: limits.max_decoded_len,
+            });
+        }
+
+        // Note: 'printf' debugging is possible like this:
+        //    use web_sys;
+        //    web_sys::console::log_1(&format!("+ {}", &tb64).into());
+
+        // Base64 decode the value.
+        buf.clear();
+        engine.decode_vec(value, buf)?;
+        let penultimate = buf.len() - 1;
+        let cs = buf[penultimate];
+        let mut expected = TaggedBase64::calc_checksum(&tag, &buf[..penultimate]);
+        if extended {
+            expected ^= EXTENDED_CHECKSUM_MASK;
+        }
+        if cs != expected {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        buf.truncate(penultimate);
+        if !extended {
+            return Ok((tag, cs, false));
+        }
+        if let Some(&header) = buf.first() {
+            check_version(header)?;
+        }
+        match buf.first() {
+            Some(&header) if header & ((1 << VERSION_SHIFT) - 1) == FLAG_DEFLATE => {
+                let (header, expanded) = TaggedBase64::inflate(&buf[1..], limits)?;
+                buf.clear();
+                if header == 0 {
+                    buf.extend_from_slice(&expanded);
+                    let cs = TaggedBase64::calc_checksum(&tag, buf);
+                    return Ok((tag, cs, false));
+                }
+                buf.push(header);
+                buf.extend_from_slice(&expanded);
+            }
+            Some(&header) if header & FLAG_RLE != 0 => {
+                // Expansion can multiply the length, so check it again. The
+                // header is kept unless run-length coding was its only flag.
+                limits.check_expanded_len(
+                    rle::unpacked_len(&buf[1..])? + usize::from(header & !FLAG_RLE != 0),
+                )?;
+                let expanded = rle::unpack(&buf[1..])?;
+                let header = header & !FLAG_RLE;
+                buf.clear();
+                if header == 0 {
+                    buf.extend_from_slice(&expanded);
+                    let cs = TaggedBase64::calc_checksum(&tag, buf);
+                    return Ok((tag, cs, false));
+                }
+                buf.push(header);
+                buf.extend_from_slice(&expanded);
+            }
+            _ => {}
+        }
+        TaggedBase64::check_extended_layout(&tag, buf)?;
+        let cs = TaggedBase64::calc_checksum(&tag, buf) ^ EXTENDED_CHECKSUM_MASK;
+        Ok((tag, cs, true))
+    }
+
+    /// Inflates the body of a value written by
+    /// [to_string_compressed](Self::to_string_compressed), returning the
+    /// original header and the rest of the value.
+    ///
+    /// Without the `deflate` feature, compressed values are rejected as
+    /// [InvalidData](Tb64Error::InvalidData).
+    fn inflate(body: &[u8], limits: ParseLimits) -> Result<(u8, Vec<u8>), Tb64Error> {
+        let (&header, rest) = body.split_first().ok_or(Tb64Error::InvalidData)?;
+        let (len, varint_len) = read_varint(rest)?;
+        // The declared length is checked before inflating, and inflating
+        // stops there, so a small string expands to at most
+        // `max_expanded_len` bytes.
+        limits.check_expanded_len(len.saturating_add(usize::from(header != 0)))?;
+        #[cfg(feature = "deflate")]
+        {
+            let expanded =
+                miniz_oxide::inflate::decompress_to_vec_with_limit(&rest[varint_len..], len)
+                    .map_err(|_| Tb64Error::InvalidData)?;
+            if expanded.len() != len {
+                return Err(Tb64Error::InvalidData);
+            }
+            Ok((header, expanded))
+        }
+        #[cfg(not(feature = "deflate"))]
+        {
+            let _ = varint_len;
+            Err(Tb64Error::InvalidData)
+        }
+    }
+
+    /// Decodes a tag written as hex digits of either case.
+    fn decode_hex_tag(hex: &str) -> Result<String, Tb64Error> {
+        if !hex.len().is_multiple_of(2) {
+            return Err(Tb64Error::InvalidTag);
+        }
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                let pair = core::str::from_utf8(pair).map_err(|_| Tb64Error::InvalidTag)?;
+                let c = u8::from_str_radix(pair, 16).map_err(|_| Tb64Error::InvalidTag)?;
+                Ok(char::from(c))
+            })
+            .collect()
+    }
+
+    /// Encodes the value in a compact binary form.
+    ///
+    /// The layout is the length of the tag as an unsigned LEB128 varint,
+    /// the tag, the value, and finally the checksum byte. Tags shorter than
+    /// 128 characters, which is to say all practical tags, need only a
+    /// single length byte.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.tag.len() + self.value.len());
+        push_varint(&mut bytes, self.tag.len());
+        bytes.extend_from_slice(self.tag.as_bytes());
+        bytes.extend_from_slice(&self.value);
+        bytes.push(self.checksum);
+        bytes
+    }
+
+    /// Decodes the binary form produced by [to_bytes](Self::to_bytes).
+    ///
+    /// As in the string form, tags longer than [TB64_MAX_TAG_LEN] are
+    /// rejected, so that every value decoded here can also be parsed from
+    /// its string form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        let (tag_len, header_len) = read_varint(bytes)?;
+        let rest = &bytes[header_len..];
+        if tag_len >= rest.len() {
+            return Err(Tb64Error::InvalidLength);
+        }
+        let (tag, rest) = rest.split_at(tag_len);
+        let tag = core::str::from_utf8(tag).map_err(|_| Tb64Error::InvalidTag)?;
+        TaggedBase64::validate_tag(tag)?;
+        let (&checksum, value) = rest.split_last().ok_or(Tb64Error::MissingChecksum)?;
+
+        // There is no marker for extended values in this form, so accept a
+        // checksum from either domain.
+        let expected = TaggedBase64::calc_checksum(tag, value);
+        let extended = checksum == expected ^ EXTENDED_CHECKSUM_MASK;
+        if extended {
+            TaggedBase64::check_extended_layout(tag, value)?;
+        } else if checksum != expected {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Checks that the header of an extended value is understood, that the
+    /// value is long enough for the fields the header announces, and that
+    /// its CRC-16 or CRC-32C, if any, matches.
+    fn check_extended_layout(tag: &str, value: &[u8]) -> Result<(), Tb64Error> {
+        let header = *value.first().ok_or(Tb64Error::InvalidData)?;
+        check_version(header)?;
+        let (prefix, suffix) = extended_fields(header).ok_or(Tb64Error::InvalidData)?;
+        if value.len() < prefix + suffix {
+            return Err(Tb64Error::InvalidData);
+        }
+        if suffix > 0 {
+            let (covered, stored) = value.split_at(value.len() - suffix);
+            let crc = TaggedBase64::calc_extra_checksum(header, tag, covered);
+            if stored != &crc.to_be_bytes()[8 - suffix..] {
+                return Err(Tb64Error::InvalidChecksum);
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the CRC-16 or CRC-32C announced by an extended header.
+    fn calc_extra_checksum(header: u8, tag: &str, value: &[u8]) -> u64 {
+        if header & FLAG_CRC32 != 0 {
+            crc::crc32c(crc::crc32c(0, tag.as_bytes()), value) as u64
+        } else {
+            crc::crc16(crc::crc16(0, tag.as_bytes()), value) as u64
+        }
+    }
+
+    /// Returns true if this value begins with an extended header.
+    fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    /// Builds a value from a checksum which may be from either domain, as
+    /// read from an untrusted binary encoding, without checking that it
+    /// matches.
+    fn from_stored_checksum(tag: InlineTag, value: InlineValue, checksum: u8) -> TaggedBase64 {
+        let extended =
+            checksum == TaggedBase64::calc_checksum(&tag, &value) ^ EXTENDED_CHECKSUM_MASK;
+        TaggedBase64 {
+            tag,
+            value,
+            checksum,
+            extended,
+        }
+    }
+
+    /// Returns true if the stored checksum matches the tag and value.
+    ///
+    /// Values built by this crate always have a matching checksum, but one
+    /// deserialized from an untrusted binary encoding may not. For an
+    /// extended value, the layout and any stronger checksum in the header
+    /// are checked as well.
+    pub fn verify_checksum(&self) -> bool {
+        let checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+        if self.extended {
+            self.checksum == checksum ^ EXTENDED_CHECKSUM_MASK
+                && TaggedBase64::check_extended_layout(&self.tag, &self.value).is_ok()
+        } else {
+            self.checksum == checksum
+        }
+    }
+
+    /// Computes the checksum of a tag alone.
+    ///
+    /// This is the checksum of a value with the given tag and an empty
+    /// value, so such a value is encoded as the tag, the delimiter, and the
+    /// base64 encoding of this single byte.
+    pub const fn tag_checksum(tag: &str) -> u8 {
+        TaggedBase64::calc_checksum(tag, &[])
+    }
+
+    /// Computes the checksum of a value with the given tag: the CRC-8 of
+    /// the tag and value, XORed with the low byte of the value's length.
+    ///
+    /// This is a `const fn`, so checksums of static identifiers can be
+    /// computed at compile time.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// const CHECKSUM: u8 = TaggedBase64::calc_checksum("TX", b"foobar");
+    /// assert_eq!(CHECKSUM, TaggedBase64::new("TX", b"foobar").unwrap().checksum());
+    /// ```
+    pub const fn calc_checksum(tag: &str, value: &[u8]) -> u8 {
+        crc::crc8(crc::crc8(0, tag.as_bytes()), value) ^ (value.len() as u8)
+    }
+
+    /// Computes the checksum of a value with the given tag, where the value
+    /// is the concatenation of `chunks`.
+    ///
+    /// This gives the same result as concatenating the chunks first, without
+    /// the intermediate allocation.
+    pub fn calc_checksum_chunks<'a, I: IntoIterator<Item = &'a [u8]>>(tag: &str, chunks: I) -> u8 {
+        let mut crc = crc::crc8(0, tag.as_bytes());
+        let mut len = 0usize;
+        for chunk in chunks {
+            crc = crc::crc8(crc, chunk);
+            len += chunk.len();
+        }
+        crc ^ (len as u8)
+    }
+
+    /// Returns true for characters permitted in URL-safe base64 encoding,
+    /// and false otherwise.
+    pub const fn is_safe_base64_ascii(c: char) -> bool {
+        c.is_ascii_alphanumeric() || (c == '-') || (c == '_')
+    }
+
+    /// Returns true if every character of `s` is a URL-safe base64
+    /// character or the delimiter.
+    ///
+    /// The string form of every TaggedBase64 passes this check, so it can
+    /// appear anywhere in a URL without percent-encoding.
+    pub fn is_url_safe_str(s: &str) -> bool {
+        s.chars()
+            .all(|c| c == TB64_DELIM || TaggedBase64::is_safe_base64_ascii(c))
+    }
+
+    /// Checks that an ASCII byte is safe for use in the tag of a
+    /// TaggedBase64. Because the tags are merely intended to be mnemonic,
+    /// there's no need to support a large and visually ambiguous
+    /// character set.
+    ///
+    /// This is a `const fn`, so it can check tags of constants at compile
+    /// time; see [const_tag].
+    pub const fn is_safe_base64_tag(tag: &str) -> bool {
+        // A non-ASCII character is encoded as bytes outside ASCII, none of
+        // which are safe, so checking bytes gives the same result.
+        let bytes = tag.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if !TaggedBase64::is_safe_base64_ascii(bytes[i] as char) {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Returns true if `tag` is accepted by [validate_tag](Self::validate_tag):
+    /// made of safe characters and at most [TB64_MAX_TAG_LEN] bytes long.
+    ///
+    /// This is a `const fn`, for use in constant contexts where the error of
+    /// `validate_tag` cannot be produced; see [const_tag].
+    pub const fn is_valid_tag(tag: &str) -> bool {
+        tag.len() <= TB64_MAX_TAG_LEN && TaggedBase64::is_safe_base64_tag(tag)
+    }
+
+    /// Checks that a tag is valid, distinguishing non-ASCII characters from
+    /// other invalid characters.
+    ///
+    /// An invalid ASCII character is reported as
+    /// [InvalidTagChar](Tb64Error::InvalidTagChar) with its position, so a
+    /// user interface can point at it. Tags longer than
+    /// [TB64_MAX_TAG_LEN] are rejected with
+    /// [TagTooLong](Tb64Error::TagTooLong).
+    ///
+    /// Tags are restricted to ASCII by design. Allowing other characters
+    /// would admit tags which look identical but differ, such as a Latin
+    /// `A` and a Cyrillic `А`.
+    pub fn validate_tag(tag: &str) -> Result<(), Tb64Error> {
+        TaggedBase64::check_tag(tag, TB64_MAX_TAG_LEN)
+    }
+
+    fn check_tag(tag: &str, max_tag_len: usize) -> Result<(), Tb64Error> {
+        if tag.len() > max_tag_len {
+            return Err(Tb64Error::TagTooLong { len: tag.len() });
+        }
+        if !tag.is_ascii() {
+            return Err(Tb64Error::NonAsciiTag);
+        }
+        match tag
+            .char_indices()
+            .find(|&(_, c)| !TaggedBase64::is_safe_base64_ascii(c))
+        {
+            Some((offset, character)) => Err(Tb64Error::InvalidTagChar { offset, character }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that a tag is valid as by [validate_tag](Self::validate_tag),
+    /// and that it has no empty segment between [namespace
+    /// separators](TB64_NAMESPACE_SEP): it does not begin or end with the
+    /// separator, or contain two in a row.
+    ///
+    /// An empty segment is reported as
+    /// [EmptyTagSegment](Tb64Error::EmptyTagSegment) with the offset at
+    /// which it would begin. A flat tag with no separator is valid.
+    pub fn validate_namespaced_tag(tag: &str) -> Result<(), Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        let mut offset = 0;
+        for segment in tag.split(TB64_NAMESPACE_SEP) {
+            if segment.is_empty() {
+                return Err(Tb64Error::EmptyTagSegment { offset });
+            }
+            offset += segment.len() + 1;
+        }
+        Ok(())
+    }
+
+    /// Splits a tag into its namespace, if any, and its kind, at the last
+    /// [TB64_NAMESPACE_SEP].
+    ///
+    /// A tag without a separator, or with the separator only at its start
+    /// or end, is flat: it has no namespace and is its own kind.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// assert_eq!(TaggedBase64::split_namespace("A_B_TX"), (Some("A_B"), "TX"));
+    /// assert_eq!(TaggedBase64::split_namespace("TX"), (None, "TX"));
+    /// assert_eq!(TaggedBase64::split_namespace("TX_"), (None, "TX_"));
+    /// ```
+    pub fn split_namespace(tag: &str) -> (Option<&str>, &str) {
+        match tag.rfind(TB64_NAMESPACE_SEP) {
+            Some(i) if i > 0 && i + 1 < tag.len() => (Some(&tag[..i]), &tag[i + 1..]),
+            _ => (None, tag),
+        }
+    }
+
+    /// Cleans up a tag entered by a user and checks that the result is a
+    /// valid tag.
+    ///
+    /// Surrounding whitespace is trimmed, full-width forms such as `ＴＸ`,
+    /// which some input methods produce, are folded to the ASCII characters
+    /// they stand for, and letters are converted to uppercase, the canonical
+    /// case of [normalized_tag](Self::normalized_tag).
+    ///
+    /// No other non-ASCII characters are mapped, since guessing what a
+    /// confusable character such as a Cyrillic `Т` was meant to be is
+    /// exactly the ambiguity ASCII-only tags avoid. Such tags are rejected
+    /// with [NonAsciiTag](Tb64Error::NonAsciiTag).
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// assert_eq!(TaggedBase64::normalize_tag(" tx\n")?, "TX");
+    /// assert_eq!(TaggedBase64::normalize_tag("ＴＸ")?, "TX");
+    /// assert!(TaggedBase64::normalize_tag("\u{0422}X").is_err());
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn normalize_tag(raw: &str) -> Result<String, Tb64Error> {
+        let tag: String = raw
+            .trim()
+            .chars()
+            .map(|c| match c {
+                // The full-width forms of `!` to `~` are offset by a fixed
+                // amount from their ASCII counterparts.
+                '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+                _ => c,
+            })
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        TaggedBase64::validate_tag(&tag)?;
+        Ok(tag)
+    }
+
+    /// Gets the tag of a TaggedBase64 instance.
+    ///
+    /// This copies the tag; use [tag_ref](Self::tag_ref) to borrow it.
+    pub fn tag(&self) -> String {
+        self.tag.to_string()
+    }
+
+    /// Borrows the tag of a TaggedBase64 instance without copying it.
+    pub fn tag_ref(&self) -> &str {
+        &self.tag
+    }
+
+    /// Sets the tag of a TaggedBase64 instance.
+    ///
+    /// # Panics
+    /// Panics if the tag is not valid, as checked by
+    /// [validate_tag](Self::validate_tag), including if it is longer than
+    /// [TB64_MAX_TAG_LEN].
+    pub fn set_tag(&mut self, tag: &str) {
+        assert!(TaggedBase64::is_valid_tag(tag), "invalid tag {:?}", tag);
+        self.set_tag_unchecked(tag);
+    }
+
+    /// Sets the tag of every value in `values`, recomputing each checksum.
+    ///
+    /// The tag is validated once, up front. If it is invalid, no value is
+    /// modified.
+    pub fn retag_batch(values: &mut [TaggedBase64], new_tag: &str) -> Result<(), Tb64Error> {
+        TaggedBase64::validate_tag(new_tag)?;
+        for value in values {
+            value.set_tag_unchecked(new_tag);
+        }
+        Ok(())
+    }
+
+    fn set_tag_unchecked(&mut self, tag: &str) {
+        let mask = if self.is_extended() {
+            EXTENDED_CHECKSUM_MASK
+        } else {
+            0
+        };
+        self.tag = tag.into();
+        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value) ^ mask;
+    }
+
+    /// Finds, for each value, the shortest prefix of its tag which is not a
+    /// prefix of any other tag in the set, for compact display.
+    ///
+    /// The prefix is at least one character long unless the tag is empty.
+    /// If no prefix is unique, because another value has the same tag or a
+    /// tag which extends this one, the full tag is returned.
+    pub fn minimal_unique_tag_prefixes(values: &[TaggedBase64]) -> Vec<String> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let tag = v.tag.as_bytes();
+                let mut len = 1;
+                for (j, other) in values.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let common = tag
+                        .iter()
+                        .zip(other.tag.as_bytes())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    len = len.max(common + 1);
+                }
+                v.tag[..len.min(tag.len())].to_string()
+            })
+            .collect()
+    }
+
+    /// Checks the tag against a shell-style glob pattern, where `*` matches
+    /// any run of characters and `?` matches exactly one character. All
+    /// other characters match themselves.
+    pub fn tag_matches_glob(&self, pattern: &str) -> bool {
+        glob_match(pattern.as_bytes(), self.tag.as_bytes())
+    }
+
+    /// Gets the value of a TaggedBase64 instance.
+    ///
+    /// For a timestamped value, this includes the encoded timestamp; use
+    /// [payload](Self::payload) to get the data alone.
+    ///
+    /// This copies the value, which may be large. Use
+    /// [value_ref](Self::value_ref) to borrow it, or
+    /// [into_value](Self::into_value) to take it.
+    pub fn value(&self) -> Vec<u8> {
+        self.value.to_vec()
+    }
+
+    /// Gets the checksum of a TaggedBase64 instance, the byte which follows
+    /// the value in the string form.
+    ///
+    /// For an extended value, such as a timestamped one, this is the
+    /// checksum of the tag and value XORed with a fixed mask.
+    pub fn checksum(&self) -> u8 {
+        self.checksum
+    }
+
+    /// Checks that the tag is not empty, for callers which treat an empty
+    /// tag as an error even though the format allows it. Returns `self`, so
+    /// it can be chained after parsing.
+    pub fn require_tag(&self) -> Result<&Self, Tb64Error> {
+        if self.tag.is_empty() {
+            Err(Tb64Error::EmptyTag)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Compares two values like `==`, but ignoring the ASCII case of the
+    /// tags.
+    ///
+    /// The checksum covers the tag as written, so it is not compared.
+    pub fn eq_ignore_tag_case(&self, other: &TaggedBase64) -> bool {
+        self.tag.eq_ignore_ascii_case(&other.tag)
+            && self.value == other.value
+            && self.is_extended() == other.is_extended()
+    }
+
+    /// Gets the tag converted to uppercase, for services which treat tags
+    /// as case-insensitive.
+    ///
+    /// Uppercase is the canonical case used by
+    /// [make_tag_uppercase](Self::make_tag_uppercase) and
+    /// [parse_ignore_tag_case](Self::parse_ignore_tag_case). Earlier
+    /// releases returned the tag in lowercase.
+    pub fn normalized_tag(&self) -> String {
+        self.tag.to_ascii_uppercase()
+    }
+
+    /// Returns true if the tag of this instance is `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tag == tag
+    }
+
+    /// Gets the namespace of the tag, as split by
+    /// [split_namespace](Self::split_namespace), or `None` for a flat tag.
+    pub fn namespace(&self) -> Option<&str> {
+        TaggedBase64::split_namespace(&self.tag).0
+    }
+
+    /// Gets the kind of the tag, the part after its namespace. For a flat
+    /// tag this is the whole tag.
+    pub fn kind(&self) -> &str {
+        TaggedBase64::split_namespace(&self.tag).1
+    }
+
+    /// Returns true if the tag is in the namespace `namespace`, or in a
+    /// namespace nested within it.
+    ///
+    /// Segments are compared whole, so `ESPRESSO_L2_TX` is in `ESPRESSO`
+    /// and `ESPRESSO_L2`, but not in `ESP`.
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.namespace().is_some_and(|ns| {
+            ns.strip_prefix(namespace)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(TB64_NAMESPACE_SEP))
+        })
+    }
+
+    /// Returns true if the tag of this instance is `tag`, ignoring ASCII
+    /// case.
+    pub fn has_tag_ignore_ascii_case(&self, tag: &str) -> bool {
+        self.tag.eq_ignore_ascii_case(tag)
+    }
+
+    /// Converts the tag to uppercase, the canonical case for applications
+    /// which treat tags as case-insensitive, and recomputes the checksum.
+    ///
+    /// Unlike [normalized_tag](Self::normalized_tag), which only reports
+    /// the tag in this case for comparison, this changes the value, and so
+    /// its string form.
+    pub fn make_tag_uppercase(&mut self) {
+        if self.tag.bytes().any(|b| b.is_ascii_lowercase()) {
+            let tag = self.tag.to_ascii_uppercase();
+            self.set_tag_unchecked(&tag);
+        }
+    }
+
+    /// Consumes a TaggedBase64 instance, returning its tag without copying
+    /// it, unless it is short enough to be stored inline.
+    pub fn into_tag(mut self) -> String {
+        mem::take(&mut self.tag).into_string()
+    }
+
+    /// Consumes a TaggedBase64 instance, returning its value without
+    /// copying it.
+    ///
+    /// Like [value](Self::value), this includes the timestamp of a
+    /// timestamped value. A value of up to 64 bytes, which is stored
+    /// inline, is copied.
+    pub fn into_value(mut self) -> Vec<u8> {
+        mem::take(&mut self.value).into_vec()
+    }
+
+    /// Consumes a TaggedBase64 instance, returning its tag and value
+    /// without copying them, unless they are stored inline.
+    pub fn into_parts(mut self) -> (String, Vec<u8>) {
+        (
+            mem::take(&mut self.tag).into_string(),
+            mem::take(&mut self.value).into_vec(),
+        )
+    }
+
+    /// Borrows the value of a TaggedBase64 instance without copying it.
+    ///
+    /// Like [value](Self::value), this includes the timestamp of a
+    /// timestamped value.
+    pub fn value_ref(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Gets the length of the value in bytes, without copying it.
+    ///
+    /// Like [value](Self::value), this includes the header of an extended
+    /// value.
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Returns true if the value is empty.
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Iterates over the bytes of the value.
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.value.iter()
+    }
+
+    /// Computes the length of the string form, as
+    /// [display_len](Self::display_len) does.
+    pub fn encoded_len(&self) -> usize {
+        self.display_len()
+    }
+
+    /// Gets the timestamp of a value constructed with
+    /// [new_timestamped](Self::new_timestamped), or `None` for a plain
+    /// value.
+    pub fn timestamp(&self) -> Option<u64> {
+        match self.value.split_first() {
+            Some((&header, rest))
+                if header & FLAG_TIMESTAMP != 0 && rest.len() >= 8 && self.is_extended() =>
+            {
+                let mut secs = [0; 8];
+                secs.copy_from_slice(&rest[..8]);
+                Some(u64::from_be_bytes(secs))
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the data of a TaggedBase64 instance, without the timestamp or
+    /// the extra checksum if there is one.
+    pub fn payload(&self) -> &[u8] {
+        let fields = match self.value.first() {
+            Some(&header) if self.is_extended() => extended_fields(header),
+            _ => None,
+        };
+        match fields {
+            Some((prefix, suffix)) if self.value.len() >= prefix + suffix => {
+                &self.value[prefix..self.value.len() - suffix]
+            }
+            _ => &self.value,
+        }
+    }
+
+    /// Gets the version of the format this value is written in.
+    ///
+    /// Plain values are written in [V1](FormatVersion::V1) and extended
+    /// values, such as timestamped ones or those with a stronger checksum,
+    /// in [V2](FormatVersion::V2). A parsed value reports the version of the
+    /// string it was parsed from.
+    pub fn format_version(&self) -> FormatVersion {
+        if self.is_extended() {
+            FormatVersion::V2
+        } else {
+            FormatVersion::V1
+        }
+    }
+
+    /// Gets the checksum protecting this value, as chosen with
+    /// [new_with_checksum](Self::new_with_checksum).
+    pub fn checksum_kind(&self) -> ChecksumKind {
+        let len = match self.value.first() {
+            Some(&header) if self.is_extended() => crc_len(header),
+            _ => 0,
+        };
+        match len {
+            4 => ChecksumKind::Crc32c,
+            2 => ChecksumKind::Crc16,
+            _ => ChecksumKind::Crc8,
+        }
+    }
+
+    /// Gets the bytes covered by the base64 portion of the string form: the
+    /// value followed by the checksum byte.
+    pub fn decoded_region(&self) -> Vec<u8> {
+        let mut region = Vec::with_capacity(self.value.len() + 1);
+        region.extend_from_slice(&self.value);
+        region.push(self.checksum);
+        region
+    }
+
+    /// Gets the base64 portion of the string form, that is, the encoding of
+    /// [decoded_region](Self::decoded_region).
+    pub fn encoded_value(&self) -> String {
+        // Encoding the whole string avoids copying the value to append the
+        // checksum.
+        let mut s = to_string(self);
+        let value_start = s.rfind(TB64_DELIM).unwrap() + 1;
+        s.replace_range(..value_start, "");
+        s
+    }
+
+    /// Appends the string form to `out`, reserving the space for it first.
+    ///
+    /// Unlike [to_string](crate::to_string), this reuses the capacity of
+    /// `out`, so encoding into a cleared buffer which has grown large
+    /// enough allocates nothing.
+    pub fn encode_into(&self, out: &mut String) {
+        out.reserve(self.display_len());
+        // Writing to a String cannot fail.
+        self.encode_to(out).unwrap();
+    }
+
+    /// Writes the string form to `w`, without building it in a separate
+    /// string first.
+    ///
+    /// This allows appending many values to a single reused buffer. For an
+    /// [io::Write](std::io::Write) sink, use [write_to](Self::write_to).
+    pub fn encode_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str(&self.tag)?;
+        w.write_char(TB64_DELIM)?;
+        if self.is_extended() {
+            w.write_char(TB64_DELIM)?;
+        }
+        // Whole groups of 3 bytes encode independently of what follows, so
+        // they are encoded straight from the value in chunks. The remaining
+        // bytes are encoded together with the checksum.
+        let mut buf = [0; 1024];
+        let mut tail = [0; 3];
+        let mut write = || {
+            let whole = self.value.len() / 3 * 3;
+            for chunk in self.value[..whole].chunks(buf.len() / 4 * 3) {
+                w.write_str(TaggedBase64::encode_chunk(chunk, &mut buf))?;
+            }
+            let rest = &self.value[whole..];
+            tail[..rest.len()].copy_from_slice(rest);
+            tail[rest.len()] = self.checksum;
+            w.write_str(TaggedBase64::encode_chunk(
+                &tail[..rest.len() + 1],
+                &mut buf,
+            ))
+        };
+        let result = write();
+        // The buffers hold pieces of the value, which may be secret.
+        #[cfg(feature = "zeroize")]
+        {
+            buf.zeroize();
+            tail.zeroize();
+        }
+        result
+    }
+
+    fn encode_chunk<'a>(chunk: &[u8], buf: &'a mut [u8]) -> &'a str {
+        // The buffer is large enough for the chunk, and base64 is ASCII.
+        let len = BASE64.encode_slice(chunk, buf).unwrap();
+        core::str::from_utf8(&buf[..len]).unwrap()
+    }
+
+    /// Writes the string form to an [io::Write](std::io::Write) sink, such
+    /// as an HTTP response body or a log file, without building it in a
+    /// string first.
+    ///
+    /// The value is encoded in chunks as by [encode_to](Self::encode_to),
+    /// so many small writes may be made; wrap an unbuffered sink in a
+    /// [BufWriter](std::io::BufWriter).
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    /// Computes the length of the string form, without producing it.
+    pub fn display_len(&self) -> usize {
+        let delims = if self.is_extended() { 2 } else { 1 };
+        TaggedBase64::encoded_len_for(&self.tag, self.value.len(), delims)
+    }
+
+    /// Computes the length of the string for a value of `value_len` bytes
+    /// with the given tag and number of delimiters.
+    fn encoded_len_for(tag: &str, value_len: usize, delims: usize) -> usize {
+        // The value is followed by the checksum byte.
+        let n = value_len + 1;
+        // Each group of 3 bytes takes 4 characters, and a partial group of
+        // 1 or 2 bytes takes 2 or 3 characters.
+        let b64 = n / 3 * 4 + [0, 2, 3][n % 3];
+        tag.len() + delims + b64
+    }
+
+    /// Converts this value to a string, run-length coding the value when
+    /// that makes the string shorter.
+    ///
+    /// Sparse values, such as zero-padded fields, encode to a much shorter
+    /// string. Other values are written exactly as by
+    /// [to_string](crate::to_string). Either form parses back to the same
+    /// value, but only the latter is [canonical](Self::is_canonical).
+    /// Parsing refuses to expand a value longer than
+    /// [TB64_MAX_EXPANDED_LEN], so longer values should be written with
+    /// [to_string](crate::to_string).
+    pub fn to_string_compact(&self) -> String {
+        let (header, body) = match self.value.split_first() {
+            Some((&header, body)) if self.is_extended() => (header, body),
+            _ => (0, &self.value[..]),
+        };
+        let mut packed = Vec::with_capacity(body.len() + 2);
+        packed.push(header | FLAG_RLE);
+        rle::pack(body, &mut packed);
+        if packed.len() >= self.value.len() {
+            return to_string(self);
+        }
+        let cs = TaggedBase64::calc_checksum(&self.tag, &packed) ^ EXTENDED_CHECKSUM_MASK;
+        packed.push(cs);
+        format!(
+            "{}{}{}{}",
+            self.tag,
+            TB64_DELIM,
+            TB64_DELIM,
+            TaggedBase64::encode_raw(&packed)
+        )
+    }
+
+    /// Converts this value to a string, deflating the value when that makes
+    /// the string shorter.
+    ///
+    /// Values holding text or serialized structures with repeated fields
+    /// often shrink severalfold, which helps keep URLs within practical
+    /// limits. Other values are written exactly as by
+    /// [to_string](crate::to_string). [parse](Self::parse) inflates the value
+    /// again, so either form parses back to the same value, but only the
+    /// latter is [canonical](Self::is_canonical). The compressed form is
+    /// written as an extended value, with a header noting the compression.
+    /// Parsing refuses to inflate a value longer than
+    /// [TB64_MAX_EXPANDED_LEN], so longer values should be written
+    /// uncompressed.
+    ///
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let json = br#"[{"id":1,"kind":"memo"},{"id":2,"kind":"memo"},{"id":3,"kind":"memo"}]"#;
+    /// let tb64 = TaggedBase64::new("DOC", json)?;
+    /// let s = tb64.to_string_compressed();
+    /// assert!(s.len() < tb64.to_string().len());
+    /// assert_eq!(TaggedBase64::parse(&s)?, tb64);
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    #[cfg(feature = "deflate")]
+    pub fn to_string_compressed(&self) -> String {
+        let (header, body) = match self.value.split_first() {
+            Some((&header, body)) if self.is_extended() => (header, body),
+            _ => (0, &self.value[..]),
+        };
+        let mut packed = Vec::with_capacity(body.len() / 2 + 8);
+        packed.push((FORMAT_VERSION << VERSION_SHIFT) | FLAG_DEFLATE);
+        packed.push(header);
+        push_varint(&mut packed, body.len());
+        packed.extend_from_slice(&miniz_oxide::deflate::compress_to_vec(body, 9));
+        if packed.len() >= self.value.len() {
+            return to_string(self);
+        }
+        let cs = TaggedBase64::calc_checksum(&self.tag, &packed) ^ EXTENDED_CHECKSUM_MASK;
+        packed.push(cs);
+        format!(
+            "{}{}{}{}",
+            self.tag,
+            TB64_DELIM,
+            TB64_DELIM,
+            TaggedBase64::encode_raw(&packed)
+        )
+    }
+
+    /// Compares two values by tag, then by value bytes.
+    pub fn canonical_cmp(&self, other: &TaggedBase64) -> Ordering {
+        self.tag
+            .cmp(&other.tag)
+            .then_with(|| self.value.cmp(&other.value))
+    }
+
+    /// Compares two values by their string forms, that is, in the order a
+    /// user sees when scanning a sorted list.
+    ///
+    /// This generally differs from [canonical_cmp](Self::canonical_cmp),
+    /// since the base64 alphabet is not in ASCII order and the string
+    /// includes the checksum. Use `display_cmp` to sort values for display,
+    /// and `canonical_cmp` when the order must follow the bytes.
+    pub fn display_cmp(&self, other: &TaggedBase64) -> Ordering {
+        to_string(self).cmp(&to_string(other))
+    }
+
+    /// Produces a key whose lexicographic order matches [canonical_cmp].
+    ///
+    /// The layout is the tag, followed by `!`, followed by the value as
+    /// lowercase hex. `!` sorts before every character allowed in a tag, so
+    /// a tag sorts before any longer tag it is a prefix of, and hex
+    /// encoding preserves the byte order of the value. The key is intended
+    /// for key-value stores; it is not a TaggedBase64 string and cannot be
+    /// parsed back.
+    ///
+    /// [canonical_cmp]: TaggedBase64::canonical_cmp
+    pub fn sortable_key(&self) -> String {
+        let mut key = String::with_capacity(self.tag.len() + 1 + 2 * self.value.len());
+        key.push_str(&self.tag);
+        key.push('!');
+        push_hex(&mut key, &self.value);
+        key
+    }
+
+    /// Produces a string whose lexicographic order matches [canonical_cmp],
+    /// and which [from_ordered_string](Self::from_ordered_string) parses
+    /// back.
+    ///
+    /// Like [sortable_key](Self::sortable_key), the tag is followed by `!`,
+    /// but the value is written in base64 with the URL-safe characters
+    /// rearranged into ASCII order, which preserves the byte order of the
+    /// value at a third of the size of hex. The checksum follows a second
+    /// `!`, so it never affects the order of distinct values. For instance,
+    /// `TX!OaxjNa4m!pk`.
+    ///
+    /// [canonical_cmp]: TaggedBase64::canonical_cmp
+    pub fn to_ordered_string(&self) -> String {
+        let mut s = String::with_capacity(self.tag.len() + 4 + (self.value.len() * 4).div_ceil(3));
+        s.push_str(&self.tag);
+        s.push(ORDERED_SEPARATOR);
+        BASE64_ORDERED.encode_string(&self.value, &mut s);
+        s.push(ORDERED_SEPARATOR);
+        BASE64_ORDERED.encode_string([self.checksum], &mut s);
+        s
+    }
+
+    /// Parses a string produced by
+    /// [to_ordered_string](Self::to_ordered_string), checking the tag and
+    /// checksum as [parse](Self::parse) does.
+    pub fn from_ordered_string(s: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, rest) = s
+            .split_once(ORDERED_SEPARATOR)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        let (value, checksum) = rest
+            .split_once(ORDERED_SEPARATOR)
+            .ok_or(Tb64Error::MissingChecksum)?;
+        TaggedBase64::check_tag(tag, TB64_MAX_TAG_LEN)?;
+        let value = BASE64_ORDERED.decode(value)?;
+        let checksum = match BASE64_ORDERED.decode(checksum)?[..] {
+            [checksum] => checksum,
+            _ => return Err(Tb64Error::InvalidChecksum),
+        };
+
+        // As in the base32 form, there is no marker for extended values, so
+        // accept a checksum from either domain.
+        let expected = TaggedBase64::calc_checksum(tag, &value);
+        let extended = checksum == expected ^ EXTENDED_CHECKSUM_MASK;
+        if extended {
+            TaggedBase64::check_extended_layout(tag, &value)?;
+        } else if checksum != expected {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Produces the string form with `sep` inserted after every `group`
+    /// characters of the base64 value, to make long values easier to read
+    /// and compare. The tag and delimiter are left intact.
+    ///
+    /// The result can be parsed with [parse_grouped](Self::parse_grouped).
+    ///
+    /// Panics if `group` is zero or if `sep` is a base64 character or the
+    /// delimiter.
+    pub fn to_string_grouped(&self, group: usize, sep: char) -> String {
+        assert!(group > 0);
+        assert!(!TaggedBase64::is_safe_base64_ascii(sep) && sep != TB64_DELIM);
+        let s = to_string(self);
+        let value_start = s.rfind(TB64_DELIM).unwrap() + 1;
+        let (prefix, value) = s.split_at(value_start);
+        let mut grouped = String::with_capacity(s.len() + value.len() / group);
+        grouped.push_str(prefix);
+        for (i, c) in value.chars().enumerate() {
+            if i > 0 && i % group == 0 {
+                grouped.push(sep);
+            }
+            grouped.push(c);
+        }
+        grouped
+    }
+
+    /// Parses a string produced by
+    /// [to_string_grouped](Self::to_string_grouped), ignoring every
+    /// occurrence of `sep`.
+    ///
+    /// Panics if `sep` is a base64 character or the delimiter.
+    pub fn parse_grouped(tb64: &str, sep: char) -> Result<TaggedBase64, Tb64Error> {
+        assert!(!TaggedBase64::is_safe_base64_ascii(sep) && sep != TB64_DELIM);
+        TaggedBase64::parse_owned(tb64.replace(sep, ""))
+    }
+
+    /// Finds the candidate tagged base64 values in free-form text, such as
+    /// logs or chat messages, and parses each of them.
+    ///
+    /// A candidate is a maximal run of base64 characters and delimiters
+    /// which contains a delimiter and does not end with one. Each is
+    /// reported with its byte range in `text` and the result of
+    /// [parse](Self::parse), so values which were mangled in transit are
+    /// found as well as valid ones. Punctuation around a value, such as
+    /// quotes, brackets, or a final full stop, is not part of the
+    /// candidate. Tags written in hex are not recognized.
+    pub fn find_all(
+        text: &str,
+    ) -> impl Iterator<Item = (Range<usize>, Result<TaggedBase64, Tb64Error>)> + '_ {
+        let in_token = |c: char| c == TB64_DELIM || TaggedBase64::is_safe_base64_ascii(c);
+        let mut pos = 0;
+        core::iter::from_fn(move || loop {
+            let start = pos + text[pos..].find(in_token)?;
+            let end = text[start..]
+                .find(|c| !in_token(c))
+                .map_or(text.len(), |len| start + len);
+            pos = end;
+            let token = &text[start..end];
+            if token.contains(TB64_DELIM) && !token.ends_with(TB64_DELIM) {
+                return Some((start..end, TaggedBase64::parse(token)));
+            }
+        })
+    }
+
+    /// Splits the string form into URL path segments.
+    ///
+    /// The first segment is the tag followed by the delimiter, so that it is
+    /// never empty, and the remaining segments are consecutive chunks of
+    /// the base64 value, each `seg_len` characters long except possibly the
+    /// last. None of the segments contain `/`. Joining the segments with
+    /// `/` gives a path which [from_path_segments](Self::from_path_segments)
+    /// can reassemble.
+    ///
+    /// Panics if `seg_len` is zero.
+    pub fn to_path_segments(&self, seg_len: usize) -> Vec<String> {
+        assert!(seg_len > 0);
+        let s = to_string(self);
+        let value_start = s.rfind(TB64_DELIM).unwrap() + 1;
+        let (prefix, value) = s.split_at(value_start);
+        let mut segments = Vec::with_capacity(1 + value.len().div_ceil(seg_len));
+        segments.push(prefix.to_string());
+        // The value is ASCII, so it can be chunked bytewise.
+        segments.extend(
+            value
+                .as_bytes()
+                .chunks(seg_len)
+                .map(|chunk| String::from_utf8_lossy(chunk).into_owned()),
+        );
+        segments
+    }
+
+    /// Reassembles a value split by
+    /// [to_path_segments](Self::to_path_segments).
+    pub fn from_path_segments(segments: &[&str]) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse_owned(segments.concat())
+    }
+
+    /// Produces the string form with the tag written as lowercase hex.
+    ///
+    /// Base64 tags are case sensitive, so they are corrupted by storage
+    /// layers which fold case. A hex tag survives case folding, at the cost
+    /// of doubling the length of the tag. The hex tag is prefixed with `.`,
+    /// which cannot appear in an ordinary tag, and [parse](Self::parse)
+    /// recognizes this form and recovers the original tag. Only the tag is
+    /// protected; the value is still case-sensitive base64.
+    pub fn to_string_hex_tag(&self) -> String {
+        let mut s = String::with_capacity(1 + 2 * self.tag.len());
+        s.push(HEX_TAG_MARKER);
+        push_hex(&mut s, self.tag.as_bytes());
+        s.push_str(&to_string(self)[self.tag.len()..]);
+        s
+    }
+
+    /// Produces a case-insensitive string form, with the value in base32
+    /// using the bech32 character set.
+    ///
+    /// The string is the tag in lowercase, the separator `1`, and the
+    /// encoding of the value and checksum, for instance `tx1vehk7cnpwtts`.
+    /// It survives media which do not preserve case, such as speech, DNS
+    /// labels, or QR codes in alphanumeric mode, for which the whole string
+    /// may be converted to uppercase. It is about 20% longer than the base64
+    /// form.
+    ///
+    /// Since case is not preserved, only tags made of uppercase letters,
+    /// digits, and `-` can be written this way. Any other character in the
+    /// tag is reported as [InvalidTagChar](Tb64Error::InvalidTagChar).
+    pub fn to_string_base32(&self) -> Result<String, Tb64Error> {
+        if let Some((offset, character)) = self
+            .tag
+            .char_indices()
+            .find(|&(_, c)| !(c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-'))
+        {
+            return Err(Tb64Error::InvalidTagChar { offset, character });
+        }
+        let mut s = String::with_capacity(self.tag.len() + 1 + (self.value.len() + 1) * 8 / 5 + 1);
+        s.push_str(&self.tag.to_ascii_lowercase());
+        s.push(BASE32_SEPARATOR);
+        let mut bytes = Vec::with_capacity(self.value.len() + 1);
+        bytes.extend_from_slice(&self.value);
+        bytes.push(self.checksum);
+        base32::encode(&bytes, &mut s);
+        Ok(s)
+    }
+
+    /// Parses a string produced by [to_string_base32](Self::to_string_base32),
+    /// in either lowercase or uppercase, but not a mixture.
+    ///
+    /// The tag is returned in uppercase. The separator is the last `1` in the
+    /// string, since `1` is not a base32 character but may appear in the tag.
+    pub fn parse_base32(s: &str) -> Result<TaggedBase64, Tb64Error> {
+        // Like bech32, reject mixed case, which is more likely a typo than
+        // intended.
+        let mut letters = s.char_indices().filter(|(_, c)| c.is_ascii_alphabetic());
+        if let Some((_, first)) = letters.next() {
+            if let Some((offset, character)) =
+                letters.find(|(_, c)| c.is_ascii_uppercase() != first.is_ascii_uppercase())
+            {
+                return Err(Tb64Error::InvalidBase32Char { offset, character });
+            }
+        }
+        let lower = s.to_ascii_lowercase();
+        let sep_pos = lower
+            .rfind(BASE32_SEPARATOR)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        let (tag, data) = (&lower[..sep_pos], &lower[sep_pos + 1..]);
+        if let Some((offset, character)) = tag
+            .char_indices()
+            .find(|&(_, c)| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'))
+        {
+            return Err(Tb64Error::InvalidTagChar { offset, character });
+        }
+        let tag = tag.to_ascii_uppercase();
+        TaggedBase64::check_tag(&tag, TB64_MAX_TAG_LEN)?;
+        let mut value = base32::decode(data, sep_pos + 1)?;
+        let checksum = value.pop().ok_or(Tb64Error::MissingChecksum)?;
+
+        // As in the binary form, there is no marker for extended values, so
+        // accept a checksum from either domain.
+        let expected = TaggedBase64::calc_checksum(&tag, &value);
+        let extended = checksum == expected ^ EXTENDED_CHECKSUM_MASK;
+        if extended {
+            TaggedBase64::check_extended_layout(&tag, &value)?;
+        } else if checksum != expected {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.into(),
+            checksum,
+            extended,
+        })
+    }
+
+    /// Produces the string form with the value written as lowercase hex,
+    /// for tools which only deal in hex.
+    ///
+    /// The hex follows the delimiter and the marker `.`, which cannot begin
+    /// a base64 value, and includes the checksum byte at the end. For
+    /// instance, `TX~.666f6f626172d7`. Only [parse_any](Self::parse_any)
+    /// and [decode_seq] accept this form; [parse](Self::parse) and the
+    /// default output remain base64.
+    pub fn to_hex_string(&self) -> String {
+        let mut s = String::with_capacity(self.tag.len() + 3 + 2 * (self.value.len() + 1));
+        s.push_str(&self.tag);
+        s.push(TB64_DELIM);
+        if self.is_extended() {
+            s.push(TB64_DELIM);
+        }
+        s.push(HEX_VALUE_MARKER);
+        push_hex(&mut s, &self.value);
+        push_hex(&mut s, &[self.checksum]);
+        s
+    }
+
+    /// Parses the usual string form, the hex form of
+    /// [to_hex_string](Self::to_hex_string), or the base32 form of
+    /// [to_string_base32](Self::to_string_base32).
+    ///
+    /// Base32 is told apart by the delimiter, which every other form
+    /// contains and no base32 string does, and hex by its marker. Either
+    /// form is checked exactly like the base64 form.
+    pub fn parse_any(s: &str) -> Result<TaggedBase64, Tb64Error> {
+        let Some(delim_pos) = s.find(TB64_DELIM) else {
+            return TaggedBase64::parse_base32(s);
+        };
+        let rest = &s[delim_pos + 1..];
+        let value_start = s.len() - rest.strip_prefix(TB64_DELIM).unwrap_or(rest).len();
+        match s[value_start..].strip_prefix(HEX_VALUE_MARKER) {
+            Some(hex) => {
+                let mut b64 = String::from(&s[..value_start]);
+                BASE64.encode_string(decode_hex(hex)?, &mut b64);
+                TaggedBase64::parse(&b64)
+            }
+            None => TaggedBase64::parse(s),
+        }
+    }
+
+    /// Checks a checksum stored separately from the value against the
+    /// checksum recomputed from the tag and value.
+    ///
+    /// The comparison takes the same time whether or not the checksums
+    /// match.
+    pub fn verify_detached(&self, checksum: u8) -> bool {
+        let mut expected = TaggedBase64::calc_checksum(&self.tag, &self.value);
+        if self.is_extended() {
+            expected ^= EXTENDED_CHECKSUM_MASK;
+        }
+        core::hint::black_box(expected ^ checksum) == 0
+    }
+
+    /// Gets a word from [CHECKSUM_WORDS] identifying the checksum.
+    ///
+    /// Comparing this word is a quick way for two people to confirm out of
+    /// band that they are looking at the same value.
+    pub fn checksum_word(&self) -> &'static str {
+        CHECKSUM_WORDS[self.checksum as usize]
+    }
+
+    /// Sets the value of a TaggedBase64 instance.
+    ///
+    /// This replaces the entire value, so a timestamped value becomes a
+    /// plain one.
+    pub fn set_value(&mut self, value: &[u8]) {
+        #[cfg(feature = "zeroize")]
+        self.value.zeroize();
+        self.value = value.into();
+        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+        self.extended = false;
+    }
+
+    /// Sets the value, unless the result would be longer than
+    /// `max_encoded_len` characters when displayed.
+    ///
+    /// The length checked is that of the whole string, tag and delimiter
+    /// included, as given by [display_len](Self::display_len). If it is too
+    /// long, this returns [ValueTooLong](Tb64Error::ValueTooLong) and the
+    /// value is left unchanged.
+    pub fn try_set_value(&mut self, value: &[u8], max_encoded_len: usize) -> Result<(), Tb64Error> {
+        let len = TaggedBase64::encoded_len_for(&self.tag, value.len(), 1);
+        if len > max_encoded_len {
+            return Err(Tb64Error::ValueTooLong {
+                len,
+                max: max_encoded_len,
+            });
+        }
+        self.set_value(value);
+        Ok(())
+    }
+
+    /// Wraps the underlying base64 encoder.
+    ///
+    /// With the `simd` feature, inputs of at least [SIMD_MIN_LEN] bytes
+    /// are encoded with vectorized instructions where the CPU supports
+    /// them. The output is the same either way.
+    // WASM doesn't support the most general type.
+    //
+    // pub fn encode_raw<T: ?Sized + AsRef<[u8]>>(input: &T) -> String;
+    pub fn encode_raw(input: &[u8]) -> String {
+        #[cfg(feature = "simd")]
+        if input.len() >= SIMD_MIN_LEN {
+            return base64_simd::URL_SAFE_NO_PAD.encode_to_string(input);
+        }
+        BASE64.encode(input)
+    }
+    /// Wraps the underlying base64 decoder.
+    ///
+    /// This is available without the `wasm-bindgen` feature. Decoding
+    /// errors are reported as [Base64](Tb64Error::Base64), which carries
+    /// the underlying [DecodeError](base64::DecodeError).
+    ///
+    /// With the `simd` feature, inputs of at least [SIMD_MIN_LEN]
+    /// characters are decoded with vectorized instructions where the CPU
+    /// supports them. The same strings are accepted either way, and
+    /// rejected strings are decoded again by the ordinary decoder to
+    /// report the error.
+    pub fn decode_raw(value: &str) -> Result<Vec<u8>, Tb64Error> {
+        #[cfg(feature = "simd")]
+        if value.len() >= SIMD_MIN_LEN {
+            if let Ok(bytes) = base64_simd::URL_SAFE_NO_PAD.decode_to_vec(value) {
+                return Ok(bytes);
+            }
+        }
+        Ok(BASE64.decode(value)?)
+    }
+
+    /// Renders the value as a `data:` URI with the given MIME type.
+    ///
+    /// Data URIs use the standard base64 alphabet with padding, not the
+    /// URL-safe alphabet used by TaggedBase64, so the value is encoded
+    /// with the standard encoder. Only the value is included; the tag
+    /// and checksum are dropped.
+    pub fn to_data_uri(&self, mime: &str) -> String {
+        format!("data:{};base64,{}", mime, STANDARD.encode(&self.value))
+    }
+}
+
+/// Exposes the value, as by [value_ref](TaggedBase64::value_ref).
+///
+/// There is deliberately no `AsRef<str>`: it would be ambiguous between the
+/// tag and the string form. Use [tag_ref](TaggedBase64::tag_ref) for the
+/// former.
+impl AsRef<[u8]> for TaggedBase64 {
+    fn as_ref(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// Converts any object that supports the Display trait to a JsValue for
+/// passing to Javascript.
+///
+/// Note: Type parameters aren't supported by `wasm-pack` yet so this
+/// can't be included in the TaggedBase64 type implementation.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+pub fn to_jsvalue<D: Display>(d: D) -> JsValue {
+    JsValue::from_str(&format!("{}", d))
+}
+
+/// JavaScript error object for a [Tb64Error], thrown by the methods of
+/// [JsTaggedBase64].
+///
+/// Besides the message, it carries the [code](Tb64Error::code) naming the
+/// kind of error and, where the error points at a character, its
+/// [offset](Tb64Error::offset) and the [character](Tb64Error::character)
+/// itself, so callers need not match on messages.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsTb64Error {
+    code: &'static str,
+    message: String,
+    offset: Option<usize>,
+    character: Option<char>,
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen]
+impl JsTb64Error {
+    /// A stable code naming the kind of error, such as
+    /// `"INVALID_CHECKSUM"`.
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.code.to_string()
+    }
+
+    /// A description of the error for display.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// The byte offset of the offending character, or `undefined`.
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The offending character, or `undefined`.
+    #[wasm_bindgen(getter, js_name = char)]
+    pub fn character(&self) -> Option<String> {
+        self.character.map(String::from)
+    }
+
+    /// Formats the error as its message.
+    #[allow(clippy::inherent_to_string)]
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.message.clone()
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+impl From<Tb64Error> for JsTb64Error {
+    fn from(error: Tb64Error) -> JsTb64Error {
+        JsTb64Error {
+            code: error.code(),
+            message: error.to_string(),
+            offset: error.offset(),
+            character: error.character(),
+        }
+    }
+}
+
+/// Converts the error to a [JsTb64Error] object.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+impl From<Tb64Error> for JsValue {
+    fn from(error: Tb64Error) -> JsValue {
+        JsTb64Error::from(error).into()
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** A value accepted by the JsTaggedBase64 constructor. A string is encoded as UTF-8. */
+export type TaggedBase64Value = string | Uint8Array | ArrayBuffer;
+
+/** The code of a JsTb64Error, naming the kind of error. */
+export type Tb64ErrorCode =
+    | "INVALID_TAG" | "INVALID_TAG_CHAR" | "MISSING_DELIMITER" | "MISSING_CHECKSUM"
+    | "BASE64" | "INVALID_CHECKSUM" | "INVALID_DATA" | "NON_ASCII_TAG"
+    | "TAG_NOT_ALLOWED" | "INVALID_LENGTH" | "EMPTY_VALUE" | "EMPTY_TAG"
+    | "UNSUPPORTED_VERSION" | "IO" | "TAG_TOO_LONG" | "VALUE_TOO_LONG"
+    | "WRONG_CHECKSUM_KIND" | "INVALID_SEGMENT" | "INVALID_ELEMENT"
+    | "INVALID_BASE32_CHAR" | "UNSUPPORTED_MULTIBASE" | "UNKNOWN_CODEC"
+    | "INVALID_DELIMITER" | "EMPTY_TAG_SEGMENT" | "WRONG_VALUE_LENGTH"
+    | "MISSING_QUERY_PARAM" | "INVALID_PERCENT_ENCODING" | "DECODED_TOO_LONG";
+"#;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen]
+extern "C" {
+    /// A `string`, `Uint8Array` or `ArrayBuffer`, as declared in the
+    /// TypeScript definitions.
+    #[wasm_bindgen(typescript_type = "TaggedBase64Value")]
+    pub type TaggedBase64Value;
+}
+
+/// Gets the bytes of a [TaggedBase64Value], encoding a string as UTF-8.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+fn js_value_bytes(value: &JsValue) -> Result<Vec<u8>, JsValue> {
+    if let Some(s) = value.as_string() {
+        Ok(s.into_bytes())
+    } else if let Some(array) = value.dyn_ref::<js_sys::Uint8Array>() {
+        Ok(array.to_vec())
+    } else if let Some(buffer) = value.dyn_ref::<js_sys::ArrayBuffer>() {
+        Ok(js_sys::Uint8Array::new(buffer).to_vec())
+    } else {
+        Err(js_sys::TypeError::new("expected a string, Uint8Array or ArrayBuffer").into())
+    }
+}
 
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
 #[wasm_bindgen]
 impl JsTaggedBase64 {
+    /// Constructs a value from a tag and a `string`, `Uint8Array` or
+    /// `ArrayBuffer`. A string is encoded as UTF-8.
+    ///
+    /// Throws a [JsTb64Error] if the tag is invalid, or a `TypeError` if
+    /// the value has another type.
     #[wasm_bindgen(constructor)]
+    pub fn from_js(tag: &str, value: TaggedBase64Value) -> Result<JsTaggedBase64, JsValue> {
+        let bytes = js_value_bytes(&value)?;
+        JsTaggedBase64::new(tag, &bytes)
+    }
+
+    /// Constructs a value from a tag and bytes.
     pub fn new(tag: &str, value: &[u8]) -> Result<JsTaggedBase64, JsValue> {
-        let result = TaggedBase64::new(tag, value);
-        match result {
-            Ok(tb) => Ok(JsTaggedBase64 { tb64: tb }),
-            Err(err) => Err(to_jsvalue(err)),
-        }
+        let tb64 = TaggedBase64::new(tag, value)?;
+        Ok(JsTaggedBase64 { tb64 })
     }
 
     /// Parses a string of the form tag~value into a TaggedBase64 value.
@@ -454,6 +4174,8 @@ impl JsTaggedBase64 {
     ///
     /// The value is a base64-encoded string, using the URL-safe character
     /// set, and no padding is used.
+    ///
+    /// Throws a [JsTb64Error] if the string does not parse.
     pub fn parse(tb64: &str) -> Result<TaggedBase64, JsValue> {
         let result = TaggedBase64::parse(tb64)?;
         Ok(result)
@@ -487,6 +4209,102 @@ impl JsTaggedBase64 {
     pub fn to_string(&self) -> String {
         self.tb64.to_string()
     }
+
+    /// Returns true if both instances have the same tag and value.
+    pub fn equals(&self, other: &JsTaggedBase64) -> bool {
+        self.tb64 == other.tb64
+    }
+
+    /// Formats the instance as a string for `JSON.stringify`, which
+    /// [parse](Self::parse) reads back.
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> String {
+        self.tb64.to_string()
+    }
+}
+
+/// Methods of [TaggedBase64] exported to JavaScript, so values returned by
+/// [JsTaggedBase64::parse] can be read back.
+///
+/// They are named for JavaScript conventions, as `tag`, `value`, `setTag`,
+/// `setValue`, `toString`, `toJSON` and `equals`. Unlike [set_tag](Self::set_tag),
+/// `setTag` throws on an invalid tag rather than panicking.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen]
+impl TaggedBase64 {
+    /// Gets the tag.
+    #[wasm_bindgen(js_name = tag)]
+    pub fn js_tag(&self) -> String {
+        self.tag()
+    }
+
+    /// Gets the value as a `Uint8Array`.
+    #[wasm_bindgen(js_name = value)]
+    pub fn js_value(&self) -> Vec<u8> {
+        self.value()
+    }
+
+    /// Sets the tag, throwing a [JsTb64Error] if it is invalid.
+    #[wasm_bindgen(js_name = setTag)]
+    pub fn js_set_tag(&mut self, tag: &str) -> Result<(), JsValue> {
+        TaggedBase64::validate_tag(tag)?;
+        self.set_tag_unchecked(tag);
+        Ok(())
+    }
+
+    /// Sets the value.
+    #[wasm_bindgen(js_name = setValue)]
+    pub fn js_set_value(&mut self, value: &[u8]) {
+        self.set_value(value);
+    }
+
+    /// Formats the value as a string of the form tag~value.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn js_to_string(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns true if both values have the same tag and value.
+    #[wasm_bindgen(js_name = equals)]
+    pub fn js_equals(&self, other: &TaggedBase64) -> bool {
+        self == other
+    }
+
+    /// Formats the value as a string for `JSON.stringify`.
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn js_to_json(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Computes the checksum of a tag and value, as
+/// [calc_checksum](TaggedBase64::calc_checksum) does. Exported to
+/// JavaScript as `calcChecksum`.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen(js_name = calcChecksum)]
+pub fn js_calc_checksum(tag: &str, value: &[u8]) -> u8 {
+    TaggedBase64::calc_checksum(tag, value)
+}
+
+/// Returns true if `tag` is a valid tag. Exported to JavaScript as
+/// `isValidTag`.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen(js_name = isValidTag)]
+pub fn js_is_valid_tag(tag: &str) -> bool {
+    TaggedBase64::validate_tag(tag).is_ok()
+}
+
+/// Checks a string of the form tag~value, returning `undefined` if it is
+/// valid and a [JsTb64Error] describing the problem otherwise. Exported to
+/// JavaScript as `validate`.
+///
+/// Unlike [JsTaggedBase64::parse], this does not throw, so form validation
+/// can show the [code](JsTb64Error::code) and
+/// [offset](JsTb64Error::offset) of the error inline as the user types.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+#[wasm_bindgen(js_name = validate)]
+pub fn js_validate(tb64: &str) -> Option<JsTb64Error> {
+    TaggedBase64::validate(tb64).err().map(JsTb64Error::from)
 }
 
 /// Trait for types whose serialization is not human-readable.
@@ -496,7 +4314,38 @@ impl JsTaggedBase64 {
 ///
 /// Rather than implement this trait manually, it is recommended to use the
 /// [macro@tagged] macro to specify a tag for your type. That macro also
-/// derives appropriate serde implementations for serializing as an opaque blob.
+/// derives appropriate serde implementations for serializing as an opaque blob,
+/// as well as `Display`, `FromStr`, and conversions to and from [TaggedBase64].
+/// Parsing and conversion from [TaggedBase64] fail with
+/// [InvalidTag](Tb64Error::InvalidTag) unless the tag is the type's tag.
 pub trait Tagged {
     fn tag() -> String;
 }
+
+/// Trait for fieldless enums whose variants are emitted as tagged markers.
+///
+/// Each variant is tagged with its name and carries its discriminant as a
+/// single byte value. Rather than implement this trait manually, derive it
+/// with [macro@AsTagged], which requires a `#[repr(u8)]` enum or one whose
+/// discriminants fit in a byte.
+///
+/// ```
+/// use tagged_base64::AsTagged;
+///
+/// #[derive(AsTagged)]
+/// #[repr(u8)]
+/// enum Event {
+///     Opened = 1,
+///     Closed = 2,
+/// }
+///
+/// assert_eq!(Event::Closed.tag(), "Closed");
+/// assert_eq!(Event::Closed.to_tagged().value(), [2]);
+/// ```
+pub trait AsTagged {
+    /// Gets the tag of this variant, its name.
+    fn tag(&self) -> &'static str;
+
+    /// Converts this variant to a TaggedBase64 holding its discriminant.
+    fn to_tagged(&self) -> TaggedBase64;
+}
@@ -1,8 +1,10 @@
 use clap::Parser;
+use std::fs::File;
 use std::io;
 use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::process::exit;
-use tagged_base64::TaggedBase64;
+use tagged_base64::{TaggedBase64, TB64_DELIM};
 
 #[derive(Parser)]
 #[command(
@@ -28,13 +30,80 @@ pub struct MainOpt {
     ///    `cat adventure.bin | tagged_base64 --tag ADVENTURE`
     #[arg(long = "tag")]
     pub tag: Option<String>,
+
+    /// File to read raw data from with --tag, instead of stdin.
+    ///
+    ///    `tagged_base64 --tag ADVENTURE --input adventure.bin`
+    #[arg(long = "input", short = 'i', requires = "tag")]
+    pub input: Option<PathBuf>,
+
+    /// Describe a TaggedBase64 string: its tag, length, checksum, and a hex
+    /// dump of the value.
+    ///
+    /// This also describes strings which do not parse, as far as possible.
+    ///    `tagged_base64 --inspect ADVENTURE~WFlaWllD`
+    #[arg(long = "inspect")]
+    pub inspect: Option<String>,
+}
+
+/// Prints `bytes` as rows of 16 hexadecimal bytes and their ASCII.
+fn hex_dump(bytes: &[u8]) {
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        println!("{:08x}  {:<47}  |{}|", row * 16, hex.join(" "), ascii);
+    }
+}
+
+/// Prints a description of `tb64_str`, returning whether it parsed.
+fn inspect(tb64_str: &str) -> bool {
+    match TaggedBase64::parse(tb64_str) {
+        Ok(v) => {
+            println!("tag:      {}", v.tag());
+            println!("length:   {} bytes", v.value().len());
+            println!("checksum: {:#04x} (valid)", v.checksum());
+            hex_dump(&v.value());
+            true
+        }
+        Err(e) => {
+            println!("error:    {}", e);
+            if let Some(class) = TaggedBase64::classify_failure(tb64_str) {
+                println!("cause:    {:?}", class);
+            }
+            if let Some((tag, value)) = tb64_str.split_once(TB64_DELIM) {
+                println!("tag:      {}", tag);
+                // Show what decodes, including the checksum byte, to help
+                // spot the damage.
+                if let Ok(bytes) = TaggedBase64::decode_raw(value) {
+                    println!(
+                        "length:   {} bytes and checksum",
+                        bytes.len().saturating_sub(1)
+                    );
+                    hex_dump(&bytes);
+                }
+            }
+            false
+        }
+    }
 }
 
 fn main() {
     let parsed = MainOpt::parse();
-    let tb64 = &parsed.tb64_str;
-    let tag = &parsed.tag;
-    if tb64.is_some() == tag.is_some() {
+    let modes = [
+        parsed.tb64_str.is_some(),
+        parsed.tag.is_some(),
+        parsed.inspect.is_some(),
+    ];
+    if modes.iter().filter(|&&m| m).count() != 1 {
         println!(
             "tagged_base64: one argument required\n\
              Try 'tagged_base64 --help' for more information."
@@ -51,9 +120,18 @@ fn main() {
                 exit(1);
             }
         };
+    } else if let Some(tb64_str) = &parsed.inspect {
+        exit(if inspect(tb64_str) { 0 } else { 1 });
     } else if let Some(tag) = &parsed.tag {
         let mut v = Vec::new();
-        io::stdin().read_to_end(&mut v).unwrap();
+        let read = match &parsed.input {
+            Some(path) => File::open(path).and_then(|mut f| f.read_to_end(&mut v)),
+            None => io::stdin().read_to_end(&mut v),
+        };
+        if let Err(e) = read {
+            print!("Error: {}", e);
+            exit(1);
+        }
         println!("{}", TaggedBase64::new(tag, &v).unwrap());
         exit(0);
     }
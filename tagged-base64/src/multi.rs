@@ -0,0 +1,100 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Composite values whose segments are checksummed individually.
+
+use crate::{TaggedBase64, Tb64Error, BASE64, TB64_DELIM};
+use ark_std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use base64::Engine;
+use core::{fmt, str::FromStr};
+
+/// A tag with several byte segments, written as `tag~seg1~seg2~...`.
+///
+/// Each segment is encoded like the value of a [TaggedBase64] with the same
+/// tag, followed by its own checksum, so `tag~seg1` is itself a valid
+/// TaggedBase64 string. Corruption is therefore localized: parsing reports
+/// the index of the first segment which fails to verify. The checksums do
+/// not cover the order of the segments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedBase64MultiChecked {
+    tag: String,
+    segments: Vec<Vec<u8>>,
+}
+
+impl TaggedBase64MultiChecked {
+    /// Constructs a value from a tag and at least one segment.
+    pub fn new(tag: &str, segments: &[&[u8]]) -> Result<Self, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        if segments.is_empty() {
+            return Err(Tb64Error::EmptyValue);
+        }
+        Ok(TaggedBase64MultiChecked {
+            tag: tag.to_string(),
+            segments: segments.iter().map(|s| s.to_vec()).collect(),
+        })
+    }
+
+    /// Parses a string of the form `tag~seg1~seg2~...`, verifying the
+    /// checksum of each segment.
+    ///
+    /// A segment which is not valid base64 or whose checksum does not match
+    /// is reported as [InvalidSegment](Tb64Error::InvalidSegment) with its
+    /// zero-based index.
+    pub fn parse(s: &str) -> Result<Self, Tb64Error> {
+        let mut parts = s.split(TB64_DELIM);
+        // `split` always yields at least one part.
+        let tag = parts.next().unwrap();
+        TaggedBase64::validate_tag(tag)?;
+        if tag.len() == s.len() {
+            return Err(Tb64Error::MissingDelimiter);
+        }
+        let segments = parts
+            .enumerate()
+            .map(|(index, part)| {
+                Self::decode_segment(tag, part).ok_or(Tb64Error::InvalidSegment { index })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TaggedBase64MultiChecked {
+            tag: tag.to_string(),
+            segments,
+        })
+    }
+
+    fn decode_segment(tag: &str, part: &str) -> Option<Vec<u8>> {
+        let mut bytes = BASE64.decode(part).ok()?;
+        let cs = bytes.pop()?;
+        (cs == TaggedBase64::calc_checksum(tag, &bytes)).then_some(bytes)
+    }
+
+    /// Gets the tag.
+    pub fn tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    /// Gets the segments, in order.
+    pub fn segments(&self) -> Vec<Vec<u8>> {
+        self.segments.clone()
+    }
+}
+
+impl fmt::Display for TaggedBase64MultiChecked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tag)?;
+        for segment in &self.segments {
+            let mut bytes = segment.clone();
+            bytes.push(TaggedBase64::calc_checksum(&self.tag, segment));
+            write!(f, "{}{}", TB64_DELIM, BASE64.encode(&bytes))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TaggedBase64MultiChecked {
+    type Err = Tb64Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
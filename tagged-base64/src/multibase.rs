@@ -0,0 +1,109 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Conversion to and from multibase strings holding multicodec values.
+//!
+//! A multibase string is a single character naming the encoding, followed
+//! by the encoded data. The data here is a multicodec: an unsigned varint
+//! code identifying the kind of value, followed by the value. The caller
+//! supplies the table of codes corresponding to tags.
+//!
+//! Multibase has no checksum, so the checksum is dropped on the way out and
+//! recomputed on the way in.
+
+use crate::{decode_hex, TaggedBase64, Tb64Error};
+use ark_std::{string::String, vec::Vec};
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+    Engine,
+};
+
+/// Multibase prefix of unpadded URL-safe base64, which is written by
+/// [TaggedBase64::to_multibase].
+const BASE64URL: char = 'u';
+
+/// Longest unsigned varint allowed by the multiformats specification.
+const MAX_VARINT_LEN: usize = 9;
+
+fn push_varint(mut n: u64, out: &mut Vec<u8>) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Splits a minimally encoded unsigned varint from the start of `bytes`.
+fn split_varint(bytes: &[u8]) -> Result<(u64, &[u8]), Tb64Error> {
+    let mut n = 0u64;
+    for (i, &b) in bytes.iter().enumerate().take(MAX_VARINT_LEN) {
+        n |= u64::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            if b == 0 && i > 0 {
+                return Err(Tb64Error::InvalidLength);
+            }
+            return Ok((n, &bytes[i + 1..]));
+        }
+    }
+    Err(Tb64Error::InvalidLength)
+}
+
+impl TaggedBase64 {
+    /// Converts this value to a multibase string in unpadded URL-safe
+    /// base64, prefixed with `u`, holding the multicodec code of the tag
+    /// and the value.
+    ///
+    /// `codecs` pairs tags with multicodec codes. A tag which is not in the
+    /// table is rejected with [TagNotAllowed](Tb64Error::TagNotAllowed).
+    ///
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// const CODECS: &[(&str, u64)] = &[("TX", 0x300001), ("NOTE", 0x300002)];
+    /// let tx = TaggedBase64::new("TX", b"foobar")?;
+    /// let multibase = tx.to_multibase(CODECS)?;
+    /// assert_eq!(multibase, "ugYDAAWZvb2Jhcg");
+    /// assert_eq!(TaggedBase64::from_multibase(&multibase, CODECS)?, tx);
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn to_multibase(&self, codecs: &[(&str, u64)]) -> Result<String, Tb64Error> {
+        let &(_, code) = codecs
+            .iter()
+            .find(|(tag, _)| *tag == self.tag.as_str())
+            .ok_or_else(|| Tb64Error::TagNotAllowed { tag: self.tag() })?;
+        let mut bytes = Vec::with_capacity(MAX_VARINT_LEN + self.value.len());
+        push_varint(code, &mut bytes);
+        bytes.extend_from_slice(&self.value);
+        let mut s = String::with_capacity(1 + bytes.len().div_ceil(3) * 4);
+        s.push(BASE64URL);
+        URL_SAFE_NO_PAD.encode_string(&bytes, &mut s);
+        Ok(s)
+    }
+
+    /// Parses a multibase string holding a multicodec value, looking up
+    /// the tag for its code in `codecs`.
+    ///
+    /// Base64 in either alphabet, padded or not (prefixes `u`, `U`, `m`,
+    /// and `M`), and hex (`f` and `F`) are understood. Other multibase
+    /// encodings are rejected with
+    /// [UnsupportedMultibase](Tb64Error::UnsupportedMultibase), and a code
+    /// which is not in the table with
+    /// [UnknownCodec](Tb64Error::UnknownCodec).
+    pub fn from_multibase(s: &str, codecs: &[(&str, u64)]) -> Result<TaggedBase64, Tb64Error> {
+        let mut chars = s.chars();
+        let prefix = chars.next().ok_or(Tb64Error::InvalidLength)?;
+        let data = chars.as_str();
+        let bytes = match prefix {
+            BASE64URL => URL_SAFE_NO_PAD.decode(data)?,
+            'U' => URL_SAFE.decode(data)?,
+            'm' => STANDARD_NO_PAD.decode(data)?,
+            'M' => STANDARD.decode(data)?,
+            'f' | 'F' => decode_hex(data)?,
+            _ => return Err(Tb64Error::UnsupportedMultibase { prefix }),
+        };
+        let (code, value) = split_varint(&bytes)?;
+        let &(tag, _) = codecs
+            .iter()
+            .find(|&&(_, c)| c == code)
+            .ok_or(Tb64Error::UnknownCodec { code })?;
+        TaggedBase64::new(tag, value)
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Values whose payload is a bundle of other tagged values.
+
+use crate::{push_varint, read_varint, TaggedBase64, Tb64Error};
+use ark_std::{boxed::Box, vec::Vec};
+
+impl TaggedBase64 {
+    /// Bundles several values into one, with the tag `tag`, so that they
+    /// travel as a single opaque token.
+    ///
+    /// Each inner value is stored in the binary form of
+    /// [to_bytes](Self::to_bytes), preceded by its length as an unsigned
+    /// LEB128 varint. The inner values keep their own tags and checksums,
+    /// and [decode_nested](Self::decode_nested) gives them back.
+    ///
+    /// ```
+    /// # use tagged_base64::{TaggedBase64, Tb64Error};
+    /// let address = TaggedBase64::new("ADDR", b"address bytes")?;
+    /// let memo = TaggedBase64::new("MEMO", b"for lunch")?;
+    /// let bundle = TaggedBase64::encode_nested("BUNDLE", &[address.clone(), memo.clone()])?;
+    ///
+    /// let parsed = TaggedBase64::parse(&bundle.to_string())?;
+    /// let [a, m]: [TaggedBase64; 2] = parsed
+    ///     .decode_nested()?
+    ///     .try_into()
+    ///     .map_err(|_| Tb64Error::InvalidLength)?;
+    /// assert_eq!((a, m), (address, memo));
+    /// # Ok::<(), Tb64Error>(())
+    /// ```
+    pub fn encode_nested(tag: &str, values: &[TaggedBase64]) -> Result<TaggedBase64, Tb64Error> {
+        let mut bundle = Vec::new();
+        for value in values {
+            let bytes = value.to_bytes();
+            push_varint(&mut bundle, bytes.len());
+            bundle.extend_from_slice(&bytes);
+        }
+        TaggedBase64::new(tag, &bundle)
+    }
+
+    /// Unpacks the values bundled by [encode_nested](Self::encode_nested),
+    /// in order.
+    ///
+    /// Each inner value is checked as [from_bytes](Self::from_bytes) checks
+    /// it, and one which fails is reported as
+    /// [InvalidElement](Tb64Error::InvalidElement) with its zero-based
+    /// index. A length which runs past the end of the payload is reported
+    /// as [InvalidLength](Tb64Error::InvalidLength). The outer tag is not
+    /// checked; compare it with [has_tag](Self::has_tag) first if it
+    /// matters.
+    pub fn decode_nested(&self) -> Result<Vec<TaggedBase64>, Tb64Error> {
+        let mut rest = self.payload();
+        let mut values = Vec::new();
+        while !rest.is_empty() {
+            let (len, header_len) = read_varint(rest)?;
+            let end = header_len
+                .checked_add(len)
+                .filter(|&end| end <= rest.len())
+                .ok_or(Tb64Error::InvalidLength)?;
+            let value = TaggedBase64::from_bytes(&rest[header_len..end]).map_err(|error| {
+                Tb64Error::InvalidElement {
+                    index: values.len(),
+                    error: Box::new(error),
+                }
+            })?;
+            values.push(value);
+            rest = &rest[end..];
+        }
+        Ok(values)
+    }
+}
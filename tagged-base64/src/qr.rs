@@ -0,0 +1,131 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! QR codes of the string form, with [qrcode] and [png].
+
+use crate::{TaggedBase64, Tb64Error};
+use qrcode::{render::svg, Color, EcLevel, QrCode};
+use std::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Error correction used for every code. Level M recovers from about 15%
+/// damage, enough for a code on a screen or a printed page.
+const QR_EC_LEVEL: EcLevel = EcLevel::M;
+
+/// Longest string a code holds at [QR_EC_LEVEL] in byte mode, used for the
+/// base64 form.
+const QR_MAX_BYTES: usize = 2331;
+
+/// Longest string a code holds at [QR_EC_LEVEL] in alphanumeric mode, used
+/// for the base32 form.
+const QR_MAX_ALPHANUMERIC: usize = 3391;
+
+/// Width of the blank margin around a code, in modules, as required by the
+/// QR code standard.
+const QUIET_ZONE: usize = 4;
+
+impl TaggedBase64 {
+    /// Renders the string form as a QR code in SVG, with 8 pixel modules.
+    ///
+    /// Fails with [ValueTooLong](Tb64Error::ValueTooLong) if the string
+    /// doesn't fit in a code.
+    /// ```
+    /// # use tagged_base64::TaggedBase64;
+    /// let svg = TaggedBase64::new("TX", b"foobar")?.to_qr_svg()?;
+    /// assert!(svg.contains("<svg"));
+    /// # Ok::<(), tagged_base64::Tb64Error>(())
+    /// ```
+    pub fn to_qr_svg(&self) -> Result<String, Tb64Error> {
+        let code = self.qr_code()?;
+        Ok(render_svg(&code))
+    }
+
+    /// Renders the string form as a QR code in PNG, with modules of `scale`
+    /// pixels, which must be at least 1.
+    ///
+    /// Fails with [ValueTooLong](Tb64Error::ValueTooLong) if the string
+    /// doesn't fit in a code.
+    pub fn to_qr_png(&self, scale: u32) -> Result<Vec<u8>, Tb64Error> {
+        let code = self.qr_code()?;
+        Ok(render_png(&code, scale))
+    }
+
+    /// Renders the base32 form of
+    /// [to_string_base32](Self::to_string_base32) in uppercase as a QR code
+    /// in SVG.
+    ///
+    /// Uppercase base32 is encoded in the alphanumeric mode of QR codes,
+    /// which is denser than the byte mode needed for base64, so the code is
+    /// smaller and easier to scan despite the longer string. Scanners read
+    /// the string back with [parse_base32](Self::parse_base32) or
+    /// [parse_any](Self::parse_any).
+    ///
+    /// Fails like [to_string_base32](Self::to_string_base32) if the tag
+    /// can't be written in base32, or with
+    /// [ValueTooLong](Tb64Error::ValueTooLong) if the string doesn't fit in
+    /// a code.
+    pub fn to_qr_svg_base32(&self) -> Result<String, Tb64Error> {
+        let code = self.qr_code_base32()?;
+        Ok(render_svg(&code))
+    }
+
+    /// Renders the base32 form as a QR code in PNG, as by
+    /// [to_qr_svg_base32](Self::to_qr_svg_base32), with modules of `scale`
+    /// pixels, which must be at least 1.
+    pub fn to_qr_png_base32(&self, scale: u32) -> Result<Vec<u8>, Tb64Error> {
+        let code = self.qr_code_base32()?;
+        Ok(render_png(&code, scale))
+    }
+
+    fn qr_code(&self) -> Result<QrCode, Tb64Error> {
+        qr_code(&self.to_string(), QR_MAX_BYTES)
+    }
+
+    fn qr_code_base32(&self) -> Result<QrCode, Tb64Error> {
+        qr_code(
+            &self.to_string_base32()?.to_ascii_uppercase(),
+            QR_MAX_ALPHANUMERIC,
+        )
+    }
+}
+
+/// Encodes `s` in a code, which holds at most `max` characters in the mode
+/// chosen for `s`. The strings encoded are ASCII, so being too long is the
+/// only possible error.
+fn qr_code(s: &str, max: usize) -> Result<QrCode, Tb64Error> {
+    QrCode::with_error_correction_level(s, QR_EC_LEVEL)
+        .map_err(|_| Tb64Error::ValueTooLong { len: s.len(), max })
+}
+
+fn render_svg(code: &QrCode) -> String {
+    code.render::<svg::Color>().module_dimensions(8, 8).build()
+}
+
+/// Encodes a code as an 8-bit grayscale PNG, with a quiet zone.
+fn render_png(code: &QrCode, scale: u32) -> Vec<u8> {
+    assert!(scale > 0, "QR code modules must be at least one pixel");
+    let scale = scale as usize;
+    let modules = code.width();
+    let size = (modules + 2 * QUIET_ZONE) * scale;
+    let mut pixels = vec![0xff; size * size];
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (x, y) = (i % modules + QUIET_ZONE, i / modules + QUIET_ZONE);
+            for row in y * scale..(y + 1) * scale {
+                pixels[row * size + x * scale..row * size + (x + 1) * scale].fill(0);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Writing to a vector can't fail.
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&pixels).unwrap();
+    writer.finish().unwrap();
+    png
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Random values for tests and demos, with [rand].
+//!
+//! ```
+//! # use rand::{distributions::Distribution, SeedableRng};
+//! # use tagged_base64::{TaggedBase64, TaggedBase64Distribution};
+//! let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0);
+//! let tx = TaggedBase64::random("TX", 32, &mut rng)?;
+//! assert_eq!(tx.tag(), "TX");
+//! assert_eq!(tx.value().len(), 32);
+//!
+//! let keys = TaggedBase64Distribution::new("KEY", 16)?;
+//! let batch: Vec<TaggedBase64> = keys.sample_iter(&mut rng).take(3).collect();
+//! assert!(batch.iter().all(|key| key.tag() == "KEY"));
+//! # Ok::<(), tagged_base64::Tb64Error>(())
+//! ```
+
+use crate::{TaggedBase64, Tb64Error};
+use ark_std::{
+    string::{String, ToString},
+    vec,
+};
+use rand::{distributions::Distribution, Rng};
+
+impl TaggedBase64 {
+    /// Constructs a value with the tag `tag` and `len` random bytes.
+    ///
+    /// Fails only if the tag is invalid.
+    pub fn random<R: Rng + ?Sized>(tag: &str, len: usize, rng: &mut R) -> Result<Self, Tb64Error> {
+        let mut value = vec![0; len];
+        rng.fill_bytes(&mut value);
+        TaggedBase64::new(tag, &value)
+    }
+}
+
+/// Values with a fixed tag and a fixed number of random bytes.
+///
+/// The tag is checked once, in [new](Self::new), so sampling can't fail.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedBase64Distribution {
+    tag: String,
+    len: usize,
+}
+
+impl TaggedBase64Distribution {
+    /// Samples values with the tag `tag` and `len` random bytes, failing if
+    /// the tag is invalid.
+    pub fn new(tag: &str, len: usize) -> Result<Self, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        Ok(TaggedBase64Distribution {
+            tag: tag.to_string(),
+            len,
+        })
+    }
+
+    /// The tag of the sampled values.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// The number of bytes in each sampled value.
+    pub fn value_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Distribution<TaggedBase64> for TaggedBase64Distribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> TaggedBase64 {
+        // The tag was validated by the constructor.
+        TaggedBase64::random(&self.tag, self.len, rng).unwrap()
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Parsing restricted to the tags an application knows about.
+
+use crate::{TaggedBase64, Tb64Error};
+use ark_std::{
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+
+/// The set of tags an application accepts, each optionally with the length
+/// its payload must have.
+///
+/// [parse](Self::parse) rejects anything else before it reaches the rest of
+/// the application, so a string pasted into the wrong field fails with an
+/// error naming the tag it has.
+///
+/// ```
+/// # use tagged_base64::{TagRegistry, TaggedBase64, Tb64Error};
+/// let registry = TagRegistry::new()
+///     .register("TX")
+///     .register_with_len("KEY", 32);
+///
+/// let tx = TaggedBase64::new("TX", b"payload")?;
+/// assert_eq!(registry.parse(&tx.to_string())?, tx);
+///
+/// let la = TaggedBase64::new("LA", b"payload")?;
+/// assert!(matches!(
+///     registry.parse(&la.to_string()),
+///     Err(Tb64Error::TagNotAllowed { .. })
+/// ));
+///
+/// let key = TaggedBase64::new("KEY", &[0; 16])?;
+/// assert!(matches!(
+///     registry.parse(&key.to_string()),
+///     Err(Tb64Error::WrongValueLength { expected: 32, found: 16 })
+/// ));
+/// # Ok::<(), Tb64Error>(())
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagRegistry {
+    tags: BTreeMap<String, Option<usize>>,
+}
+
+impl TagRegistry {
+    /// Creates a registry with no tags, which rejects everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts values with `tag` and a payload of any length.
+    ///
+    /// Registering a tag again replaces its expected length.
+    pub fn register(mut self, tag: &str) -> Self {
+        self.tags.insert(tag.to_string(), None);
+        self
+    }
+
+    /// Accepts values with `tag` and a payload of exactly `len` bytes.
+    ///
+    /// For extended values, the length is that of the
+    /// [payload](TaggedBase64::payload), without the header.
+    pub fn register_with_len(mut self, tag: &str, len: usize) -> Self {
+        self.tags.insert(tag.to_string(), Some(len));
+        self
+    }
+
+    /// Returns true if `tag` is registered.
+    pub fn contains(&self, tag: &str) -> bool {
+        self.tags.contains_key(tag)
+    }
+
+    /// Returns the payload length registered for `tag`, or `None` if the tag
+    /// is not registered or accepts any length.
+    pub fn expected_len(&self, tag: &str) -> Option<usize> {
+        self.tags.get(tag).copied().flatten()
+    }
+
+    /// Iterates over the registered tags in order.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.keys().map(String::as_str)
+    }
+
+    /// Checks that an already parsed value has a registered tag and the
+    /// registered payload length.
+    pub fn check(&self, tb64: &TaggedBase64) -> Result<(), Tb64Error> {
+        let tag = tb64.tag_ref();
+        let expected = self.tags.get(tag).ok_or_else(|| Tb64Error::TagNotAllowed {
+            tag: tag.to_string(),
+        })?;
+        match *expected {
+            Some(expected) if tb64.payload().len() != expected => {
+                Err(Tb64Error::WrongValueLength {
+                    expected,
+                    found: tb64.payload().len(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses a string of the form tag~value, requiring the tag to be
+    /// registered and the payload to have the registered length.
+    ///
+    /// The tag is checked before the value is decoded, so an unknown tag is
+    /// reported as such even if the rest of the string is also corrupt.
+    pub fn parse(&self, tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let tag = TaggedBase64::peek_tag(tb64)?;
+        if !self.contains(&tag) {
+            return Err(Tb64Error::TagNotAllowed {
+                tag: tag.into_owned(),
+            });
+        }
+        let parsed = TaggedBase64::parse(tb64)?;
+        self.check(&parsed)?;
+        Ok(parsed)
+    }
+}
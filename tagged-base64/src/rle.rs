@@ -0,0 +1,100 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! PackBits run-length coding, used by
+//! [TaggedBase64::to_string_compact](crate::TaggedBase64::to_string_compact).
+//!
+//! The input is split into packets, each introduced by a control byte `n`:
+//! - `0..=127`: the next `n + 1` bytes are copied literally;
+//! - `129..=255`: the next byte is repeated `257 - n` times;
+//! - `128` is never produced and is rejected when decoding.
+
+use crate::Tb64Error;
+use ark_std::vec::Vec;
+
+/// Longest run or literal that fits in a single packet.
+const MAX_PACKET: usize = 128;
+
+/// Shortest run worth breaking a literal for.
+const MIN_RUN: usize = 3;
+
+/// Returns the length of the run of equal bytes at the start of `data`, up
+/// to [MAX_PACKET].
+fn run_len(data: &[u8]) -> usize {
+    match data.first() {
+        Some(&b) => data
+            .iter()
+            .take(MAX_PACKET)
+            .take_while(|&&x| x == b)
+            .count(),
+        None => 0,
+    }
+}
+
+/// Appends the PackBits encoding of `data` to `out`.
+pub(crate) fn pack(data: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < data.len() {
+        let run = run_len(&data[i..]);
+        if run >= MIN_RUN {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+        } else {
+            let start = i;
+            while i < data.len() && i - start < MAX_PACKET && run_len(&data[i..]) < MIN_RUN {
+                i += 1;
+            }
+            out.push((i - start - 1) as u8);
+            out.extend_from_slice(&data[start..i]);
+        }
+    }
+}
+
+/// Computes the length of the decoding of PackBits data without decoding
+/// it, failing like [unpack] on malformed data.
+pub(crate) fn unpacked_len(data: &[u8]) -> Result<usize, Tb64Error> {
+    let mut len = 0usize;
+    let mut i = 0;
+    while let Some(&n) = data.get(i) {
+        match n {
+            0..=127 => {
+                len += n as usize + 1;
+                i += n as usize + 2;
+            }
+            128 => return Err(Tb64Error::InvalidData),
+            _ => {
+                len += 257 - n as usize;
+                i += 2;
+            }
+        }
+    }
+    // The last packet must not run past the end.
+    if i > data.len() {
+        return Err(Tb64Error::InvalidData);
+    }
+    Ok(len)
+}
+
+/// Decodes PackBits data produced by [pack].
+pub(crate) fn unpack(data: &[u8]) -> Result<Vec<u8>, Tb64Error> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while let Some(&n) = data.get(i) {
+        i += 1;
+        match n {
+            0..=127 => {
+                let len = n as usize + 1;
+                let literal = data.get(i..i + len).ok_or(Tb64Error::InvalidData)?;
+                out.extend_from_slice(literal);
+                i += len;
+            }
+            128 => return Err(Tb64Error::InvalidData),
+            _ => {
+                let &b = data.get(i).ok_or(Tb64Error::InvalidData)?;
+                out.resize(out.len() + 257 - n as usize, b);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
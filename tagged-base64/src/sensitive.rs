@@ -0,0 +1,106 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Values which must not appear in logs.
+
+use crate::{TaggedBase64, Tb64Error, TB64_DELIM};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A value holding secret material, such as a private key or seed, whose
+/// `Debug` and `Display` show only the tag and the length of the value,
+/// as `KEY~<redacted, 32 bytes>`.
+///
+/// Wrapping a field in this type keeps it out of derived `Debug` output
+/// and tracing. The value is only reachable through
+/// [expose](Self::expose) and [into_inner](Self::into_inner), so code
+/// reading it is easy to find. Serialization is unchanged, since writing a
+/// secret to storage is deliberate.
+///
+/// ```
+/// # use tagged_base64::{SensitiveTaggedBase64, TaggedBase64};
+/// #[derive(Debug)]
+/// struct Wallet {
+///     seed: SensitiveTaggedBase64,
+/// }
+///
+/// let wallet = Wallet {
+///     seed: TaggedBase64::new("SEED", &[7; 32])?.into(),
+/// };
+/// assert_eq!(
+///     format!("{:?}", wallet),
+///     "Wallet { seed: SEED~<redacted, 32 bytes> }"
+/// );
+/// assert_eq!(wallet.seed.expose().value(), [7; 32]);
+/// # Ok::<(), tagged_base64::Tb64Error>(())
+/// ```
+#[derive(Clone, Eq, PartialEq)]
+pub struct SensitiveTaggedBase64(TaggedBase64);
+
+impl SensitiveTaggedBase64 {
+    /// Wraps a value.
+    pub fn new(tb64: TaggedBase64) -> Self {
+        SensitiveTaggedBase64(tb64)
+    }
+
+    /// Gets the tag, which is not redacted.
+    pub fn tag(&self) -> &str {
+        self.0.tag_ref()
+    }
+
+    /// Borrows the wrapped value, including the secret.
+    pub fn expose(&self) -> &TaggedBase64 {
+        &self.0
+    }
+
+    /// Unwraps the value, including the secret.
+    pub fn into_inner(self) -> TaggedBase64 {
+        self.0
+    }
+}
+
+impl From<TaggedBase64> for SensitiveTaggedBase64 {
+    fn from(tb64: TaggedBase64) -> Self {
+        SensitiveTaggedBase64(tb64)
+    }
+}
+
+impl FromStr for SensitiveTaggedBase64 {
+    type Err = Tb64Error;
+
+    fn from_str(s: &str) -> Result<Self, Tb64Error> {
+        TaggedBase64::parse(s).map(SensitiveTaggedBase64)
+    }
+}
+
+impl fmt::Display for SensitiveTaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}<redacted, {} bytes>",
+            self.tag(),
+            TB64_DELIM,
+            self.0.value_ref().len()
+        )
+    }
+}
+
+impl fmt::Debug for SensitiveTaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SensitiveTaggedBase64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SensitiveTaggedBase64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TaggedBase64::deserialize(deserializer).map(SensitiveTaggedBase64)
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Serde adapter for byte fields.
+//!
+//! `#[serde(with = ...)]` names a module, so the tag is supplied by declaring
+//! one with [tagged_bytes](crate::tagged_bytes). A field using it serializes
+//! exactly as a [TaggedBase64] with that tag would: as a string in
+//! human-readable formats such as JSON, and in the compact binary form
+//! otherwise. Deserialization rejects values with a different tag.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use tagged_base64::TaggedBase64;
+//!
+//! tagged_base64::tagged_bytes!(mod tx_bytes, "TX");
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Transfer {
+//!     #[serde(with = "tx_bytes")]
+//!     id: [u8; 6],
+//!     #[serde(with = "tx_bytes")]
+//!     parent: Vec<u8>,
+//! }
+//!
+//! let transfer = Transfer { id: *b"foobar", parent: vec![] };
+//! let json = serde_json::to_string(&transfer).unwrap();
+//! assert_eq!(
+//!     json,
+//!     format!(r#"{{"id":"TX~Zm9vYmFy1w","parent":"{}"}}"#, TaggedBase64::new("TX", b"").unwrap()),
+//! );
+//! ```
+
+use crate::{TaggedBase64, Tb64Error};
+use ark_std::{format, vec::Vec};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Serialize};
+
+pub use serde::{Deserializer, Serializer};
+
+#[doc(hidden)]
+pub use ark_std::vec::Vec as __Vec;
+
+/// Serializes `bytes` as a [TaggedBase64] with the tag `tag`.
+pub fn serialize<S>(tag: &str, bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let tb64 = TaggedBase64::new(tag, bytes).map_err(S::Error::custom)?;
+    tb64.serialize(serializer)
+}
+
+/// Deserializes a [TaggedBase64] with the tag `tag` and converts its value.
+///
+/// Any type convertible from `Vec<u8>` can be produced, including `Vec<u8>`
+/// itself and fixed size arrays, which fail on a value of the wrong length.
+pub fn deserialize<'de, D, T>(tag: &str, deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<Vec<u8>>,
+{
+    let tb64 = TaggedBase64::deserialize(deserializer)?;
+    if tb64.tag_ref() != tag {
+        return Err(D::Error::custom(Tb64Error::InvalidTag));
+    }
+    // An extended value, such as a timestamped one, contributes its payload.
+    let bytes = if tb64.is_extended() {
+        tb64.payload().to_vec()
+    } else {
+        tb64.into_value()
+    };
+    let len = bytes.len();
+    T::try_from(bytes).map_err(|_| D::Error::custom(format!("unexpected value length {}", len)))
+}
+
+/// Declares a module for `#[serde(with = ...)]` which serializes byte fields
+/// as tagged base64 with a fixed tag.
+///
+/// `tagged_bytes!(mod tx_bytes, "TX")` declares `tx_bytes`, whose
+/// `serialize` accepts any `AsRef<[u8]>` field, and whose `deserialize`
+/// produces any type convertible from `Vec<u8>`. A visibility may precede
+/// `mod`. See [serde_bytes](crate::serde_bytes) for an example.
+#[macro_export]
+macro_rules! tagged_bytes {
+    ($vis:vis mod $name:ident, $tag:expr) => {
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub fn serialize<S, T>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::serde_bytes::Serializer,
+                T: AsRef<[u8]> + ?Sized,
+            {
+                $crate::serde_bytes::serialize($tag, bytes.as_ref(), serializer)
+            }
+
+            pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+            where
+                D: $crate::serde_bytes::Deserializer<'de>,
+                T: TryFrom<$crate::serde_bytes::__Vec<u8>>,
+            {
+                $crate::serde_bytes::deserialize($tag, deserializer)
+            }
+        }
+    };
+}
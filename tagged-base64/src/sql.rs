@@ -0,0 +1,85 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Diesel support.
+//!
+//! A [TaggedBase64] is stored in a `Text` column as its string form, and
+//! its checksum is verified when it is loaded. Loading parses with
+//! [parse_untrusted](TaggedBase64::parse_untrusted), so a column holding a
+//! value longer than
+//! [TB64_UNTRUSTED_MAX_DECODED_LEN](crate::TB64_UNTRUSTED_MAX_DECODED_LEN)
+//! fails to load. Writing requires a backend
+//! feature, `diesel-postgres` or `diesel-sqlite`; reading works with any
+//! backend.
+//!
+//! Tables which keep the tag and the raw bytes in separate columns, for
+//! instance `Text` and `Bytea` columns kept from before a migration, can be
+//! loaded through [TaggedColumns].
+
+use crate::TaggedBase64;
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql, FromStaticSqlRow, Queryable},
+    sql_types::{Binary, Text},
+};
+use std::string::String;
+use std::vec::Vec;
+
+impl<DB: Backend> FromSql<Text, DB> for TaggedBase64
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(TaggedBase64::parse_untrusted(&s)?)
+    }
+}
+
+#[cfg(feature = "diesel-postgres")]
+impl diesel::serialize::ToSql<Text, diesel::pg::Pg> for TaggedBase64 {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> diesel::serialize::Result {
+        use std::io::Write;
+        write!(out, "{}", self)?;
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
+#[cfg(feature = "diesel-sqlite")]
+impl diesel::serialize::ToSql<Text, diesel::sqlite::Sqlite> for TaggedBase64 {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, diesel::sqlite::Sqlite>,
+    ) -> diesel::serialize::Result {
+        use std::string::ToString;
+        out.set_value(self.to_string());
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
+/// A [TaggedBase64] loaded from a tag column of type `Text` and a value
+/// column of type `Binary` (`Bytea` in Postgres).
+///
+/// No checksum is stored in this layout, so only the tag is checked. To
+/// write such a row, bind [tag_ref](TaggedBase64::tag_ref) and
+/// [value_ref](TaggedBase64::value_ref) to the two columns.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedColumns(pub TaggedBase64);
+
+impl<DB: Backend> Queryable<(Text, Binary), DB> for TaggedColumns
+where
+    (String, Vec<u8>): FromStaticSqlRow<(Text, Binary), DB>,
+{
+    type Row = (String, Vec<u8>);
+
+    fn build((tag, value): Self::Row) -> deserialize::Result<Self> {
+        Ok(TaggedColumns(TaggedBase64::new(&tag, &value)?))
+    }
+}
+
+impl From<TaggedColumns> for TaggedBase64 {
+    fn from(columns: TaggedColumns) -> Self {
+        columns.0
+    }
+}
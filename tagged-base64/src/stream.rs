@@ -0,0 +1,290 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Encoding and decoding of large values written to an [io::Write] or read
+//! from an [io::Read] incrementally.
+
+use crate::{crc, TaggedBase64, Tb64Error, BASE64, TB64_DELIM, TB64_MAX_TAG_LEN};
+use base64::Engine;
+use std::{
+    collections::VecDeque,
+    io,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Number of base64 characters read at a time. A multiple of 4, so reads
+/// split the input on group boundaries.
+const CHUNK_LEN: usize = 4096;
+
+/// Iterator over the bytes of a value decoded from a reader, returned by
+/// [TaggedBase64::decode_streaming].
+pub struct StreamingDecoder<R> {
+    reader: R,
+    crc: u8,
+    len: usize,
+    /// Characters read but not yet decoded, fewer than a full group.
+    pending: Vec<u8>,
+    /// Bytes decoded but not yet returned. The last of them may be the
+    /// checksum, so one is always held back until the input ends.
+    decoded: VecDeque<u8>,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: io::Read> StreamingDecoder<R> {
+    fn new(tag: &str, reader: R) -> Self {
+        let crc = crc::crc8(0, tag.as_bytes());
+        StreamingDecoder {
+            reader,
+            crc,
+            len: 0,
+            pending: Vec::new(),
+            decoded: VecDeque::new(),
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Reads and decodes the next chunk of input.
+    fn fill(&mut self) -> Result<(), Tb64Error> {
+        let start = self.pending.len();
+        self.pending.resize(start + CHUNK_LEN, 0);
+        let n = loop {
+            match self.reader.read(&mut self.pending[start..]) {
+                Ok(n) => break n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(io_error(err)),
+            }
+        };
+        self.pending.truncate(start + n);
+        self.eof = n == 0;
+
+        // Decode every complete group, or everything once the input ends.
+        let groups = if self.eof {
+            self.pending.len()
+        } else {
+            self.pending.len() / 4 * 4
+        };
+        let bytes = BASE64.decode(&self.pending[..groups])?;
+        self.decoded.extend(bytes);
+        self.pending.drain(..groups);
+        Ok(())
+    }
+
+    /// Checks the held-back checksum once the input has ended.
+    fn finish(&mut self) -> Result<(), Tb64Error> {
+        let cs = self.decoded.pop_front().ok_or(Tb64Error::MissingChecksum)?;
+        if cs != self.crc ^ (self.len as u8) {
+            return Err(Tb64Error::InvalidChecksum);
+        }
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Iterator for StreamingDecoder<R> {
+    type Item = Result<u8, Tb64Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        while self.decoded.len() < 2 && !self.eof {
+            if let Err(err) = self.fill() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        if self.decoded.len() >= 2 {
+            let b = self.decoded.pop_front()?;
+            self.crc = crc::crc8(self.crc, &[b]);
+            self.len += 1;
+            return Some(Ok(b));
+        }
+        self.done = true;
+        self.finish().err().map(Err)
+    }
+}
+
+impl TaggedBase64 {
+    /// Decodes the base64 portion of a plain value with the given tag,
+    /// read incrementally from `reader`.
+    ///
+    /// The returned iterator yields the value bytes as they are decoded,
+    /// without holding the whole value in memory, and ends with an error if
+    /// the input is not valid base64 or the checksum does not match. Since
+    /// the checksum follows the value, a corrupted value is only detected
+    /// once all of it has been read.
+    pub fn decode_streaming<R: io::Read>(tag: &str, reader: R) -> StreamingDecoder<R> {
+        StreamingDecoder::new(tag, reader)
+    }
+}
+
+/// Writer which encodes the bytes written to it as the value of a plain
+/// TaggedBase64, writing the string form to an inner writer.
+///
+/// The tag and delimiter are written on construction and the checksum by
+/// [finish](Self::finish), so the value is never held in memory. The output
+/// is incomplete, and does not parse, until `finish` is called.
+pub struct TaggedBase64Writer<W: io::Write> {
+    writer: W,
+    crc: u8,
+    len: usize,
+    /// Bytes written but not yet encoded, fewer than a full group.
+    pending: Vec<u8>,
+    /// Characters encoded but not yet passed on to `writer`.
+    encoded: Vec<u8>,
+}
+
+impl<W: io::Write> TaggedBase64Writer<W> {
+    /// Checks the tag and writes it, followed by the delimiter, to `writer`.
+    pub fn new(tag: &str, mut writer: W) -> Result<Self, Tb64Error> {
+        TaggedBase64::validate_tag(tag)?;
+        write!(writer, "{}{}", tag, TB64_DELIM).map_err(io_error)?;
+        let crc = crc::crc8(0, tag.as_bytes());
+        Ok(TaggedBase64Writer {
+            writer,
+            crc,
+            len: 0,
+            pending: Vec::with_capacity(CHUNK_LEN),
+            encoded: Vec::with_capacity(CHUNK_LEN),
+        })
+    }
+
+    /// Passes on the encoded characters, keeping any the inner writer has
+    /// not taken if it fails, so that they are retried by the next call.
+    fn write_encoded(&mut self) -> io::Result<()> {
+        while !self.encoded.is_empty() {
+            match self.writer.write(&self.encoded) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.encoded.drain(..n);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the rest of the value and the checksum, returning the inner
+    /// writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_encoded()?;
+        let cs = self.crc ^ (self.len as u8);
+        self.pending.push(cs);
+        let encoded = BASE64.encode(&self.pending);
+        self.writer.write_all(encoded.as_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: io::Write> io::Write for TaggedBase64Writer<W> {
+    /// Takes all of `buf`, passing on the characters encoded by earlier
+    /// calls first. If the inner writer fails, none of `buf` is taken, and
+    /// the call may be retried.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_encoded()?;
+        self.crc = crc::crc8(self.crc, buf);
+        self.len = self.len.wrapping_add(buf.len());
+        self.pending.extend_from_slice(buf);
+        // Encode every complete group, keeping the remainder for later. The
+        // characters are passed on by the next call, since `buf` is taken
+        // by now and a failure could no longer be reported as not taking
+        // it.
+        let groups = self.pending.len() / 3 * 3;
+        if groups >= CHUNK_LEN / 4 * 3 {
+            let encoded = BASE64.encode(&self.pending[..groups]);
+            self.pending.drain(..groups);
+            self.encoded.extend_from_slice(encoded.as_bytes());
+        }
+        Ok(buf.len())
+    }
+
+    /// Flushes the inner writer. Up to a chunk of the value may still be
+    /// buffered, to keep the encoding aligned on whole groups.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_encoded()?;
+        self.writer.flush()
+    }
+}
+
+/// Reader which decodes the value of a plain TaggedBase64 from the string
+/// form read from an inner reader.
+///
+/// The tag is read on construction. Reads then return the value, and fail
+/// with [io::ErrorKind::InvalidData] if the input is not valid base64 or, at
+/// the end, if the checksum does not match. Wrap an unbuffered reader in an
+/// [io::BufReader], since the tag is read a byte at a time.
+pub struct TaggedBase64Reader<R: io::Read> {
+    tag: String,
+    decoder: StreamingDecoder<R>,
+    /// An error hit after part of a read was filled, reported by the next
+    /// read.
+    error: Option<Tb64Error>,
+}
+
+impl<R: io::Read> TaggedBase64Reader<R> {
+    /// Reads and checks the tag and delimiter from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, Tb64Error> {
+        let mut tag = Vec::new();
+        loop {
+            let mut byte = [0];
+            match reader.read(&mut byte) {
+                Ok(0) => return Err(Tb64Error::MissingDelimiter),
+                Ok(_) if byte[0] == TB64_DELIM as u8 => break,
+                Ok(_) if tag.len() == TB64_MAX_TAG_LEN => {
+                    return Err(Tb64Error::TagTooLong { len: tag.len() + 1 })
+                }
+                Ok(_) => tag.push(byte[0]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(io_error(err)),
+            }
+        }
+        let tag = String::from_utf8(tag).map_err(|_| Tb64Error::NonAsciiTag)?;
+        TaggedBase64::validate_tag(&tag)?;
+        let decoder = StreamingDecoder::new(&tag, reader);
+        Ok(TaggedBase64Reader {
+            tag,
+            decoder,
+            error: None,
+        })
+    }
+
+    /// Gets the tag read from the input.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+}
+
+impl<R: io::Read> io::Read for TaggedBase64Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(err) = self.error.take() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        let mut n = 0;
+        while n < buf.len() {
+            match self.decoder.next() {
+                Some(Ok(b)) => {
+                    buf[n] = b;
+                    n += 1;
+                }
+                Some(Err(err)) if n == 0 => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err))
+                }
+                Some(Err(err)) => {
+                    self.error = Some(err);
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok(n)
+    }
+}
+
+fn io_error(err: io::Error) -> Tb64Error {
+    Tb64Error::Io {
+        message: err.to_string(),
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Conformance checks for downstream test suites.
+//!
+//! A crate with types implementing [Tagged] can run the same checks as this
+//! crate does on its own values, rather than copying them:
+//!
+//! ```
+//! # use rand::SeedableRng;
+//! use tagged_base64::{test_utils, TaggedBase64};
+//!
+//! let tb64 = TaggedBase64::new("TX", b"foobar").unwrap();
+//! test_utils::assert_roundtrip(&tb64);
+//!
+//! let mut rng = rand_chacha::ChaChaRng::seed_from_u64(0);
+//! let corrupted = test_utils::corrupt_one_char(&tb64.to_string(), &mut rng);
+//! assert!(TaggedBase64::parse(&corrupted).is_err());
+//! ```
+
+use crate::{Tagged, TaggedBase64, Tb64Error, TB64_DELIM};
+use ark_std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+    string::{String, ToString},
+    vec::Vec,
+};
+use rand::Rng;
+
+/// Characters which never appear in a valid string, substituted by
+/// [corrupt_one_char] along with the tag characters and the delimiter.
+const INVALID_CHARS: &[char] = &['+', '/', '=', '.', ' '];
+
+/// Checks that a value survives every conversion to and from other forms:
+/// its string form, parsing and validating that string, and the compact
+/// binary form. Panics if any of them changes it.
+pub fn assert_roundtrip(tb64: &TaggedBase64) {
+    assert!(tb64.verify_checksum(), "checksum mismatch in {}", tb64);
+    let s = tb64.to_string();
+    assert_eq!(&TaggedBase64::parse(&s).unwrap(), tb64, "parsing {}", s);
+    assert_eq!(&TaggedBase64::from_str(&s).unwrap(), tb64, "parsing {}", s);
+    TaggedBase64::validate(&s).unwrap();
+    assert_eq!(TaggedBase64::peek_tag(&s).unwrap(), tb64.tag_ref());
+    assert_eq!(
+        &TaggedBase64::from_bytes(&tb64.to_bytes()).unwrap(),
+        tb64,
+        "binary form of {}",
+        s
+    );
+}
+
+/// Checks the conversions of a type implementing [Tagged], such as one
+/// declared with [macro@crate::tagged]: its string form has the type's tag
+/// and parses back to `value`, and strings with another tag are rejected
+/// with [InvalidTag](Tb64Error::InvalidTag).
+pub fn assert_tagged_roundtrip<T>(value: &T)
+where
+    T: Tagged + Debug + Display + FromStr<Err = Tb64Error> + PartialEq,
+    for<'a> &'a T: Into<TaggedBase64>,
+{
+    let tb64: TaggedBase64 = value.into();
+    assert_eq!(tb64.tag(), T::tag());
+    assert_eq!(value.to_string(), tb64.to_string());
+    assert_roundtrip(&tb64);
+    assert_eq!(&T::from_str(&tb64.to_string()).unwrap(), value);
+
+    let other_tag = if T::tag() == "X" { "Y" } else { "X" };
+    let other = TaggedBase64::new(other_tag, tb64.value_ref()).unwrap();
+    assert!(matches!(
+        T::from_str(&other.to_string()),
+        Err(Tb64Error::InvalidTag)
+    ));
+}
+
+/// Substitutes one character of `s`, chosen at random, so that the result
+/// does not parse.
+///
+/// A single substitution in a valid string is caught by the checksum, except
+/// occasionally one of the delimiter. Such substitutions are discarded and
+/// another is chosen. Panics if `s` is empty.
+pub fn corrupt_one_char<R: Rng + ?Sized>(s: &str, rng: &mut R) -> String {
+    assert!(!s.is_empty(), "can't corrupt an empty string");
+    let replacements = tag_chars()
+        .chain([TB64_DELIM])
+        .chain(INVALID_CHARS.iter().copied())
+        .collect::<Vec<_>>();
+    let chars = s.chars().collect::<Vec<_>>();
+    loop {
+        let i = rng.gen_range(0..chars.len());
+        let c = replacements[rng.gen_range(0..replacements.len())];
+        if chars[i] == c {
+            continue;
+        }
+        let corrupted = chars
+            .iter()
+            .enumerate()
+            .map(|(j, &d)| if j == i { c } else { d })
+            .collect::<String>();
+        if TaggedBase64::parse(&corrupted).is_err() {
+            return corrupted;
+        }
+    }
+}
+
+/// Every character allowed in a tag, in ASCII order.
+pub fn tag_chars() -> impl Iterator<Item = char> {
+    (0..=127u8)
+        .map(char::from)
+        .filter(|&c| TaggedBase64::is_safe_base64_ascii(c))
+}
+
+/// Every ASCII character not allowed in a tag, in ASCII order.
+pub fn invalid_tag_chars() -> impl Iterator<Item = char> {
+    (0..=127u8)
+        .map(char::from)
+        .filter(|&c| !TaggedBase64::is_safe_base64_ascii(c))
+}
+
+/// Every valid tag of at most `max_len` characters, shortest first,
+/// starting with the empty tag.
+///
+/// There are 64 to the power `max_len` tags of the longest length, so this
+/// is only practical for lengths of 2 or 3.
+pub fn all_tags(max_len: usize) -> impl Iterator<Item = String> {
+    let chars = tag_chars().collect::<Vec<_>>();
+    (0..=max_len).flat_map(move |len| {
+        let chars = chars.clone();
+        let count = 64usize
+            .checked_pow(len as u32)
+            .expect("too many tags to enumerate");
+        (0..count).map(move |mut n| {
+            (0..len)
+                .map(|_| {
+                    let c = chars[n % 64];
+                    n /= 64;
+                    c
+                })
+                .collect()
+        })
+    })
+}
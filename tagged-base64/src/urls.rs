@@ -0,0 +1,71 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Carrying values in URLs with the [url] crate.
+//!
+//! The string form needs no percent-encoding anywhere in a URL, but
+//! `application/x-www-form-urlencoded` serializers, including
+//! [Url::query_pairs_mut], encode the delimiter as `%7E` regardless, and some
+//! proxies and HTTP clients do the same. These helpers write the delimiter
+//! as is, and accept values whether or not they were encoded on the way.
+//!
+//! ```
+//! # use tagged_base64::{urls, TaggedBase64};
+//! # use url::Url;
+//! let tb64 = TaggedBase64::new("TX", b"foobar")?;
+//! let mut url = Url::parse("https://example.com/tx?page=2").unwrap();
+//! urls::append_query_param(&mut url, "id", &tb64);
+//! assert_eq!(url.as_str(), "https://example.com/tx?page=2&id=TX~Zm9vYmFy1w");
+//! assert_eq!(urls::from_query(&url, "id")?, tb64);
+//!
+//! let proxied = Url::parse("https://example.com/tx/TX%7EZm9vYmFy1w").unwrap();
+//! let segment = proxied.path_segments().unwrap().next_back().unwrap();
+//! assert_eq!(urls::decode_component(segment)?, tb64);
+//! # Ok::<(), tagged_base64::Tb64Error>(())
+//! ```
+
+use crate::{TaggedBase64, Tb64Error};
+use std::{
+    format,
+    string::{String, ToString},
+};
+use url::{form_urlencoded, Url};
+
+/// Appends `key=value` to the query of `url`, with the string form of
+/// `tb64` as the value, keeping any existing parameters.
+///
+/// The key is form-encoded as usual. The value is written as is, since it
+/// only contains characters which are allowed in a query.
+pub fn append_query_param(url: &mut Url, key: &str, tb64: &TaggedBase64) {
+    let key: String = form_urlencoded::byte_serialize(key.as_bytes()).collect();
+    let pair = format!("{}={}", key, tb64);
+    let query = match url.query() {
+        Some(query) if !query.is_empty() => format!("{}&{}", query, pair),
+        _ => pair,
+    };
+    url.set_query(Some(&query));
+}
+
+/// Parses the value of the first query parameter named `key` in `url`.
+///
+/// Percent-encoding of the value, such as `%7E` for the delimiter, is
+/// reversed first. The value is parsed with
+/// [parse_untrusted](TaggedBase64::parse_untrusted), since URLs usually come
+/// from outside. Fails with
+/// [MissingQueryParam](Tb64Error::MissingQueryParam) if there is no such
+/// parameter.
+pub fn from_query(url: &Url, key: &str) -> Result<TaggedBase64, Tb64Error> {
+    let (_, value) =
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .ok_or_else(|| Tb64Error::MissingQueryParam {
+                key: key.to_string(),
+            })?;
+    TaggedBase64::parse_untrusted(&value)
+}
+
+/// Parses a component of a URL, such as a path segment, which may have been
+/// percent-encoded on the way, as by
+/// [parse_percent_encoded](TaggedBase64::parse_percent_encoded).
+pub fn decode_component(component: &str) -> Result<TaggedBase64, Tb64Error> {
+    TaggedBase64::parse_percent_encoded(component)
+}
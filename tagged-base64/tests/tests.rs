@@ -4,12 +4,17 @@ use ark_serialize::*;
 use base64::engine::Engine;
 use quickcheck_macros::quickcheck;
 
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::convert::TryInto;
 use std::str;
 use tagged_base64::*;
 
 #[cfg(target_arch = "wasm32")]
-use {wasm_bindgen::JsValue, wasm_bindgen_test::*};
+use {
+    wasm_bindgen::{JsCast, JsValue},
+    wasm_bindgen_test::*,
+};
 
 // Run WASM tests like this
 //    wasm-pack test --headless --firefox --chrome
@@ -296,6 +301,9 @@ fn tag_setter() {
     tb64.set_tag(tag);
     assert_eq!(tb64.tag(), tag);
     assert_eq!(tb64.value(), bits);
+    // The stored checksum is recomputed for the new tag.
+    assert_eq!(tb64, TaggedBase64::new(tag, bits).unwrap());
+    assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -317,6 +325,9 @@ fn value_setter() {
     tb64.set_value(new_bits);
     assert_eq!(tb64.tag(), tag);
     assert_eq!(tb64.value(), new_bits);
+    // The stored checksum is recomputed for the new value.
+    assert_eq!(tb64, TaggedBase64::new(tag, new_bits).unwrap());
+    assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -355,19 +366,88 @@ fn test_empty_value() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn test_js_new_error() {
-    match JsTaggedBase64::new("~", b"oops!") {
-        Err(e) => assert_eq!(e, to_jsvalue("An invalid character was found in the tag.")),
-        _ => assert!(false),
-    }
+    let e = JsTaggedBase64::new("~", b"oops!").unwrap_err();
+    assert!(e.is_object() && !e.is_string());
+    // The fields are getters of the JsTb64Error, as JavaScript reads them.
+    let field = |name: &str| js_sys::Reflect::get(&e, &JsValue::from_str(name)).unwrap();
+    assert_eq!(field("code").as_string().unwrap(), "INVALID_TAG_CHAR");
+    assert_eq!(field("offset").as_f64(), Some(0.0));
+    assert_eq!(field("char").as_string().unwrap(), "~");
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn wasm_exported_methods() {
+    let mut tb64 = JsTaggedBase64::parse("TX~Zm9vYmFy1w").unwrap();
+    assert_eq!(tb64.js_tag(), "TX");
+    assert_eq!(tb64.js_value(), b"foobar");
+    assert_eq!(tb64.js_to_string(), "TX~Zm9vYmFy1w");
+    assert!(tb64.js_equals(&TaggedBase64::new("TX", b"foobar").unwrap()));
+
+    assert!(tb64.js_set_tag("T~X").is_err());
+    assert_eq!(tb64.js_tag(), "TX");
+    tb64.js_set_tag("TY").unwrap();
+    tb64.js_set_value(b"baz");
+    assert!(tb64.js_equals(&TaggedBase64::new("TY", b"baz").unwrap()));
+
+    let js = JsTaggedBase64::new("TX", b"foobar").unwrap();
+    assert!(js.equals(&JsTaggedBase64::new("TX", b"foobar").unwrap()));
+    assert!(!js.equals(&JsTaggedBase64::new("TX", b"foobaz").unwrap()));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn wasm_constructor_values() {
+    let expected = JsTaggedBase64::new("TX", b"foobar").unwrap();
+    let from_str = JsTaggedBase64::from_js("TX", JsValue::from_str("foobar").unchecked_into());
+    assert!(from_str.unwrap().equals(&expected));
+    assert!(JsTaggedBase64::from_js("TX", JsValue::from_f64(1.0).unchecked_into()).is_err());
+    assert_eq!(expected.to_json(), "TX~Zm9vYmFy1w");
+    assert_eq!(
+        JsTaggedBase64::parse(&expected.to_json())
+            .unwrap()
+            .js_to_json(),
+        "TX~Zm9vYmFy1w"
+    );
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn wasm_error_to_string() {
+    let error = JsTb64Error::from(Tb64Error::from(base64::DecodeError::InvalidByte(66, 42)));
+    assert_eq!(error.code(), "BASE64");
     assert_eq!(
-        JsValue::from(Tb64Error::from(base64::DecodeError::InvalidByte(66, 42))),
-        to_jsvalue("invalid base 64: Invalid symbol 42, offset 66.")
+        error.message(),
+        "invalid base 64: Invalid symbol 42, offset 66."
     );
+    assert_eq!(error.to_string(), error.message());
+    assert_eq!(error.offset(), Some(66));
+    assert_eq!(error.character(), Some("*".to_string()));
+
+    let error = JsTb64Error::from(TaggedBase64::new("T~G", b"").unwrap_err());
+    assert_eq!(error.code(), "INVALID_TAG_CHAR");
+    assert_eq!(error.offset(), Some(1));
+    assert_eq!(error.character(), Some("~".to_string()));
+    let error = JsTb64Error::from(Tb64Error::InvalidChecksum);
+    assert_eq!(error.code(), "INVALID_CHECKSUM");
+    assert_eq!(error.offset(), None);
+    assert_eq!(error.character(), None);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn wasm_validation_functions() {
+    assert_eq!(js_calc_checksum("TX", b"foobar"), 0xd7);
+    assert!(js_is_valid_tag("TX"));
+    assert!(!js_is_valid_tag("T~X"));
+
+    assert!(js_validate("TX~Zm9vYmFy1w").is_none());
+    let error = js_validate("TY~Zm9vYmFy1w").unwrap();
+    assert_eq!(error.code(), "INVALID_CHECKSUM");
+    let error = js_validate("TX~Zm9v*mFy1w").unwrap();
+    assert_eq!(error.code(), "BASE64");
+    assert_eq!(error.offset(), Some(4));
+    assert_eq!(error.character(), Some("*".to_string()));
 }
 
 #[test]
@@ -385,12 +465,28 @@ fn test_error_fmt() {
 fn basic_errors() {
     let e = TaggedBase64::new("A/A", &[0]).unwrap_err();
     println!("{:?}: {}", e, e);
-    assert!(matches!(e, Tb64Error::InvalidTag));
+    assert!(matches!(
+        e,
+        Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '/'
+        }
+    ));
 
     let e = TaggedBase64::parse("AA").unwrap_err();
     println!("{:?}: {}", e, e);
     assert!(matches!(e, Tb64Error::MissingDelimiter));
 
+    // A missing delimiter is reported as such, even when the would-be tag
+    // also contains invalid characters.
+    let e = TaggedBase64::parse("TX+ID").unwrap_err();
+    println!("{:?}: {}", e, e);
+    assert!(matches!(e, Tb64Error::MissingDelimiter));
+    assert!(matches!(
+        TaggedBase64::decode_into("TX+ID", &mut Vec::new()),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+
     let e = TaggedBase64::parse("AAA~A/A").unwrap_err();
     println!("{:?}: {}", e, e);
     assert!(matches!(e, Tb64Error::Base64 { .. }));
@@ -405,7 +501,7 @@ fn basic_errors() {
 
     let e = TaggedBase64::parse("AAA~AAAAA").unwrap_err();
     println!("{:?}: {}", e, e);
-    assert!(matches!(e, Tb64Error::Base64 { .. }));
+    assert!(matches!(e, Tb64Error::InvalidLength));
 
     let e = TaggedBase64::parse("AAA~AAF").unwrap_err();
     println!("{:?}: {}", e, e);
@@ -434,185 +530,4673 @@ fn one_bit_corruption_quickcheck(tag: u16, data: (Vec<u8>, u8), bit_to_flip: u16
     one_bit_corruption(tag, data, bit_to_flip);
 }
 
-#[tagged("BLOB")]
-#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-struct Blob(Vec<u8>);
+#[test]
+fn test_peek_tag() {
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(TaggedBase64::peek_tag(&tx.to_string()).unwrap(), "TX");
+    assert!(matches!(
+        TaggedBase64::peek_tag(&tx.to_string()).unwrap(),
+        Cow::Borrowed("TX")
+    ));
+    assert_eq!(
+        TaggedBase64::peek_tag(&tx.to_string_hex_tag()).unwrap(),
+        "TX"
+    );
+    assert_eq!(TaggedBase64::peek_tag("~anything").unwrap(), "");
 
-#[tagged("BLOB", compressed)]
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct BlobCompressed(Vec<u8>);
+    // The value is not decoded.
+    assert_eq!(TaggedBase64::peek_tag("LA~not base64!").unwrap(), "LA");
+    assert!(TaggedBase64::parse("LA~not base64!").is_err());
 
-#[tagged("BLOB", checked)]
-#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-struct BlobChecked(Vec<u8>);
+    assert!(matches!(
+        TaggedBase64::peek_tag("TX"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::peek_tag("T+X~Zm9v"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '+'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::peek_tag(&format!("{}~AA", "T".repeat(TB64_MAX_TAG_LEN + 1))),
+        Err(Tb64Error::TagTooLong { .. })
+    ));
+}
 
-#[tagged("BLOB", compressed, checked)]
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct BlobCompressedChecked(Vec<u8>);
+#[test]
+fn test_find_all() {
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap().to_string();
+    let la = TaggedBase64::new_timestamped("LA", &[0xfb; 20], 17)
+        .unwrap()
+        .to_string();
+    let bad = tx.replacen("TX", "TY", 1);
+    let text = format!(
+        "2024-01-01 INFO submitted {tx}, sent to \"{la}\".\n\
+         ~~struck~~ see <https://example.com/tx/{bad}?x=1> and `{tx}`; a~ b ~~",
+    );
+    let found: Vec<_> = TaggedBase64::find_all(&text).collect();
+    let tokens: Vec<&str> = found.iter().map(|(r, _)| &text[r.clone()]).collect();
+    // Markdown strikethrough and stray delimiters are not candidates.
+    assert_eq!(tokens, [tx.as_str(), &la, &bad, &tx]);
+    assert_eq!(
+        found[0].1.as_ref().unwrap(),
+        &TaggedBase64::parse(&tx).unwrap()
+    );
+    assert_eq!(found[1].1.as_ref().unwrap().timestamp(), Some(17));
+    assert!(matches!(found[2].1, Err(Tb64Error::InvalidChecksum)));
+    assert!(found[3].1.is_ok());
 
-impl Valid for BlobCompressed {
-    fn check(&self) -> Result<(), SerializationError> {
-        // Mock test, validation always fails
-        Err(SerializationError::InvalidData)
-    }
+    assert_eq!(TaggedBase64::find_all("").count(), 0);
+    assert_eq!(TaggedBase64::find_all("no values here.").count(), 0);
+    let (range, parsed) = TaggedBase64::find_all(&tx).next().unwrap();
+    assert_eq!(range, 0..tx.len());
+    assert!(parsed.is_ok());
 }
 
-impl CanonicalSerialize for BlobCompressed {
-    fn serialize_with_mode<W: Write>(
-        &self,
-        mut writer: W,
-        compress: Compress,
-    ) -> Result<(), SerializationError> {
-        // Mock test, always serialize into fixed bytes array
-        match compress {
-            Compress::Yes => writer
-                .write_all(&[1])
-                .map_err(|_| SerializationError::InvalidData),
-            Compress::No => writer
-                .write_all(&[1, 2])
-                .map_err(|_| SerializationError::InvalidData),
+#[test]
+fn test_seq() {
+    let values = vec![
+        TaggedBase64::new("TX", b"foobar").unwrap(),
+        TaggedBase64::new("", &[]).unwrap(),
+        TaggedBase64::new_timestamped("NOTE", &[0xfb; 33], 17).unwrap(),
+    ];
+    let s = encode_seq(&values);
+    assert_eq!(
+        s,
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(":")
+    );
+    assert!(s
+        .chars()
+        .all(|c| c == TB64_SEQ_DELIM || c == TB64_DELIM || TaggedBase64::is_safe_base64_ascii(c)));
+    assert_eq!(decode_seq(&s).unwrap(), values);
+    assert_eq!(decode_seq(&encode_seq(&values[..1])).unwrap(), &values[..1]);
+
+    // The sequence fits in a query parameter as is.
+    let url = url::Url::parse(&format!("https://example.com/notes?c={}", s)).unwrap();
+    assert_eq!(url.query(), Some(format!("c={}", s).as_str()));
+    let (_, c) = url.query_pairs().next().unwrap();
+    assert_eq!(decode_seq(&c).unwrap(), values);
+
+    assert_eq!(encode_seq(&[]), "");
+    assert_eq!(decode_seq("").unwrap(), vec![]);
+
+    // Any form parse accepts can be part of a sequence.
+    let hex_tag = format!("{}:{}", values[0].to_string_hex_tag(), values[0]);
+    assert_eq!(
+        decode_seq(&hex_tag).unwrap(),
+        [values[0].clone(), values[0].clone()]
+    );
+
+    let bad = format!("{}:TY~Zm9vYmFy1w", values[0]);
+    match decode_seq(&bad) {
+        Err(Tb64Error::InvalidElement { index: 1, error }) => {
+            assert!(matches!(*error, Tb64Error::InvalidChecksum))
         }
+        other => panic!("unexpected {:?}", other),
     }
+    assert!(matches!(
+        decode_seq(&format!("{}:", values[0])),
+        Err(Tb64Error::InvalidElement { index: 1, .. })
+    ));
+}
 
-    fn serialized_size(&self, compress: Compress) -> usize {
-        // Mock test, always return the same size
-        // Actually this function is not used in derived serde implementation.
-        if compress == Compress::Yes {
-            1
-        } else {
-            2
-        }
+#[test]
+fn test_custom_delimiter() {
+    assert_eq!(Tb64Format::default().delim(), TB64_DELIM);
+    for delim in ['a', '0', '-', '_', '.', ' ', '\n', 'é'] {
+        assert!(matches!(
+            Tb64Format::new(delim),
+            Err(Tb64Error::InvalidDelimiter { delim: d }) if d == delim
+        ));
+    }
+
+    let format = Tb64Format::new('*').unwrap();
+    let plain = TaggedBase64::new("TX", b"foobar").unwrap();
+    let ext = TaggedBase64::new_timestamped("TX", b"foobar", 17).unwrap();
+    for tb64 in [&plain, &ext, &TaggedBase64::new("", b"").unwrap()] {
+        let s = format.format(tb64);
+        assert_eq!(s, tb64.to_string().replace('~', "*"));
+        assert_eq!(&format.parse(&s).unwrap(), tb64);
+        assert_eq!(Tb64Format::default().format(tb64), tb64.to_string());
+        assert_eq!(
+            &Tb64Format::default().parse(&tb64.to_string()).unwrap(),
+            tb64
+        );
     }
+    assert!(format.format(&ext).starts_with("TX**"));
+
+    // The standard delimiter is reported as an invalid character.
+    assert!(matches!(
+        format.parse("TX~Zm9vYmFy1w"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        format.parse("T~X*Zm9vYmFy1w"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+    assert!(matches!(
+        format.parse("TX*Zm9~YmFy1w"),
+        Err(Tb64Error::Base64 {
+            error: base64::DecodeError::InvalidByte(3, b'~')
+        })
+    ));
 }
 
-impl CanonicalDeserialize for BlobCompressed {
-    fn deserialize_with_mode<R: Read>(
-        _reader: R,
-        compress: Compress,
-        validate: Validate,
-    ) -> Result<Self, SerializationError> {
-        if validate == Validate::Yes {
-            unreachable!()
-        } else {
-            match compress {
-                Compress::Yes => Ok(Self(vec![1])),
-                Compress::No => Ok(Self(vec![1, 2])),
-            }
+#[test]
+fn test_base32() {
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(tx.to_string_base32().unwrap(), "tx1vehk7cnpwtts");
+    assert_eq!(TaggedBase64::parse_base32("tx1vehk7cnpwtts").unwrap(), tx);
+    assert_eq!(TaggedBase64::parse_base32("TX1VEHK7CNPWTTS").unwrap(), tx);
+    assert_eq!(TaggedBase64::parse_any("TX1VEHK7CNPWTTS").unwrap(), tx);
+    assert_eq!(TaggedBase64::parse_any(&tx.to_string()).unwrap(), tx);
+
+    // Every length of value, tags ending in the separator, and extended
+    // values all round trip.
+    for len in 0..20 {
+        let value: Vec<u8> = (0..len as u8).map(|i| i.wrapping_mul(97)).collect();
+        for tb64 in [
+            TaggedBase64::new("", &value).unwrap(),
+            TaggedBase64::new("V1", &value).unwrap(),
+            TaggedBase64::new("NOTE-2", &value).unwrap(),
+            TaggedBase64::new_timestamped("TX", &value, 17).unwrap(),
+            TaggedBase64::new_with_checksum("TX", &value, ChecksumKind::Crc32c).unwrap(),
+        ] {
+            let s = tb64.to_string_base32().unwrap();
+            assert!(!s.chars().any(|c| c.is_ascii_uppercase()));
+            let parsed = TaggedBase64::parse_base32(&s).unwrap();
+            assert_eq!(parsed, tb64);
+            assert_eq!(parsed.timestamp(), tb64.timestamp());
+            assert_eq!(parsed.checksum_kind(), tb64.checksum_kind());
+            assert_eq!(
+                TaggedBase64::parse_any(&s.to_ascii_uppercase()).unwrap(),
+                tb64
+            );
         }
     }
+
+    // Tags which depend on case cannot be written.
+    assert!(matches!(
+        TaggedBase64::new("Tx", b"").unwrap().to_string_base32(),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: 'x'
+        })
+    ));
+    assert!(TaggedBase64::new("T_X", b"")
+        .unwrap()
+        .to_string_base32()
+        .is_err());
+
+    assert!(matches!(
+        TaggedBase64::parse_base32("tx1vehk7cnpwttS"),
+        Err(Tb64Error::InvalidBase32Char {
+            offset: 14,
+            character: 'S'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("tx1vehk7cnbwtts"),
+        Err(Tb64Error::InvalidBase32Char {
+            offset: 10,
+            character: 'b'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("tx1vehk7cnpwtt"),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("ty1vehk7cnpwtts"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("txvehk7cnpwtts"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("tx1"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_base32("t.x1vehk7cnpwtts"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '.'
+        })
+    ));
 }
-impl Valid for BlobCompressedChecked {
-    fn check(&self) -> Result<(), SerializationError> {
-        // Mock test, validation always fails
-        Err(SerializationError::InvalidData)
+
+#[quickcheck]
+fn base32_quickcheck(tag: u16, value: Vec<u8>) -> bool {
+    let tb64 = TaggedBase64::new(&format!("T{}", tag), &value).unwrap();
+    TaggedBase64::parse_base32(&tb64.to_string_base32().unwrap()).unwrap() == tb64
+}
+
+#[test]
+fn test_multibase() {
+    const CODECS: &[(&str, u64)] = &[("TX", 0x300001), ("NOTE", 0x7f), ("", 0)];
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+    let s = tx.to_multibase(CODECS).unwrap();
+    assert_eq!(s, "ugYDAAWZvb2Jhcg");
+    assert_eq!(TaggedBase64::from_multibase(&s, CODECS).unwrap(), tx);
+
+    // The other base64 alphabets and hex are accepted.
+    let bytes = [0x81, 0x80, 0xc0, 0x01, b'f', b'o', b'o', b'b', b'a', b'r'];
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    for s in [
+        format!(
+            "U{}",
+            base64::engine::general_purpose::URL_SAFE.encode(bytes)
+        ),
+        format!(
+            "m{}",
+            base64::engine::general_purpose::STANDARD_NO_PAD.encode(bytes)
+        ),
+        format!(
+            "M{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+        format!("f{}", hex),
+        format!("F{}", hex.to_uppercase()),
+    ] {
+        assert_eq!(TaggedBase64::from_multibase(&s, CODECS).unwrap(), tx);
+    }
+
+    for tb64 in [
+        TaggedBase64::new("NOTE", &[0xfb; 40]).unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+    ] {
+        let s = tb64.to_multibase(CODECS).unwrap();
+        assert_eq!(TaggedBase64::from_multibase(&s, CODECS).unwrap(), tb64);
+    }
+
+    assert!(matches!(
+        TaggedBase64::new("LA", b"").unwrap().to_multibase(CODECS),
+        Err(Tb64Error::TagNotAllowed { tag }) if tag == "LA"
+    ));
+    assert!(matches!(
+        TaggedBase64::from_multibase("zQmfoo", CODECS),
+        Err(Tb64Error::UnsupportedMultibase { prefix: 'z' })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_multibase("f0500", CODECS),
+        Err(Tb64Error::UnknownCodec { code: 5 })
+    ));
+    // Truncated and overlong varints.
+    for hex in ["f", "f81", "f8000", "f818080808080808080800001"] {
+        assert!(matches!(
+            TaggedBase64::from_multibase(hex, CODECS),
+            Err(Tb64Error::InvalidLength)
+        ));
     }
+    assert!(TaggedBase64::from_multibase("", CODECS).is_err());
+    assert!(TaggedBase64::from_multibase("u+", CODECS).is_err());
 }
 
-impl CanonicalSerialize for BlobCompressedChecked {
-    fn serialize_with_mode<W: Write>(
-        &self,
-        mut writer: W,
-        compress: Compress,
-    ) -> Result<(), SerializationError> {
-        // Mock test, always serialize into fixed bytes array
-        match compress {
-            Compress::Yes => writer
-                .write_all(&[1])
-                .map_err(|_| SerializationError::InvalidData),
-            Compress::No => writer
-                .write_all(&[1, 2])
-                .map_err(|_| SerializationError::InvalidData),
-        }
+#[test]
+fn test_hex_string() {
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(tx.to_hex_string(), "TX~.666f6f626172d7");
+    assert_eq!(tx.to_string(), "TX~Zm9vYmFy1w");
+    assert_eq!(TaggedBase64::parse_any("TX~.666f6f626172d7").unwrap(), tx);
+    assert_eq!(TaggedBase64::parse_any("TX~.666F6F626172D7").unwrap(), tx);
+    assert!(TaggedBase64::parse("TX~.666f6f626172d7").is_err());
+
+    for tb64 in [
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new_timestamped("TX", b"foobar", 17).unwrap(),
+        TaggedBase64::new_with_checksum("NOTE", &[0xfb; 40], ChecksumKind::Crc16).unwrap(),
+    ] {
+        let s = tb64.to_hex_string();
+        assert!(s.starts_with(&format!("{}~", tb64.tag())));
+        let parsed = TaggedBase64::parse_any(&s).unwrap();
+        assert_eq!(parsed, tb64);
+        assert_eq!(parsed.timestamp(), tb64.timestamp());
     }
+    assert!(TaggedBase64::new_timestamped("TX", b"", 1)
+        .unwrap()
+        .to_hex_string()
+        .starts_with("TX~~."));
 
-    fn serialized_size(&self, compress: Compress) -> usize {
-        // Mock test, always return the same size
-        // Actually this function is not used in derived serde implementation.
-        if compress == Compress::Yes {
-            1
-        } else {
-            2
-        }
+    // A hex tag may be combined with a hex value.
+    let hex_tag = tx.to_string_hex_tag();
+    let both = format!(
+        "{}.666f6f626172d7",
+        &hex_tag[..hex_tag.find('~').unwrap() + 1]
+    );
+    assert_eq!(TaggedBase64::parse_any(&both).unwrap(), tx);
+
+    // The marker is not the sequence separator, so hex values can be part
+    // of a sequence.
+    let seq = format!("{}{}{}", tx.to_hex_string(), TB64_SEQ_DELIM, tx);
+    assert_eq!(decode_seq(&seq).unwrap(), [tx.clone(), tx.clone()]);
+
+    assert!(matches!(
+        TaggedBase64::parse_any("TX~.666f6f626172d8"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_any("TX~.666f6f626172d"),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_any("TX~.666f6f62617zd7"),
+        Err(Tb64Error::InvalidData)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_any("TX~."),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[test]
+fn test_fixed_size_and_integers() {
+    let tb64 = TaggedBase64::new("KEY", &[1, 2, 3, 4]).unwrap();
+    assert_eq!(<[u8; 4]>::try_from(&tb64).unwrap(), [1, 2, 3, 4]);
+    assert!(matches!(
+        <[u8; 3]>::try_from(&tb64),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        <[u8; 5]>::try_from(&tb64),
+        Err(Tb64Error::InvalidLength)
+    ));
+    let empty: [u8; 0] = (&TaggedBase64::new("KEY", &[]).unwrap())
+        .try_into()
+        .unwrap();
+    assert_eq!(empty, [0u8; 0]);
+
+    for n in [0, 1, (1 << 53) + 1, u64::MAX] {
+        let tb64 = TaggedBase64::from_u64("N", n).unwrap();
+        assert_eq!(tb64.value(), n.to_be_bytes());
+        assert_eq!(tb64.try_to_u64().unwrap(), n);
+        assert_eq!(
+            TaggedBase64::parse(&tb64.to_string())
+                .unwrap()
+                .try_to_u64()
+                .unwrap(),
+            n
+        );
     }
+    assert_eq!(
+        TaggedBase64::from_u64("N", 0x0102030405060708)
+            .unwrap()
+            .value(),
+        [1, 2, 3, 4, 5, 6, 7, 8]
+    );
+    assert!(matches!(
+        TaggedBase64::new("N", &[1, 2, 3]).unwrap().try_to_u64(),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(TaggedBase64::from_u64("N~", 1).is_err());
 }
 
-impl CanonicalDeserialize for BlobCompressedChecked {
-    fn deserialize_with_mode<R: Read>(
-        _reader: R,
-        _compress: Compress,
-        validate: Validate,
-    ) -> Result<Self, SerializationError> {
-        if validate == Validate::Yes {
-            // Mock test, validation always fails
-            Err(SerializationError::InvalidData)
-        } else {
-            unreachable!()
-        }
+#[test]
+fn test_custom_engine() {
+    use tagged_base64::base64::{
+        alphabet,
+        engine::{general_purpose, GeneralPurpose},
+    };
+
+    let padded = GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::PAD);
+    let tb64 = TaggedBase64::new("TX", &[0xfb, 0xff, 0xbf, 0x10]).unwrap();
+    let s = tb64.to_string_with_engine(&padded);
+    assert_eq!(s, format!("{}=", tb64));
+    assert_eq!(TaggedBase64::parse_with_engine(&s, &padded).unwrap(), tb64);
+    assert!(TaggedBase64::parse(&s).is_err());
+    assert!(TaggedBase64::parse_with_engine(&tb64.to_string(), &padded).is_err());
+
+    let standard = general_purpose::STANDARD_NO_PAD;
+    let s = tb64.to_string_with_engine(&standard);
+    assert_eq!(s, tb64.to_string().replace('-', "+").replace('_', "/"));
+    assert_eq!(
+        TaggedBase64::parse_with_engine(&s, &standard).unwrap(),
+        tb64
+    );
+
+    // The default engine gives the usual string, for extended values too.
+    for tb64 in [
+        tb64.clone(),
+        TaggedBase64::new_timestamped("TX", b"foobar", 17).unwrap(),
+    ] {
+        let s = tb64.to_string_with_engine(&BASE64);
+        assert_eq!(s, tb64.to_string());
+        assert_eq!(TaggedBase64::parse_with_engine(&s, &BASE64).unwrap(), tb64);
+    }
+}
+
+#[cfg(feature = "simd")]
+#[quickcheck]
+fn simd_raw_quickcheck(value: Vec<u8>, repeat: u8, flip: usize) -> bool {
+    // Repeat the value to reach the vectorized path.
+    let value = value.repeat(repeat as usize % 64 + 1);
+    let value = [value.as_slice(), &[0x5a; SIMD_MIN_LEN]].concat();
+    let encoded = TaggedBase64::encode_raw(&value);
+    assert_eq!(encoded, BASE64.encode(&value));
+    assert_eq!(TaggedBase64::decode_raw(&encoded).unwrap(), value);
+
+    // Corrupted and non-canonical strings are rejected alike.
+    let mut corrupted = encoded.clone().into_bytes();
+    let i = flip % corrupted.len();
+    corrupted[i] = [b'+', b'=', b'~', b'A', b'_'][flip % 5];
+    let corrupted = String::from_utf8(corrupted).unwrap();
+    // A final group of 2 characters with nonzero unused bits.
+    let odd = TaggedBase64::encode_raw(&value[..value.len() / 3 * 3 - 2]);
+    let trailing = format!("{}B", &odd[..odd.len() - 1]);
+    for s in [&corrupted, &trailing, &format!("{}A", encoded)] {
+        assert_eq!(
+            format!("{:?}", TaggedBase64::decode_raw(s)),
+            format!("{:?}", BASE64.decode(s).map_err(Tb64Error::from))
+        );
     }
+    true
 }
 
 #[test]
-fn test_tagged() {
-    let bytes = (0..100).collect();
-    let b = Blob(bytes);
-    let t = TaggedBase64::from(&b);
-    assert!(t.to_string().starts_with("BLOB~"));
-    assert_eq!(b, t.try_into().unwrap());
+fn test_reused_buffers() {
+    let large = TaggedBase64::new("LARGE", &[0xfb; 1000]).unwrap();
+    let small = TaggedBase64::new_timestamped("TX", b"foobar", 17).unwrap();
+
+    let mut out = String::new();
+    large.encode_into(&mut out);
+    assert_eq!(out, large.to_string());
+    small.encode_into(&mut out);
+    assert_eq!(out, format!("{}{}", large, small));
+    let (ptr, capacity) = (out.as_ptr(), out.capacity());
+    out.clear();
+    small.encode_into(&mut out);
+    assert_eq!(out, small.to_string());
+    assert_eq!((out.as_ptr(), out.capacity()), (ptr, capacity));
+
+    let mut tag = String::new();
+    let mut value = Vec::new();
+    TaggedBase64::decode_into_parts(&large.to_string(), &mut tag, &mut value).unwrap();
+    assert_eq!(
+        (tag.as_str(), value.as_slice()),
+        ("LARGE", large.value_ref())
+    );
+    let (tag_ptr, value_ptr) = (tag.as_ptr(), value.as_ptr());
+    TaggedBase64::decode_into_parts(&small.to_string(), &mut tag, &mut value).unwrap();
+    assert_eq!((tag.as_str(), value.as_slice()), ("TX", small.value_ref()));
+    assert_eq!((tag.as_ptr(), value.as_ptr()), (tag_ptr, value_ptr));
+
+    assert!(matches!(
+        TaggedBase64::decode_into_parts("TY~Zm9vYmFy1w", &mut tag, &mut value),
+        Err(Tb64Error::InvalidChecksum)
+    ));
 }
 
 #[test]
-fn test_serde_json_str() {
-    let bytes = (0..100).collect::<Vec<_>>();
-    let t = TaggedBase64::new("TAG", &bytes).unwrap();
-    let s = serde_json::to_string(&t).unwrap();
-    assert!(s.starts_with("\"TAG~"));
-    assert_eq!(t, serde_json::from_str(&s).unwrap());
+fn test_string_forms_agree() {
+    // Values straddling the chunk size of the encoder, and extended values.
+    for len in [0, 1, 2, 3, 766, 767, 768, 769, 770, 1535, 1536, 1537, 5000] {
+        let value: Vec<u8> = (0..len).map(|i| (i * 31 % 251) as u8).collect();
+        for tb64 in [
+            TaggedBase64::new("TX", &value).unwrap(),
+            TaggedBase64::new_with_checksum("TX", &value, ChecksumKind::Crc32c).unwrap(),
+        ] {
+            let encoded = BASE64.encode(tb64.decoded_region());
+            let delims = if tb64.checksum_kind() == ChecksumKind::Crc8 {
+                "~"
+            } else {
+                "~~"
+            };
+            let expected = format!("TX{}{}", delims, encoded);
+            assert_eq!(tb64.encoded_value(), encoded);
+            assert_eq!(format!("{}", tb64), expected);
+            assert_eq!(to_string(&tb64), expected);
+            assert_eq!(String::from(&tb64), expected);
+            assert_eq!(tb64.display_len(), expected.len());
+        }
+    }
 }
 
 #[test]
-fn test_serde_json_value() {
-    let bytes = (0..100).collect::<Vec<_>>();
-    let t = TaggedBase64::new("TAG", &bytes).unwrap();
-    let v = serde_json::to_value(&t).unwrap();
-    assert_eq!(t, serde_json::from_value(v).unwrap());
+fn test_inline_storage_boundaries() {
+    // Tags and values on either side of the inline limits behave alike, and
+    // serialize as a String, a Vec<u8> and the checksum byte.
+    for tag_len in [0, 1, 21, 22, 23, 40] {
+        let tag = "T".repeat(tag_len);
+        for len in [0, 1, 63, 64, 65, 200] {
+            let value: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let tb64 = TaggedBase64::new(&tag, &value).unwrap();
+            assert_eq!(tb64.tag(), tag);
+            assert_eq!(tb64.value(), value);
+            assert_eq!(tb64.clone(), tb64);
+            assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
+
+            let mut bytes = Vec::new();
+            tb64.serialize_compressed(&mut bytes).unwrap();
+            let mut expected = Vec::new();
+            (tag.clone(), value.clone(), tb64.checksum())
+                .serialize_compressed(&mut expected)
+                .unwrap();
+            assert_eq!(bytes, expected);
+            assert_eq!(TaggedBase64::deserialize_compressed(&*bytes).unwrap(), tb64);
+
+            let mut moved = tb64.clone();
+            moved.set_value(&[7; 100]);
+            moved.set_value(&value);
+            assert_eq!(moved, tb64);
+            assert_eq!(tb64.into_parts(), (tag.clone(), value));
+        }
+    }
 }
 
 #[test]
-fn test_serde_bincode() {
-    let bytes = (0..100).collect::<Vec<_>>();
-    let t = TaggedBase64::new("TAG", &bytes).unwrap();
+fn test_checksum_vectors() {
+    // The CRC-8 check value for "123456789" is 0xF4.
+    const CHECKSUM: u8 = TaggedBase64::calc_checksum("", b"123456789");
+    assert_eq!(CHECKSUM, 0xF4 ^ 9);
     assert_eq!(
-        t,
-        bincode::deserialize(&bincode::serialize(&t).unwrap()).unwrap()
+        TaggedBase64::tag_checksum("TX"),
+        TaggedBase64::calc_checksum("TX", &[])
     );
+
+    let value = b"123456789";
+    for (tb64, expected) in [
+        (TaggedBase64::new("TX", value), "TX~MTIzNDU2Nzg5Pg"),
+        (
+            TaggedBase64::new_with_checksum("TX", value, ChecksumKind::Crc16),
+            "TX~~BDEyMzQ1Njc4OQBmug",
+        ),
+        (
+            TaggedBase64::new_with_checksum("TX", value, ChecksumKind::Crc32c),
+            "TX~~CDEyMzQ1Njc4OQ8QFxwJ",
+        ),
+    ] {
+        let tb64 = tb64.unwrap();
+        assert_eq!(tb64.to_string(), expected);
+        assert_eq!(TaggedBase64::parse(expected).unwrap(), tb64);
+    }
 }
 
 #[test]
-fn test_serde_compressed_checked() {
-    let blob = BlobCompressedChecked(vec![1, 2]);
-    let bytes = bincode::serialize(&blob).unwrap();
-    assert!(bincode::deserialize::<BlobCompressedChecked>(&bytes).is_err());
+fn test_tag_registry() {
+    let registry = TagRegistry::new()
+        .register("TX")
+        .register_with_len("KEY", 4);
+    assert!(registry.contains("TX"));
+    assert!(!registry.contains("LA"));
+    assert_eq!(registry.expected_len("KEY"), Some(4));
+    assert_eq!(registry.expected_len("TX"), None);
+    assert_eq!(registry.tags().collect::<Vec<_>>(), ["KEY", "TX"]);
 
-    let blob = BlobCompressed(vec![1, 2]);
-    let bytes = bincode::serialize(&blob).unwrap();
+    let tx = TaggedBase64::new("TX", b"anything").unwrap();
+    assert_eq!(registry.parse(&tx.to_string()).unwrap(), tx);
+    let key = TaggedBase64::new("KEY", &[1, 2, 3, 4]).unwrap();
+    assert_eq!(registry.parse(&key.to_string()).unwrap(), key);
+    let key = TaggedBase64::new_timestamped("KEY", &[1, 2, 3, 4], 42).unwrap();
+    assert_eq!(registry.parse(&key.to_string()).unwrap(), key);
+
+    // An unknown tag is reported even when the checksum is also wrong.
+    match registry.parse("LA~Zm9vYmFy1w") {
+        Err(Tb64Error::TagNotAllowed { tag }) => assert_eq!(tag, "LA"),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(matches!(
+        registry.parse("TY~Zm9vYmFy1w"),
+        Err(Tb64Error::TagNotAllowed { .. })
+    ));
+    assert!(matches!(
+        registry.parse("TX~Zm9vYmFz1w"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    let short = TaggedBase64::new("KEY", &[1, 2, 3]).unwrap();
+    assert!(matches!(
+        registry.parse(&short.to_string()),
+        Err(Tb64Error::WrongValueLength {
+            expected: 4,
+            found: 3
+        })
+    ));
+    assert!(matches!(
+        registry.check(&short),
+        Err(Tb64Error::WrongValueLength { .. })
+    ));
+
+    // Registering again replaces the length.
+    let registry = registry.register("KEY");
+    assert_eq!(registry.parse(&short.to_string()).unwrap(), short);
+    assert!(matches!(
+        TagRegistry::new().parse(&tx.to_string()),
+        Err(Tb64Error::TagNotAllowed { .. })
+    ));
+}
+
+#[test]
+fn test_namespaced_tags() {
+    let tb64 = TaggedBase64::new_namespaced("ESPRESSO_L2", "TX", b"payload").unwrap();
+    assert_eq!(tb64.tag_ref(), "ESPRESSO_L2_TX");
+    assert_eq!(tb64.namespace(), Some("ESPRESSO_L2"));
+    assert_eq!(tb64.kind(), "TX");
+    assert!(tb64.has_namespace("ESPRESSO"));
+    assert!(tb64.has_namespace("ESPRESSO_L2"));
+    assert!(!tb64.has_namespace("ESP"));
+    assert!(!tb64.has_namespace("L2"));
+    assert!(!tb64.has_namespace("ESPRESSO_L2_TX"));
+    // Namespaced tags are ordinary tags in the string form.
+    let parsed = TaggedBase64::parse(&tb64.to_string()).unwrap();
+    assert_eq!(parsed, tb64);
     assert_eq!(
-        bincode::deserialize::<BlobCompressed>(&bytes).unwrap(),
-        BlobCompressed(vec![1])
+        parsed,
+        TaggedBase64::new("ESPRESSO_L2_TX", b"payload").unwrap()
     );
+
+    let flat = TaggedBase64::new("TX", b"payload").unwrap();
+    assert_eq!(flat.namespace(), None);
+    assert_eq!(flat.kind(), "TX");
+    assert!(!flat.has_namespace("TX"));
+    assert!(!flat.has_namespace(""));
+    for tag in ["", "_TX", "TX_", "_"] {
+        assert_eq!(TaggedBase64::split_namespace(tag), (None, tag));
+    }
+
+    assert!(TaggedBase64::validate_namespaced_tag("TX").is_ok());
+    assert!(TaggedBase64::validate_namespaced_tag("A_B_TX").is_ok());
+    for (tag, offset) in [("", 0), ("_TX", 0), ("TX_", 3), ("A__TX", 2)] {
+        match TaggedBase64::validate_namespaced_tag(tag) {
+            Err(Tb64Error::EmptyTagSegment { offset: found }) => assert_eq!(found, offset),
+            other => panic!("unexpected result {:?} for {:?}", other, tag),
+        }
+    }
+    assert!(matches!(
+        TaggedBase64::validate_namespaced_tag("A.TX"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '.'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::new_namespaced("", "TX", b""),
+        Err(Tb64Error::EmptyTagSegment { offset: 0 })
+    ));
+    assert!(matches!(
+        TaggedBase64::new_namespaced("APP", "", b""),
+        Err(Tb64Error::EmptyTagSegment { offset: 4 })
+    ));
+    assert!(matches!(
+        TaggedBase64::new_namespaced("APP", "T_X", b""),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 5,
+            character: '_'
+        })
+    ));
 }
 
 #[test]
-fn test_compat() {
-    // A hard-coded example, for easily checking compatibility with ports to other languages.
-    let tag = "abcdefghijklmnopqrstuvwxyz-ABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
-    let data = "~Yeah, we can have spaces and odd stuff—😀 here. ¯⧵_(ツ)_/¯".as_bytes();
-    let expected = "abcdefghijklmnopqrstuvwxyz-ABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789~flllYWgsIHdlIGNhbiBoYXZlIHNwYWNlcyBhbmQgb2RkIHN0dWZm4oCU8J-YgCBoZXJlLiDCr-KntV8o44OEKV8vwq_6";
+fn test_detected_format_version() {
+    let plain = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(plain.format_version(), FormatVersion::V1);
+    assert_eq!(plain.format_version().number(), 1);
+    for tb64 in [
+        TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap(),
+        TaggedBase64::new_with_checksum("TX", b"foobar", ChecksumKind::Crc16).unwrap(),
+        TaggedBase64::new_with_checksum("TX", b"foobar", ChecksumKind::Crc32c).unwrap(),
+    ] {
+        assert_eq!(tb64.format_version(), FormatVersion::V2);
+        assert_eq!(tb64.format_version().number(), 2);
+    }
+
+    // Version 1 strings parse as before and report their version.
+    let parsed = TaggedBase64::parse("TX~Zm9vYmFy1w").unwrap();
+    assert_eq!(parsed, plain);
+    assert_eq!(parsed.format_version(), FormatVersion::V1);
+    let v2 = TaggedBase64::new_timestamped("TX", &[7; 100], 42).unwrap();
+    let parsed = TaggedBase64::parse(&v2.to_string()).unwrap();
+    assert_eq!(parsed.format_version(), FormatVersion::V2);
+    let parsed = TaggedBase64::parse(&v2.to_string_compact()).unwrap();
+    assert_eq!(parsed.format_version(), FormatVersion::V2);
+    assert!(FormatVersion::V1 < FormatVersion::V2);
+}
+
+#[test]
+fn test_sized_checksum() {
+    for (len, expected) in [
+        (0, ChecksumKind::Crc8),
+        (CRC16_MIN_LEN - 1, ChecksumKind::Crc8),
+        (CRC16_MIN_LEN, ChecksumKind::Crc16),
+        (CRC32_MIN_LEN - 1, ChecksumKind::Crc16),
+        (CRC32_MIN_LEN, ChecksumKind::Crc32c),
+        (100_000, ChecksumKind::Crc32c),
+    ] {
+        assert_eq!(ChecksumKind::for_len(len), expected);
+        let value: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let tb64 = TaggedBase64::new_with_sized_checksum("PROOF", &value).unwrap();
+        assert_eq!(tb64.checksum_kind(), expected);
+        assert_eq!(tb64.payload(), &value[..]);
+        let parsed = TaggedBase64::parse(&tb64.to_string()).unwrap();
+        assert_eq!(parsed, tb64);
+        assert_eq!(parsed.checksum_kind(), expected);
+    }
+
+    // A corrupted byte in a long value is caught by the wider checksum even
+    // when the CRC-8 byte is fixed up to match.
+    let value = vec![0x5a; CRC32_MIN_LEN];
+    let tb64 = TaggedBase64::new_with_sized_checksum("PROOF", &value).unwrap();
+    let mut bytes = tb64.value();
+    bytes[100] ^= 1;
+    let cs = TaggedBase64::calc_checksum("PROOF", &bytes) ^ 0x80;
+    bytes.push(cs);
+    let s = format!("PROOF~~{}", TaggedBase64::encode_raw(&bytes));
+    assert!(matches!(
+        TaggedBase64::parse(&s),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_error_codes() {
+    let err = TaggedBase64::new("T~G", b"").unwrap_err();
+    assert_eq!(err.code(), "INVALID_TAG_CHAR");
+    assert_eq!(err.offset(), Some(1));
+    assert_eq!(err.character(), Some('~'));
+
+    let err = TaggedBase64::parse("TX~Zm9v*mFy1w").unwrap_err();
+    assert_eq!(err.code(), "BASE64");
+    assert_eq!(err.offset(), Some(4));
+    assert_eq!(err.character(), Some('*'));
+
+    let err = TaggedBase64::parse("TY~Zm9vYmFy1w").unwrap_err();
+    assert_eq!(err.code(), "INVALID_CHECKSUM");
+    assert_eq!(err.offset(), None);
+    assert_eq!(err.character(), None);
 
-    let tb64 = TaggedBase64::new(tag, data).unwrap();
-    let s = tb64.to_string();
     assert_eq!(
-        s, expected,
+        TaggedBase64::parse("TX").unwrap_err().code(),
+        "MISSING_DELIMITER"
+    );
+    assert_eq!(
+        TaggedBase64::validate_namespaced_tag("A__B")
+            .unwrap_err()
+            .offset(),
+        Some(2)
+    );
+    assert_eq!(
+        Tb64Error::InvalidDelimiter { delim: 'x' }.character(),
+        Some('x')
+    );
+}
+
+#[test]
+fn test_display_flags() {
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(format!("{tb:16}|"), "TX~Zm9vYmFy1w   |");
+    assert_eq!(format!("{tb:>16}"), "   TX~Zm9vYmFy1w");
+    assert_eq!(format!("{tb:*^16}"), "*TX~Zm9vYmFy1w**");
+    assert_eq!(format!("{tb:4}"), "TX~Zm9vYmFy1w");
+    assert_eq!(format!("{tb:.4}"), "TX~Zm9vYmFy1w");
+    assert_eq!(format!("{tb:#}"), "TX~Zm9vYmFy1w [6 bytes, checksum=0xd7]");
+    assert_eq!(
+        format!("{tb:>#40}"),
+        "  TX~Zm9vYmFy1w [6 bytes, checksum=0xd7]"
+    );
+
+    let extended = TaggedBase64::new_timestamped("TX", b"", 1).unwrap();
+    assert_eq!(
+        format!("{extended:>30}"),
+        format!("{:>30}", extended.to_string())
+    );
+}
+
+#[test]
+fn test_fmt_truncated() {
+    let tb64 = TaggedBase64::new("TX", &(0..100).collect::<Vec<_>>()).unwrap();
+    let s = tb64.to_string();
+    for max_chars in [0, 5, 12, 13, 40] {
+        let short = tb64.fmt_truncated(max_chars);
+        assert!(short.starts_with("TX~"));
+        assert_eq!(short.chars().count(), max_chars.max(6));
+        let (head, tail) = short["TX~".len()..].split_once('…').unwrap();
+        assert!(s["TX~".len()..].starts_with(head));
+        assert!(s.ends_with(tail));
+        assert!(!head.is_empty() && !tail.is_empty());
+    }
+    assert_eq!(tb64.fmt_truncated(s.len()), s);
+    assert_eq!(tb64.fmt_truncated(usize::MAX), s);
+
+    // Both delimiters of an extended value are kept.
+    let tb64 = TaggedBase64::new_timestamped("TX", &[7; 100], 42).unwrap();
+    assert!(tb64.fmt_truncated(12).starts_with("TX~~"));
+    assert_eq!(tb64.fmt_truncated(12).chars().count(), 12);
+
+    // The tag is never cut, even if it alone exceeds the limit.
+    let tb64 = TaggedBase64::new("LONGTAG", &[7; 100]).unwrap();
+    assert!(tb64.fmt_truncated(4).starts_with("LONGTAG~"));
+
+    // Short values are not elided to no benefit.
+    let tb64 = TaggedBase64::new("LONGTAG", b"a").unwrap();
+    assert_eq!(tb64.fmt_truncated(4), tb64.to_string());
+}
+
+#[test]
+fn test_sensitive() {
+    let tb64 = TaggedBase64::new("KEY", &[42; 32]).unwrap();
+    let secret = SensitiveTaggedBase64::from(tb64.clone());
+    assert_eq!(format!("{:?}", secret), "KEY~<redacted, 32 bytes>");
+    assert_eq!(format!("{:#?}", secret), "KEY~<redacted, 32 bytes>");
+    assert_eq!(secret.to_string(), "KEY~<redacted, 32 bytes>");
+    assert!(!format!("{:?}", Some(&secret)).contains(&tb64.to_string()[4..]));
+    assert_eq!(secret.tag(), "KEY");
+    assert_eq!(secret.expose(), &tb64);
+    assert_eq!(
+        tb64.to_string().parse::<SensitiveTaggedBase64>().unwrap(),
+        secret
+    );
+    assert_eq!(secret.clone().into_inner(), tb64);
+
+    // Serialization writes the value itself.
+    let json = serde_json::to_string(&secret).unwrap();
+    assert_eq!(json, serde_json::to_string(&tb64).unwrap());
+    assert_eq!(
+        serde_json::from_str::<SensitiveTaggedBase64>(&json).unwrap(),
+        secret
+    );
+}
+
+#[test]
+fn test_parse_percent_encoded() {
+    let tb64 = TaggedBase64::new("T_X-", &[0xfb, 0xff, 1, 2, 3]).unwrap();
+    let s = tb64.to_string();
+    assert!(s.contains(['_', '-']));
+    for encoded in [
+        s.clone(),
+        s.replace('~', "%7E"),
+        s.replace('~', "%7e"),
+        s.replace('_', "%5F").replace('-', "%2D"),
+        s.replace('~', "%7E").replace('_', "%5f"),
+    ] {
+        assert_eq!(
+            TaggedBase64::parse_percent_encoded(&encoded).unwrap(),
+            tb64,
+            "{}",
+            encoded
+        );
+    }
+    let extended = TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap();
+    assert_eq!(
+        TaggedBase64::parse_percent_encoded(&extended.to_string().replace('~', "%7E")).unwrap(),
+        extended
+    );
+
+    // Malformed escapes, and escapes of characters which never appear.
+    for (s, offset) in [
+        ("TX%7Zm9vYmFy1w", 2),
+        ("TX~Zm9vYmFy1w%", 13),
+        ("TX~Zm9vYmFy1w%7", 13),
+        ("TX%+1Zm9vYmFy1w", 2),
+        ("TX%7EZm9v%2BYmFy1w", 9),
+        ("TX%7EZm9vYmFy1w%3D", 15),
+        ("T%20X~Zm9vYmFy1w", 1),
+        ("TX%7EZm9vYmFy1w%25", 15),
+    ] {
+        let err = TaggedBase64::parse_percent_encoded(s).unwrap_err();
+        assert!(
+            matches!(err, Tb64Error::InvalidPercentEncoding { offset: o } if o == offset),
+            "{} {:?}",
+            s,
+            err
+        );
+        assert_eq!(err.offset(), Some(offset));
+    }
+    // A valid escape doesn't excuse a bad checksum.
+    assert!(matches!(
+        TaggedBase64::parse_percent_encoded("TY%7EZm9vYmFy1w"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_tag_case_normalization() {
+    let tb64 = TaggedBase64::new("TX", b"foobar").unwrap();
+    for s in [
+        "TX~Zm9vYmFy1w",
+        "tx~Zm9vYmFy1w",
+        "Tx~Zm9vYmFy1w",
+        &TaggedBase64::new("tx", b"foobar").unwrap().to_string(),
+    ] {
+        let parsed = TaggedBase64::parse_ignore_tag_case(s).unwrap();
+        assert_eq!(parsed, tb64, "{}", s);
+        assert_eq!(parsed.to_string(), "TX~Zm9vYmFy1w");
+        assert!(parsed.verify_checksum());
+        // Both case-insensitive APIs agree on the canonical case.
+        assert_eq!(
+            parsed.tag(),
+            TaggedBase64::parse_without_checksum(s)
+                .unwrap()
+                .normalized_tag()
+        );
+    }
+    assert!(TaggedBase64::parse("tx~Zm9vYmFy1w").is_err());
+
+    // The value is still protected by the checksum.
+    assert!(matches!(
+        TaggedBase64::parse_ignore_tag_case("tx~Zm9vYmFz1w"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_ignore_tag_case("tx"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+
+    let extended = TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap();
+    let retyped = extended.to_string().replacen("TX", "tx", 1);
+    assert_eq!(
+        TaggedBase64::parse_ignore_tag_case(&retyped).unwrap(),
+        extended
+    );
+
+    let mut tb64 = TaggedBase64::new("my-Tag_1", b"foobar").unwrap();
+    tb64.make_tag_uppercase();
+    assert_eq!(tb64.tag(), "MY-TAG_1");
+    assert!(tb64.verify_checksum());
+    assert_eq!(tb64, TaggedBase64::new("MY-TAG_1", b"foobar").unwrap());
+    assert!(tb64.has_tag_ignore_ascii_case("my-tag_1"));
+    assert!(tb64.eq_ignore_tag_case(&TaggedBase64::new("my-tag_1", b"foobar").unwrap()));
+}
+
+#[test]
+fn test_parse_limits() {
+    let limits = ParseLimits {
+        max_tag_len: 4,
+        max_decoded_len: 10,
+        max_expanded_len: 10,
+    };
+    for len in [0, 1, 9, 10] {
+        let tb64 = TaggedBase64::new("TAG", &vec![7; len]).unwrap();
+        assert_eq!(
+            TaggedBase64::parse_with_limits(&tb64.to_string(), limits).unwrap(),
+            tb64
+        );
+    }
+    let tb64 = TaggedBase64::new("TAG", &[7; 11]).unwrap();
+    assert!(matches!(
+        TaggedBase64::parse_with_limits(&tb64.to_string(), limits),
+        Err(Tb64Error::DecodedTooLong { len: 11, max: 10 })
+    ));
+    let tb64 = TaggedBase64::new("TAGS5", &[7; 10]).unwrap();
+    assert!(matches!(
+        TaggedBase64::parse_with_limits(&tb64.to_string(), limits),
+        Err(Tb64Error::TagTooLong { len: 5 })
+    ));
+
+    // A run-length coded value is checked once expanded.
+    let sparse = TaggedBase64::new("TAG", &[0; 1000]).unwrap();
+    let compact = sparse.to_string_compact();
+    let limits = ParseLimits {
+        max_decoded_len: 100,
+        max_expanded_len: 1000,
+        ..limits
+    };
+    assert!(matches!(
+        TaggedBase64::parse_with_limits(&compact, limits),
+        Err(Tb64Error::DecodedTooLong {
+            len: 1000,
+            max: 100
+        })
+    ));
+    let limits = ParseLimits {
+        max_decoded_len: 1000,
+        ..limits
+    };
+    assert_eq!(
+        TaggedBase64::parse_with_limits(&compact, limits).unwrap(),
+        sparse
+    );
+    let limits = ParseLimits {
+        max_expanded_len: 999,
+        ..limits
+    };
+    assert!(matches!(
+        TaggedBase64::parse_with_limits(&compact, limits),
+        Err(Tb64Error::DecodedTooLong {
+            len: 1000,
+            max: 999
+        })
+    ));
+
+    // Plain parse bounds expansion too, however short the string.
+    let full = TaggedBase64::new("TX", &vec![0; TB64_MAX_EXPANDED_LEN]).unwrap();
+    assert_eq!(
+        TaggedBase64::parse(&full.to_string_compact()).unwrap(),
+        full
+    );
+    let bomb = TaggedBase64::new("TX", &vec![0; 4 * TB64_MAX_EXPANDED_LEN]).unwrap();
+    let s = bomb.to_string_compact();
+    assert!(s.len() * 20 < bomb.len());
+    let mut buf = Vec::new();
+    for err in [
+        TaggedBase64::parse(&s).unwrap_err(),
+        TaggedBase64Ref::parse(&s).unwrap_err(),
+        TaggedBase64::decode_into(&s, &mut buf).unwrap_err(),
+    ] {
+        assert!(matches!(
+            err,
+            Tb64Error::DecodedTooLong { len, max }
+                if len == 4 * TB64_MAX_EXPANDED_LEN && max == TB64_MAX_EXPANDED_LEN
+        ));
+    }
+
+    // The defaults are for untrusted input; parse itself has no value limit.
+    assert_eq!(ParseLimits::default().max_tag_len, TB64_MAX_TAG_LEN);
+    let big = TaggedBase64::new("TX", &vec![1; TB64_UNTRUSTED_MAX_DECODED_LEN + 1]).unwrap();
+    let err = TaggedBase64::parse_untrusted(&big.to_string()).unwrap_err();
+    assert_eq!(err.code(), "DECODED_TOO_LONG");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "value of {} bytes is longer than the limit of {}",
+            TB64_UNTRUSTED_MAX_DECODED_LEN + 1,
+            TB64_UNTRUSTED_MAX_DECODED_LEN
+        )
+    );
+    assert_eq!(TaggedBase64::parse(&big.to_string()).unwrap(), big);
+    let ok = TaggedBase64::new("TX", &vec![1; TB64_UNTRUSTED_MAX_DECODED_LEN]).unwrap();
+    assert_eq!(TaggedBase64::parse_untrusted(&ok.to_string()).unwrap(), ok);
+}
+
+#[test]
+fn test_parse_bytes() {
+    let stamped = TaggedBase64::new_timestamped("TX", b"foobar", 1)
+        .unwrap()
+        .to_string();
+    let compact = TaggedBase64::new("TX", &[0; 100])
+        .unwrap()
+        .to_string_compact();
+    for s in [
+        "TX~Zm9vYmFy1w",
+        "~tw",
+        stamped.as_str(),
+        compact.as_str(),
+        ".5458~Zm9vYmFy1w",
+        "TY~Zm9vYmFy1w",
+        "TX~Zm9v*mFy1w",
+        "T X~Zm9vYmFy1w",
+        "TX",
+        "TX~",
+        "TX~Zm9vY",
+        "Té~Zm9vYmFy1w",
+    ] {
+        match (
+            TaggedBase64::parse(s),
+            TaggedBase64::parse_bytes(s.as_bytes()),
+        ) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b, "{s}"),
+            (Err(a), Err(b)) => {
+                assert_eq!(a.code(), b.code(), "{s}");
+                assert_eq!(a.offset(), b.offset(), "{s}");
+            }
+            (a, b) => panic!("{s}: {a:?} != {b:?}"),
+        }
+    }
+
+    // Bytes which are not UTF-8 are reported where they are.
+    assert!(matches!(
+        TaggedBase64::parse_bytes(b"TX~Zm9vYmFy1\x80"),
+        Err(Tb64Error::Base64 {
+            error: base64::DecodeError::InvalidByte(9, 0x80)
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_bytes(b"T\xffX~Zm9vYmFy1w"),
+        Err(Tb64Error::NonAsciiTag)
+    ));
+}
+
+#[test]
+fn test_const_tag() {
+    const TX: &str = const_tag!("TX");
+    assert_eq!(TX, "TX");
+    const {
+        assert!(!TaggedBase64::is_safe_base64_tag("T~X"));
+        assert!(!TaggedBase64::is_valid_tag(
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        ));
+    }
+
+    // The const checks agree with validate_tag.
+    for tag in [
+        "",
+        "TX",
+        "a-_9",
+        "T X",
+        "T~",
+        "Té",
+        &"A".repeat(64),
+        &"A".repeat(65),
+    ] {
+        assert_eq!(
+            TaggedBase64::is_valid_tag(tag),
+            TaggedBase64::validate_tag(tag).is_ok(),
+            "{tag}"
+        );
+        assert_eq!(
+            TaggedBase64::is_safe_base64_tag(tag),
+            tag.chars().all(TaggedBase64::is_safe_base64_ascii),
+            "{tag}"
+        );
+    }
+}
+
+#[test]
+fn test_without_checksum() {
+    for value in [&b""[..], b"f", b"fo", b"foo", b"foobar"] {
+        let tb = TaggedBase64::new("TX", value).unwrap();
+        let s = tb.to_string_without_checksum();
+        assert_eq!(s, format!("TX~{}", TaggedBase64::encode_raw(value)));
+        assert_eq!(TaggedBase64::parse_without_checksum(&s).unwrap(), tb);
+    }
+
+    // The plain form would otherwise be misread, its last byte taken for
+    // a checksum.
+    assert!(TaggedBase64::parse("TX~Zm9vYmFy").is_err());
+
+    // An extended value reads back as plain bytes.
+    let stamped = TaggedBase64::new_timestamped("TX", b"foobar", 1).unwrap();
+    let plain =
+        TaggedBase64::parse_without_checksum(&stamped.to_string_without_checksum()).unwrap();
+    assert_eq!(plain.value(), stamped.value());
+    assert!(plain.timestamp().is_none());
+
+    assert!(matches!(
+        TaggedBase64::parse_without_checksum("TX"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_without_checksum("T X~Zm9v"),
+        Err(Tb64Error::InvalidTagChar { offset: 1, .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_without_checksum("TX~Zm9v="),
+        Err(Tb64Error::Base64 { .. })
+    ));
+}
+
+#[test]
+fn test_nested() {
+    let inner = vec![
+        TaggedBase64::new("ADDR", &[1; 32]).unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("PROOF", &[7; 300]).unwrap(),
+        TaggedBase64::new_timestamped("MEMO", b"lunch", 5).unwrap(),
+    ];
+    let bundle = TaggedBase64::encode_nested("BUNDLE", &inner).unwrap();
+    assert_eq!(bundle.tag(), "BUNDLE");
+    let parsed = TaggedBase64::parse(&bundle.to_string()).unwrap();
+    assert_eq!(parsed.decode_nested().unwrap(), inner);
+
+    // Bundles nest.
+    let outer = TaggedBase64::encode_nested("OUTER", &[bundle.clone(), inner[0].clone()]).unwrap();
+    let unpacked = outer.decode_nested().unwrap();
+    assert_eq!(unpacked[0].decode_nested().unwrap(), inner);
+    assert_eq!(unpacked[1], inner[0]);
+
+    let empty = TaggedBase64::encode_nested("BUNDLE", &[]).unwrap();
+    assert!(empty.value().is_empty());
+    assert!(empty.decode_nested().unwrap().is_empty());
+    assert!(matches!(
+        TaggedBase64::encode_nested("B~", &inner),
+        Err(Tb64Error::InvalidTagChar { offset: 1, .. })
+    ));
+
+    // Truncated framing and corrupt inner values are caught.
+    let value = bundle.value();
+    let truncated = TaggedBase64::new("BUNDLE", &value[..value.len() - 1]).unwrap();
+    assert!(matches!(
+        truncated.decode_nested(),
+        Err(Tb64Error::InvalidLength)
+    ));
+    let mut corrupt = value.clone();
+    corrupt[40] ^= 1;
+    assert!(matches!(
+        TaggedBase64::new("BUNDLE", &corrupt)
+            .unwrap()
+            .decode_nested(),
+        Err(Tb64Error::InvalidElement { index: 1, .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::new("BUNDLE", &[0x80])
+            .unwrap()
+            .decode_nested(),
+        Err(Tb64Error::InvalidLength)
+    ));
+}
+
+/// Checks that validate agrees with parse on `s`, including the error.
+fn check_validate(s: &str) {
+    assert_eq!(
+        format!("{:?}", TaggedBase64::validate(s)),
+        format!("{:?}", TaggedBase64::parse(s).map(|_| ())),
+        "{:?}",
+        s
+    );
+}
+
+#[test]
+fn test_validate() {
+    let long = TaggedBase64::new("TX", &[7; 1000]).unwrap().to_string();
+    let ext = TaggedBase64::new_timestamped("TX", b"foobar", 17)
+        .unwrap()
+        .to_string();
+    for s in [
+        "TX~Zm9vYmFy1w",
+        &TaggedBase64::new("Zg", b"f").unwrap().to_string(),
+        &long,
+        &ext,
+        &TaggedBase64::new("", b"").unwrap().to_string(),
+    ] {
+        assert!(TaggedBase64::validate(s).is_ok(), "{:?}", s);
+    }
+    for s in [
+        "TX",
+        "TX~",
+        "TX~Z",
+        "T.X~Zm9vYmFy1w",
+        "TX~Zm9vYmFy1x",
+        "TX~Zm9vYmFy1w==",
+        "TX~Zm9v+mFy1w",
+        "TY~Zm9vYmFy1w",
+        &long.replacen('H', "I", 1),
+        &format!("{}=", &long[..long.len() - 1]),
+        &ext.replacen("TX", "TY", 1),
+    ] {
+        assert!(TaggedBase64::validate(s).is_err(), "{:?}", s);
+        check_validate(s);
+    }
+}
+
+#[quickcheck]
+fn validate_quickcheck(s: String, tag: String, value: Vec<u8>) -> bool {
+    check_validate(&s);
+    check_validate(&format!("TX~{}", s));
+    if let Ok(tb) = TaggedBase64::new(&tag, &value) {
+        check_validate(&tb.to_string());
+    }
+    true
+}
+
+#[tagged("BLOB")]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Blob(Vec<u8>);
+
+#[tagged("BLOB", compressed)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BlobCompressed(Vec<u8>);
+
+#[tagged("TX")]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct TransactionId(Vec<u8>);
+
+#[tagged("LA")]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct LedgerAddress {
+    index: u64,
+    key: Vec<u8>,
+}
+
+#[tagged("BLOB", checked)]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct BlobChecked(Vec<u8>);
+
+#[tagged("BLOB", compressed, checked)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BlobCompressedChecked(Vec<u8>);
+
+impl Valid for BlobCompressed {
+    fn check(&self) -> Result<(), SerializationError> {
+        // Mock test, validation always fails
+        Err(SerializationError::InvalidData)
+    }
+}
+
+impl CanonicalSerialize for BlobCompressed {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        // Mock test, always serialize into fixed bytes array
+        match compress {
+            Compress::Yes => writer
+                .write_all(&[1])
+                .map_err(|_| SerializationError::InvalidData),
+            Compress::No => writer
+                .write_all(&[1, 2])
+                .map_err(|_| SerializationError::InvalidData),
+        }
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        // Mock test, always return the same size
+        // Actually this function is not used in derived serde implementation.
+        if compress == Compress::Yes {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl CanonicalDeserialize for BlobCompressed {
+    fn deserialize_with_mode<R: Read>(
+        _reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        if validate == Validate::Yes {
+            unreachable!()
+        } else {
+            match compress {
+                Compress::Yes => Ok(Self(vec![1])),
+                Compress::No => Ok(Self(vec![1, 2])),
+            }
+        }
+    }
+}
+impl Valid for BlobCompressedChecked {
+    fn check(&self) -> Result<(), SerializationError> {
+        // Mock test, validation always fails
+        Err(SerializationError::InvalidData)
+    }
+}
+
+impl CanonicalSerialize for BlobCompressedChecked {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        // Mock test, always serialize into fixed bytes array
+        match compress {
+            Compress::Yes => writer
+                .write_all(&[1])
+                .map_err(|_| SerializationError::InvalidData),
+            Compress::No => writer
+                .write_all(&[1, 2])
+                .map_err(|_| SerializationError::InvalidData),
+        }
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        // Mock test, always return the same size
+        // Actually this function is not used in derived serde implementation.
+        if compress == Compress::Yes {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl CanonicalDeserialize for BlobCompressedChecked {
+    fn deserialize_with_mode<R: Read>(
+        _reader: R,
+        _compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        if validate == Validate::Yes {
+            // Mock test, validation always fails
+            Err(SerializationError::InvalidData)
+        } else {
+            unreachable!()
+        }
+    }
+}
+
+#[test]
+fn test_tagged() {
+    let bytes = (0..100).collect();
+    let b = Blob(bytes);
+    let t = TaggedBase64::from(&b);
+    assert!(t.to_string().starts_with("BLOB~"));
+    assert_eq!(b, t.try_into().unwrap());
+}
+
+#[test]
+fn test_tagged_id_types() {
+    let tx = TransactionId(vec![1, 2, 3]);
+    let la = LedgerAddress {
+        index: 7,
+        key: vec![1, 2, 3],
+    };
+    assert!(tx.to_string().starts_with("TX~"));
+    assert!(la.to_string().starts_with("LA~"));
+    assert_eq!(tx.to_string().parse::<TransactionId>().unwrap(), tx);
+    assert_eq!(la.to_string().parse::<LedgerAddress>().unwrap(), la);
+
+    let json = serde_json::to_string(&tx).unwrap();
+    assert_eq!(json, format!("\"{}\"", tx));
+    assert_eq!(serde_json::from_str::<TransactionId>(&json).unwrap(), tx);
+
+    // The same bytes under another tag are rejected.
+    let retagged = TaggedBase64::new("LA", &TaggedBase64::from(&tx).value()).unwrap();
+    assert!(matches!(
+        TransactionId::try_from(retagged.clone()),
+        Err(Tb64Error::InvalidTag)
+    ));
+    let json = serde_json::to_string(&retagged).unwrap();
+    assert!(serde_json::from_str::<TransactionId>(&json).is_err());
+}
+
+#[test]
+fn test_canonical() {
+    let x = ark_bls12_381::Fr::from(42u64);
+    let tb = TaggedBase64::from_canonical("FR", &x).unwrap();
+    assert_eq!(tb.tag(), "FR");
+    assert_eq!(tb.to_canonical::<ark_bls12_381::Fr>().unwrap(), x);
+    assert!(TaggedBase64::from_canonical("F+", &x).is_err());
+
+    // Not a valid serialization of a field element.
+    let short = TaggedBase64::new("FR", &tb.value()[1..]).unwrap();
+    assert!(matches!(
+        short.to_canonical::<ark_bls12_381::Fr>(),
+        Err(Tb64Error::InvalidData)
+    ));
+
+    let la = LedgerAddress {
+        index: 7,
+        key: vec![1, 2, 3],
+    };
+    let tb = TaggedBase64::from_canonical("LA", &la).unwrap();
+    assert_eq!(tb.to_canonical_tagged::<LedgerAddress>().unwrap(), la);
+    let tb = TaggedBase64::new("TX", &tb.value()).unwrap();
+    assert!(matches!(
+        tb.to_canonical_tagged::<LedgerAddress>(),
+        Err(Tb64Error::InvalidTag)
+    ));
+}
+
+#[test]
+fn test_tagged_from_str() {
+    let b = Blob((0..10).collect());
+    let s = b.to_string();
+    assert_eq!(s.parse::<Blob>().unwrap(), b);
+
+    // The tag must match the type's tag.
+    let other = TaggedBase64::new("TX", &TaggedBase64::from(&b).value()).unwrap();
+    assert!(matches!(
+        other.to_string().parse::<Blob>(),
+        Err(Tb64Error::InvalidTag)
+    ));
+    assert!(matches!(
+        "BLOB~AAAA".parse::<Blob>(),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    let garbage = TaggedBase64::new("BLOB", &[1]).unwrap();
+    assert!(matches!(
+        garbage.to_string().parse::<Blob>(),
+        Err(Tb64Error::InvalidData)
+    ));
+}
+
+#[test]
+fn test_serde_json_str() {
+    let bytes = (0..100).collect::<Vec<_>>();
+    let t = TaggedBase64::new("TAG", &bytes).unwrap();
+    let s = serde_json::to_string(&t).unwrap();
+    assert!(s.starts_with("\"TAG~"));
+    assert_eq!(t, serde_json::from_str(&s).unwrap());
+
+    // Deserializing verifies the tag and checksum.
+    let tampered = s.replacen("TAG", "TAH", 1);
+    assert!(serde_json::from_str::<TaggedBase64>(&tampered).is_err());
+    let invalid = s.replacen("TAG", "T+G", 1);
+    assert!(serde_json::from_str::<TaggedBase64>(&invalid).is_err());
+}
+
+#[test]
+fn test_serde_json_value() {
+    let bytes = (0..100).collect::<Vec<_>>();
+    let t = TaggedBase64::new("TAG", &bytes).unwrap();
+    let v = serde_json::to_value(&t).unwrap();
+    assert_eq!(t, serde_json::from_value(v).unwrap());
+}
+
+#[test]
+fn test_serde_json_map() {
+    let t = TaggedBase64::new("TX", b"foobar").unwrap();
+    for json in [
+        r#"{"tag": "TX", "value": [102, 111, 111, 98, 97, 114]}"#,
+        r#"{"base64": "Zm9vYmFy", "tag": "TX"}"#,
+    ] {
+        assert_eq!(serde_json::from_str::<TaggedBase64>(json).unwrap(), t);
+        let v: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::from_value::<TaggedBase64>(v).unwrap(), t);
+    }
+    // Serializing still produces the string form.
+    assert_eq!(serde_json::to_string(&t).unwrap(), r#""TX~Zm9vYmFy1w""#);
+
+    for json in [
+        r#"{"tag": "T~X", "value": []}"#,
+        r#"{"tag": "TX"}"#,
+        r#"{"value": []}"#,
+        r#"{"tag": "TX", "value": [], "base64": ""}"#,
+        r#"{"tag": "TX", "tag": "TX", "value": []}"#,
+        r#"{"tag": "TX", "base64": "Zm9v*mFy"}"#,
+        r#"{"tag": "TX", "value": [256]}"#,
+        r#"{"tag": "TX", "value": [], "checksum": 0}"#,
+        "42",
+    ] {
+        assert!(
+            serde_json::from_str::<TaggedBase64>(json).is_err(),
+            "{json}"
+        );
+    }
+}
+
+#[test]
+fn test_serde_bincode() {
+    let bytes = (0..100).collect::<Vec<_>>();
+    let t = TaggedBase64::new("TAG", &bytes).unwrap();
+    assert_eq!(
+        t,
+        bincode::deserialize(&bincode::serialize(&t).unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn test_serde_binary_formats() {
+    let values = [
+        TaggedBase64::new("TX", b"foobar").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("TAG", &(0..=255).collect::<Vec<_>>()).unwrap(),
+        TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap(),
+    ];
+    for t in values {
+        let bytes = bincode::serialize(&t).unwrap();
+        assert_eq!(bincode::deserialize::<TaggedBase64>(&bytes).unwrap(), t);
+        let bytes = postcard::to_allocvec(&t).unwrap();
+        assert_eq!(postcard::from_bytes::<TaggedBase64>(&bytes).unwrap(), t);
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&t, &mut bytes).unwrap();
+        assert_eq!(
+            ciborium::from_reader::<TaggedBase64, _>(bytes.as_slice()).unwrap(),
+            t
+        );
+    }
+
+    // The tag, the raw value and the checksum, without base64 or a nested encoding.
+    let t = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(
+        bincode::serialize(&t).unwrap(),
+        bincode::serialize(&("TX", &b"foobar"[..], t.checksum())).unwrap()
+    );
+    let mut expected = vec![2];
+    expected.extend(b"TX");
+    expected.push(6);
+    expected.extend(b"foobar");
+    expected.push(t.checksum());
+    assert_eq!(postcard::to_allocvec(&t).unwrap(), expected);
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&t, &mut bytes).unwrap();
+    assert!(bytes.windows(7).any(|w| w == b"\x46foobar"));
+}
+
+#[test]
+fn test_serde_validates() {
+    let t = TaggedBase64::new("TAG", &[1, 2, 3]).unwrap();
+
+    let mut bytes = bincode::serialize(&t).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    let err = bincode::deserialize::<TaggedBase64>(&bytes).unwrap_err();
+    assert_eq!(err.to_string(), Tb64Error::InvalidChecksum.to_string());
+
+    let mut bytes = bincode::serialize(&t).unwrap();
+    let pos = bytes.windows(3).position(|w| w == b"TAG").unwrap();
+    bytes[pos + 1] = b'+';
+    let err = bincode::deserialize::<TaggedBase64>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("invalid character '+'"));
+
+    let s = serde_json::to_string(&t).unwrap().replacen("TAG", "TAH", 1);
+    assert!(serde_json::from_str::<TaggedBase64>(&s).is_err());
+}
+
+#[test]
+fn test_serde_compressed_checked() {
+    let blob = BlobCompressedChecked(vec![1, 2]);
+    let bytes = bincode::serialize(&blob).unwrap();
+    assert!(bincode::deserialize::<BlobCompressedChecked>(&bytes).is_err());
+
+    let blob = BlobCompressed(vec![1, 2]);
+    let bytes = bincode::serialize(&blob).unwrap();
+    assert_eq!(
+        bincode::deserialize::<BlobCompressed>(&bytes).unwrap(),
+        BlobCompressed(vec![1])
+    );
+}
+
+#[test]
+fn test_compat() {
+    // A hard-coded example, for easily checking compatibility with ports to other languages.
+    let tag = "abcdefghijklmnopqrstuvwxyz-ABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
+    let data = "~Yeah, we can have spaces and odd stuff—😀 here. ¯⧵_(ツ)_/¯".as_bytes();
+    let expected = "abcdefghijklmnopqrstuvwxyz-ABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789~flllYWgsIHdlIGNhbiBoYXZlIHNwYWNlcyBhbmQgb2RkIHN0dWZm4oCU8J-YgCBoZXJlLiDCr-KntV8o44OEKV8vwq_6";
+
+    let tb64 = TaggedBase64::new(tag, data).unwrap();
+    let s = tb64.to_string();
+    assert_eq!(
+        s, expected,
         "actual string {s} does not match expected {expected}"
     );
-    assert_eq!(tb64, expected.parse().unwrap());
+    assert_eq!(tb64, expected.parse().unwrap());
+}
+
+#[test]
+fn test_data_uri() {
+    let value = (0..=255).collect::<Vec<u8>>();
+    let tb64 = TaggedBase64::new("IMG", &value).unwrap();
+    let uri = tb64.to_data_uri("image/png");
+    let b64 = uri.strip_prefix("data:image/png;base64,").unwrap();
+    assert_eq!(
+        base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .unwrap(),
+        value
+    );
+}
+
+#[test]
+fn test_is_canonical() {
+    let t = TaggedBase64::new("TAG", b"").unwrap();
+    assert_eq!(t.to_string(), "TAG~Ew");
+    assert!(TaggedBase64::is_canonical("TAG~Ew").unwrap());
+
+    // Padding and non-zero trailing bits are decodable but not canonical.
+    assert!(!TaggedBase64::is_canonical("TAG~Ew==").unwrap());
+    assert!(!TaggedBase64::is_canonical("TAG~Ex").unwrap());
+    assert!(TaggedBase64::parse("TAG~Ex").is_err());
+
+    assert!(TaggedBase64::is_canonical("TAG").is_err());
+    assert!(TaggedBase64::is_canonical("TAG~AA").is_err());
+}
+
+#[test]
+fn test_checksum_word() {
+    // "TAG~Ew" carries the checksum 0x13.
+    let t = TaggedBase64::parse("TAG~Ew").unwrap();
+    assert_eq!(t.checksum_word(), "Aztec");
+    assert_eq!(CHECKSUM_WORDS[0], "aardvark");
+    assert_eq!(CHECKSUM_WORDS[255], "Zulu");
+}
+
+#[test]
+fn test_decode_into() {
+    let mut buf = Vec::new();
+    for (tag, value) in [
+        ("A", &b"some longer value to grow the buffer"[..]),
+        ("BB", b"short"),
+        ("", b""),
+        ("TX", b"transaction identifier goes here"),
+    ] {
+        let s = TaggedBase64::new(tag, value).unwrap().to_string();
+        assert_eq!(TaggedBase64::decode_into(&s, &mut buf).unwrap(), tag);
+        assert_eq!(buf, value);
+    }
+    assert!(matches!(
+        TaggedBase64::decode_into("AAA~AAA", &mut buf),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_sortable_key() {
+    let values = [
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("A", b"\xff").unwrap(),
+        TaggedBase64::new("A", b"").unwrap(),
+        TaggedBase64::new("A", b"\x01\x02").unwrap(),
+        TaggedBase64::new("A", b"\x01").unwrap(),
+        TaggedBase64::new("A-", b"").unwrap(),
+        TaggedBase64::new("AB", b"\x00").unwrap(),
+        TaggedBase64::new("A_", b"\x10").unwrap(),
+        TaggedBase64::new("a", b"\x00").unwrap(),
+        TaggedBase64::new("9", b"\x00").unwrap(),
+    ];
+    let mut by_cmp = values.to_vec();
+    by_cmp.sort_by(|a, b| a.canonical_cmp(b));
+    let mut by_key = values.to_vec();
+    by_key.sort_by_key(|t| t.sortable_key());
+    assert_eq!(by_cmp, by_key);
+    assert_eq!(values[3].sortable_key(), "A!0102");
+}
+
+#[test]
+fn test_ordered_string() {
+    let mut values = vec![
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("A", b"\xff").unwrap(),
+        TaggedBase64::new("A", b"").unwrap(),
+        TaggedBase64::new("A", b"\x01\x02").unwrap(),
+        TaggedBase64::new("A", b"\x01").unwrap(),
+        TaggedBase64::new("A", b"\x01\x00").unwrap(),
+        TaggedBase64::new("A", b"\x00\xff\xff\xff").unwrap(),
+        TaggedBase64::new("A-", b"").unwrap(),
+        TaggedBase64::new("AB", b"\x00").unwrap(),
+        TaggedBase64::new("A_", b"\x10").unwrap(),
+        TaggedBase64::new("a", b"\x00").unwrap(),
+        TaggedBase64::new("9", b"\x00").unwrap(),
+        TaggedBase64::new("TX", b"foobar").unwrap(),
+    ];
+    // Every value of up to three bytes drawn from a few bytes, which
+    // covers a difference in each position of each base64 group.
+    let mut prefixes = vec![vec![]];
+    for _ in 0..3 {
+        let mut longer = vec![];
+        for prefix in &prefixes {
+            for b in [0x00, 0x55, 0xaa, 0xff] {
+                let mut value: Vec<u8> = prefix.clone();
+                value.push(b);
+                longer.push(value);
+            }
+        }
+        values.extend(prefixes.iter().map(|v| TaggedBase64::new("R", v).unwrap()));
+        prefixes = longer;
+    }
+    values.extend(prefixes.iter().map(|v| TaggedBase64::new("R", v).unwrap()));
+    values.push(TaggedBase64::new_timestamped("X", b"payload", 1_700_000_000).unwrap());
+
+    let mut by_cmp = values.clone();
+    by_cmp.sort_by(|a, b| a.canonical_cmp(b));
+    let mut by_string = values.clone();
+    by_string.sort_by_key(|t| t.to_ordered_string());
+    assert_eq!(by_cmp, by_string);
+
+    for tb64 in &values {
+        let s = tb64.to_ordered_string();
+        assert_eq!(&TaggedBase64::from_ordered_string(&s).unwrap(), tb64);
+    }
+    assert_eq!(
+        TaggedBase64::new("TX", b"foobar")
+            .unwrap()
+            .to_ordered_string(),
+        "TX!OaxjNa4m!pk"
+    );
+
+    assert!(matches!(
+        TaggedBase64::from_ordered_string("TX~Zm9vYmFy1w"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_ordered_string("TX!OaxjNa4m"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_ordered_string("TY!OaxjNa4m!pk"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_ordered_string("TX!Oaxj+a4m!pk"),
+        Err(Tb64Error::Base64 { .. })
+    ));
+}
+
+#[test]
+fn test_encode_batch() {
+    let pairs: [(&str, &[u8]); 4] = [
+        ("TX", b"transaction identifier goes here"),
+        ("", b""),
+        ("KEY", b"public key bits"),
+        ("Zg", b"f"),
+    ];
+    let encoded = TaggedBase64::encode_batch(&pairs).unwrap();
+    assert_eq!(encoded.len(), pairs.len());
+    for ((tag, value), s) in pairs.iter().zip(&encoded) {
+        assert_eq!(*s, TaggedBase64::new(tag, value).unwrap().to_string());
+    }
+
+    assert!(matches!(
+        TaggedBase64::encode_batch(&[("A", b"a"), ("B~", b"b")]),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+}
+
+#[test]
+fn test_decode_batch() {
+    let values: Vec<TaggedBase64> = (0..1000u32)
+        .map(|i| TaggedBase64::new("ID", &i.to_be_bytes()).unwrap())
+        .collect();
+    let mut strings: Vec<String> = values.iter().map(|t| t.to_string()).collect();
+    strings[3] = "ID~bogus".to_string();
+    strings[500] = "I D~AAAAAAA".to_string();
+    let refs: Vec<&str> = strings.iter().map(String::as_str).collect();
+
+    let results = TaggedBase64::decode_batch(&refs);
+    assert_eq!(results.len(), values.len());
+    for (i, (result, value)) in results.iter().zip(&values).enumerate() {
+        match (i, result) {
+            (3, Err(Tb64Error::InvalidElement { index: 3, .. })) => {}
+            (500, Err(Tb64Error::InvalidElement { index: 500, error })) => {
+                assert!(matches!(
+                    **error,
+                    Tb64Error::InvalidTagChar { offset: 1, .. }
+                ))
+            }
+            (_, Ok(parsed)) => assert_eq!(parsed, value),
+            _ => panic!("{i}: {result:?}"),
+        }
+    }
+    assert!(TaggedBase64::decode_batch(&[]).is_empty());
+
+    // The batches round trip.
+    let pairs: Vec<(&str, &[u8])> = values.iter().map(|t| ("ID", t.value_ref())).collect();
+    let encoded = TaggedBase64::encode_batch(&pairs).unwrap();
+    let refs: Vec<&str> = encoded.iter().map(String::as_str).collect();
+    let decoded: Vec<TaggedBase64> = TaggedBase64::decode_batch(&refs)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(decoded, values);
+}
+
+#[test]
+fn test_timestamped() {
+    let secs = 1_700_000_000;
+    let t = TaggedBase64::new_timestamped("EVT", b"payload", secs).unwrap();
+    assert_eq!(t.timestamp(), Some(secs));
+    assert_eq!(t.payload(), b"payload");
+
+    let s = t.to_string();
+    assert!(s.starts_with("EVT~~"));
+    let parsed = TaggedBase64::parse(&s).unwrap();
+    assert_eq!(parsed, t);
+    assert_eq!(parsed.timestamp(), Some(secs));
+    assert_eq!(parsed.payload(), b"payload");
+
+    // Retagging keeps the timestamp.
+    let mut retagged = parsed.clone();
+    retagged.set_tag("LOG");
+    assert_eq!(retagged.timestamp(), Some(secs));
+    assert_eq!(
+        TaggedBase64::parse(&retagged.to_string()).unwrap(),
+        retagged
+    );
+
+    // An empty payload is fine.
+    let t = TaggedBase64::new_timestamped("EVT", b"", 0).unwrap();
+    assert_eq!(
+        TaggedBase64::parse(&t.to_string()).unwrap().timestamp(),
+        Some(0)
+    );
+
+    // Plain values have no timestamp, even if their bytes look like one.
+    let plain = TaggedBase64::new("EVT", &t.value()).unwrap();
+    assert_eq!(plain.timestamp(), None);
+    assert_eq!(plain.payload(), &t.value()[..]);
+    assert!(!plain.to_string().contains("~~"));
+
+    // The two forms can't be confused when parsing.
+    let plain_body = plain.to_string().split_once('~').unwrap().1.to_string();
+    assert!(matches!(
+        TaggedBase64::parse(&format!("EVT~~{}", plain_body)),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    let ts_body = t.to_string().split_once("~~").unwrap().1.to_string();
+    assert!(matches!(
+        TaggedBase64::parse(&format!("EVT~{}", ts_body)),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+
+    // Timestamps must be complete.
+    let short = TaggedBase64::new("EVT", &[1, 0, 0, 0]).unwrap();
+    let mut bytes = TaggedBase64::decode_raw(short.to_string().split_once('~').unwrap().1).unwrap();
+    *bytes.last_mut().unwrap() ^= 0x80;
+    assert!(matches!(
+        TaggedBase64::parse(&format!("EVT~~{}", TaggedBase64::encode_raw(&bytes))),
+        Err(Tb64Error::InvalidData)
+    ));
+}
+
+#[test]
+fn test_allowed_tags() {
+    let allowed = ["TX", "LA"];
+    let tx = TaggedBase64::new("TX", b"tx").unwrap();
+    assert_eq!(
+        TaggedBase64::try_from_allowed_tags(&tx.to_string(), &allowed).unwrap(),
+        tx
+    );
+
+    let key = TaggedBase64::new("KEY", b"key").unwrap();
+    assert!(matches!(
+        TaggedBase64::try_from_allowed_tags(&key.to_string(), &allowed),
+        Err(Tb64Error::TagNotAllowed { tag }) if tag == "KEY"
+    ));
+
+    assert!(matches!(
+        TaggedBase64::try_from_allowed_tags("TX", &allowed),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::try_from_allowed_tags("TX~AAA", &allowed),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_decoded_region() {
+    for t in [
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new_timestamped("EVT", b"event", 42).unwrap(),
+    ] {
+        let region = t.decoded_region();
+        assert_eq!(
+            TaggedBase64::decode_raw(&t.encoded_value()).unwrap(),
+            region
+        );
+        assert_eq!(&region[..region.len() - 1], &t.value()[..]);
+        assert!(t.to_string().ends_with(&t.encoded_value()));
+    }
+}
+
+#[test]
+fn test_hex_tag() {
+    // A storage layer that folds the case of tags.
+    fn fold_tag(s: &str, fold: fn(&str) -> String) -> String {
+        let (tag, value) = s.split_once('~').unwrap();
+        format!("{}~{}", fold(tag), value)
+    }
+
+    for t in [
+        TaggedBase64::new("TxId", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("a-B_9", b"\x00\x01").unwrap(),
+        TaggedBase64::new_timestamped("Evt", b"event", 42).unwrap(),
+    ] {
+        let s = t.to_string_hex_tag();
+        assert!(s.starts_with('.'));
+        assert_eq!(s.split_once('~').unwrap().0.len(), 1 + 2 * t.tag().len());
+        for folded in [
+            fold_tag(&s, str::to_ascii_lowercase),
+            fold_tag(&s, str::to_ascii_uppercase),
+        ] {
+            let parsed = TaggedBase64::parse(&folded).unwrap();
+            assert_eq!(parsed, t);
+            assert_eq!(parsed.tag(), t.tag());
+        }
+    }
+
+    // The plain form does not survive case folding.
+    let t = TaggedBase64::new("TxId", b"abc").unwrap();
+    let folded = fold_tag(&t.to_string(), str::to_ascii_lowercase);
+    assert!(TaggedBase64::parse(&folded).is_err());
+
+    // The hex must decode to a valid tag.
+    assert!(matches!(
+        TaggedBase64::parse(".5~wA"),
+        Err(Tb64Error::InvalidTag)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse(".7e~wA"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 0,
+            character: '~'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse(".zz~wA"),
+        Err(Tb64Error::InvalidTag)
+    ));
+}
+
+#[test]
+fn test_tag_matches_glob() {
+    let tb = |tag| TaggedBase64::new(tag, b"").unwrap();
+
+    assert!(tb("TX_MINT").tag_matches_glob("TX_*"));
+    assert!(tb("TX_MINT").tag_matches_glob("*_MINT"));
+    assert!(tb("TX_").tag_matches_glob("TX_*"));
+    assert!(!tb("TX").tag_matches_glob("TX_*"));
+    assert!(!tb("LA_MINT").tag_matches_glob("TX_*"));
+    assert!(tb("TX_MINT").tag_matches_glob("*"));
+    assert!(tb("").tag_matches_glob("*"));
+    assert!(tb("TX_MINT").tag_matches_glob("T*_*T"));
+    assert!(!tb("TX_MINT").tag_matches_glob("T*_*X"));
+
+    assert!(tb("TX1").tag_matches_glob("TX?"));
+    assert!(!tb("TX").tag_matches_glob("TX?"));
+    assert!(!tb("TX12").tag_matches_glob("TX?"));
+    assert!(tb("TX12").tag_matches_glob("?X??"));
+
+    assert!(tb("KEY").tag_matches_glob("KEY"));
+    assert!(!tb("KEY").tag_matches_glob("key"));
+    assert!(!tb("KEY").tag_matches_glob("KE"));
+    assert!(tb("").tag_matches_glob(""));
+    assert!(!tb("").tag_matches_glob("?"));
+}
+
+#[test]
+fn test_compact_bytes() {
+    let long_tag = "T".repeat(200);
+    for t in [
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new_with_max_tag_len(&"A".repeat(127), b"x", usize::MAX).unwrap(),
+        TaggedBase64::new_with_max_tag_len(&"A".repeat(128), b"x", usize::MAX).unwrap(),
+        TaggedBase64::new_with_max_tag_len(&long_tag, b"long tag", usize::MAX).unwrap(),
+        TaggedBase64::new_with_max_tag_len(&"B".repeat(20_000), b"", usize::MAX).unwrap(),
+        TaggedBase64::new_timestamped("EVT", b"event", 42).unwrap(),
+    ] {
+        let bytes = t.to_bytes();
+        // Longer tags are written, but not read back, as in the string form.
+        if t.tag().len() <= TB64_MAX_TAG_LEN {
+            assert_eq!(TaggedBase64::from_bytes(&bytes).unwrap(), t);
+        } else {
+            assert!(matches!(
+                TaggedBase64::from_bytes(&bytes),
+                Err(Tb64Error::TagTooLong { len }) if len == t.tag().len()
+            ));
+        }
+        let expected_header = match t.tag().len() {
+            0..=127 => 1,
+            128..=16383 => 2,
+            _ => 3,
+        };
+        assert_eq!(
+            bytes.len(),
+            expected_header + t.tag().len() + t.value().len() + 1
+        );
+    }
+
+    let t = TaggedBase64::new("TX", b"tx").unwrap();
+    assert_eq!(t.to_bytes()[0], 2);
+
+    // Malformed varints.
+    for bytes in [
+        &[][..],
+        &[0x80],
+        &[0x80, 0x00, b'A', 0],
+        &[
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+        ],
+    ] {
+        assert!(matches!(
+            TaggedBase64::from_bytes(bytes),
+            Err(Tb64Error::InvalidLength)
+        ));
+    }
+
+    // The tag length must leave room for the checksum.
+    assert!(matches!(
+        TaggedBase64::from_bytes(&[3, b'T', b'X']),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_bytes(&[2, b'T', b'X']),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_bytes(&[2, b'T', b'~', 0]),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+
+    let mut bytes = t.to_bytes();
+    *bytes.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        TaggedBase64::from_bytes(&bytes),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_classify_failure() {
+    let good = TaggedBase64::new("TX", b"some bytes").unwrap().to_string();
+    assert_eq!(TaggedBase64::classify_failure(&good), None);
+
+    assert_eq!(
+        TaggedBase64::classify_failure(""),
+        Some(FailureClass::Empty)
+    );
+    assert_eq!(
+        TaggedBase64::classify_failure("TX~"),
+        Some(FailureClass::Empty)
+    );
+    assert_eq!(
+        TaggedBase64::classify_failure("TXc29tZQ"),
+        Some(FailureClass::MissingDelimiter)
+    );
+    assert_eq!(
+        TaggedBase64::classify_failure("T X~AAA"),
+        Some(FailureClass::BadTagChar(1, ' '))
+    );
+    assert_eq!(
+        TaggedBase64::classify_failure(".5g~AAA"),
+        Some(FailureClass::BadTagChar(2, 'g'))
+    );
+
+    let (tag, value) = good.split_once('~').unwrap();
+    let spaced = format!("{}~{} {}", tag, &value[..3], &value[3..]);
+    assert_eq!(
+        TaggedBase64::classify_failure(&spaced),
+        Some(FailureClass::BadValueChar(6, b' '))
+    );
+    let truncated = format!("{}~{}", tag, &value[..value.len() - 2]);
+    assert_eq!(
+        TaggedBase64::classify_failure(&truncated),
+        Some(FailureClass::WrongLength)
+    );
+    let typo = format!("{}~A{}", tag, &value[1..]);
+    assert_eq!(
+        TaggedBase64::classify_failure(&typo),
+        Some(FailureClass::ChecksumMismatch)
+    );
+    assert_eq!(
+        TaggedBase64::classify_failure("TAG~Ex"),
+        Some(FailureClass::BadValueChar(5, b'x'))
+    );
+
+    let ext = TaggedBase64::new("EVT", &[0x7f, 0x80]).unwrap();
+    let mut bytes = ext.decoded_region();
+    *bytes.last_mut().unwrap() ^= 0x80;
+    assert_eq!(
+        TaggedBase64::classify_failure(&format!("EVT~~{}", TaggedBase64::encode_raw(&bytes))),
+        Some(FailureClass::UnsupportedFormat)
+    );
+}
+
+#[test]
+fn test_try_from_cow() {
+    let t = TaggedBase64::new("TX", b"transaction identifier goes here").unwrap();
+    let s = t.to_string();
+    assert_eq!(
+        TaggedBase64::try_from(Cow::Borrowed(s.as_str())).unwrap(),
+        t
+    );
+    assert_eq!(TaggedBase64::try_from(Cow::Owned(s.clone())).unwrap(), t);
+
+    let hex: Cow<str> = Cow::Owned(t.to_string_hex_tag());
+    assert_eq!(TaggedBase64::try_from(hex).unwrap(), t);
+
+    assert!(matches!(
+        TaggedBase64::try_from(Cow::Owned("TX~AAA".to_string())),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_grouped() {
+    let t = TaggedBase64::new("TX", b"transaction identifier goes here").unwrap();
+    let s = t.to_string();
+    let grouped = t.to_string_grouped(4, ' ');
+    let (tag, value) = grouped.split_once('~').unwrap();
+    assert_eq!(tag, "TX");
+    let groups = value.split(' ').collect::<Vec<_>>();
+    assert!(groups[..groups.len() - 1].iter().all(|g| g.len() == 4));
+    assert!(groups[groups.len() - 1].len() <= 4);
+    assert_eq!(grouped.replace(' ', ""), s);
+    assert_eq!(TaggedBase64::parse_grouped(&grouped, ' ').unwrap(), t);
+
+    let t = TaggedBase64::new_timestamped("EVT", b"event", 42).unwrap();
+    let grouped = t.to_string_grouped(5, '.');
+    assert!(grouped.starts_with("EVT~~"));
+    assert_eq!(TaggedBase64::parse_grouped(&grouped, '.').unwrap(), t);
+}
+
+#[test]
+#[should_panic]
+fn test_grouped_base64_separator() {
+    TaggedBase64::new("TX", b"tx")
+        .unwrap()
+        .to_string_grouped(4, '-');
+}
+
+#[test]
+fn test_new_nonempty() {
+    assert_eq!(
+        TaggedBase64::new_nonempty("TX", b"tx").unwrap(),
+        TaggedBase64::new("TX", b"tx").unwrap()
+    );
+    assert!(matches!(
+        TaggedBase64::new_nonempty("TX", b""),
+        Err(Tb64Error::EmptyValue)
+    ));
+    assert!(matches!(
+        TaggedBase64::new_nonempty("T~", b"tx"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+}
+
+#[test]
+fn test_tag_checksum() {
+    assert_eq!(TaggedBase64::tag_checksum("TAG"), 0x13);
+    for tag in ["", "TAG", "A", "many-bits"] {
+        let empty = TaggedBase64::new(tag, b"").unwrap();
+        let s = format!(
+            "{}~{}",
+            tag,
+            TaggedBase64::encode_raw(&[TaggedBase64::tag_checksum(tag)])
+        );
+        assert_eq!(empty.to_string(), s);
+        assert_eq!(TaggedBase64::parse(&s).unwrap(), empty);
+    }
+}
+
+#[test]
+fn test_retag_batch() {
+    let mut values = vec![
+        TaggedBase64::new("OLD", b"one").unwrap(),
+        TaggedBase64::new("OLDER", b"").unwrap(),
+        TaggedBase64::new_timestamped("OLD", b"three", 3).unwrap(),
+    ];
+    let payloads = values.iter().map(|v| v.value()).collect::<Vec<_>>();
+
+    TaggedBase64::retag_batch(&mut values, "NEW").unwrap();
+    for (v, payload) in values.iter().zip(&payloads) {
+        let parsed = TaggedBase64::parse(&v.to_string()).unwrap();
+        assert_eq!(parsed.tag(), "NEW");
+        assert_eq!(&parsed.value(), payload);
+        assert_eq!(&parsed, v);
+    }
+    assert_eq!(values[2].timestamp(), Some(3));
+
+    assert!(matches!(
+        TaggedBase64::retag_batch(&mut values, "N~W"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+    assert!(values.iter().all(|v| v.tag() == "NEW"));
+}
+
+#[test]
+fn test_verify_detached() {
+    for t in [
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new_timestamped("EVT", b"event", 42).unwrap(),
+    ] {
+        // Store the checksum apart from the value.
+        let detached = *t.decoded_region().last().unwrap();
+        assert!(t.verify_detached(detached));
+        assert!(!t.verify_detached(detached ^ 1));
+        assert!(!t.verify_detached(detached ^ 0x80));
+    }
+}
+
+#[test]
+fn test_path_segments() {
+    for t in [
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new_timestamped("EVT", b"event", 42).unwrap(),
+    ] {
+        for seg_len in [1, 3, 4, 16, 1000] {
+            let segments = t.to_path_segments(seg_len);
+            assert!(segments.iter().all(|s| !s.is_empty() && !s.contains('/')));
+            assert!(segments[1..].iter().all(|s| s.len() <= seg_len));
+            assert!(segments[0].starts_with(&t.tag()));
+
+            let path = segments.join("/");
+            let parts = path.split('/').collect::<Vec<_>>();
+            assert_eq!(TaggedBase64::from_path_segments(&parts).unwrap(), t);
+        }
+    }
+    assert!(TaggedBase64::from_path_segments(&["TX~", "AA/", "A"]).is_err());
+}
+
+#[test]
+fn test_minimal_unique_tag_prefixes() {
+    let values = ["TXIN", "TXOUT", "TOKEN", "LA", "TX", "KEY", "KEY", ""]
+        .iter()
+        .map(|tag| TaggedBase64::new(tag, b"").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        TaggedBase64::minimal_unique_tag_prefixes(&values),
+        ["TXI", "TXO", "TO", "L", "TX", "KEY", "KEY", ""]
+    );
+
+    let single = [TaggedBase64::new("LONGTAG", b"").unwrap()];
+    assert_eq!(TaggedBase64::minimal_unique_tag_prefixes(&single), ["L"]);
+    assert!(TaggedBase64::minimal_unique_tag_prefixes(&[]).is_empty());
+}
+
+#[cfg(feature = "hmac")]
+#[test]
+fn test_authenticated() {
+    let key = b"secret key";
+    let token = TaggedBase64::new_authenticated("CAP", b"read:ledger", key).unwrap();
+    assert!(token.starts_with("CAP~"));
+    let t = TaggedBase64::verify_authenticated(&token, key).unwrap();
+    assert_eq!(t, TaggedBase64::new("CAP", b"read:ledger").unwrap());
+
+    // Empty values are authenticated too.
+    let empty = TaggedBase64::new_authenticated("", b"", key).unwrap();
+    assert_eq!(
+        TaggedBase64::verify_authenticated(&empty, key)
+            .unwrap()
+            .value(),
+        b""
+    );
+
+    // The wrong key fails.
+    assert!(matches!(
+        TaggedBase64::verify_authenticated(&token, b"other key"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+
+    // Flipping any bit of the decoded token fails.
+    let (tag, value) = token.split_once('~').unwrap();
+    let bytes = TaggedBase64::decode_raw(value).unwrap();
+    for i in 0..bytes.len() {
+        let mut tampered = bytes.clone();
+        tampered[i] ^= 1;
+        let tampered = format!("{}~{}", tag, TaggedBase64::encode_raw(&tampered));
+        assert!(matches!(
+            TaggedBase64::verify_authenticated(&tampered, key),
+            Err(Tb64Error::InvalidChecksum)
+        ));
+    }
+
+    // So does changing the tag.
+    assert!(matches!(
+        TaggedBase64::verify_authenticated(&format!("CAQ~{}", value), key),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::verify_authenticated("CAP~AAAA", key),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[test]
+fn test_non_ascii_tag() {
+    // Cyrillic and Greek letters which look like ASCII ones, and an
+    // invisible space.
+    for confusable in ["\u{0422}X", "KEY\u{0391}", "\u{0410}", "TX\u{200b}"] {
+        assert!(matches!(
+            TaggedBase64::new(confusable, b""),
+            Err(Tb64Error::NonAsciiTag)
+        ));
+        assert!(matches!(
+            TaggedBase64::parse(&format!("{}~wA", confusable)),
+            Err(Tb64Error::NonAsciiTag)
+        ));
+        assert!(matches!(
+            TaggedBase64::normalize_tag(confusable),
+            Err(Tb64Error::NonAsciiTag)
+        ));
+    }
+    assert!(matches!(
+        TaggedBase64::new("T X", b""),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: ' '
+        })
+    ));
+
+    assert_eq!(TaggedBase64::normalize_tag("  TX\n").unwrap(), "TX");
+    assert_eq!(TaggedBase64::normalize_tag("Tx").unwrap(), "TX");
+    assert_eq!(
+        TaggedBase64::normalize_tag("\u{3000}\u{ff34}\u{ff58}_\u{ff11}").unwrap(),
+        "TX_1"
+    );
+    // Only full-width forms are folded, not other lookalikes.
+    assert!(matches!(
+        TaggedBase64::normalize_tag("\u{ff34}\u{0425}"),
+        Err(Tb64Error::NonAsciiTag)
+    ));
+    assert_eq!(TaggedBase64::normalize_tag("").unwrap(), "");
+    assert!(matches!(
+        TaggedBase64::normalize_tag("T X"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: ' '
+        })
+    ));
+}
+
+#[test]
+fn test_compact() {
+    let mut sparse = vec![0u8; 64];
+    sparse[10] = 7;
+    sparse[40..44].copy_from_slice(b"abcd");
+    let tb64 = TaggedBase64::new("SPARSE", &sparse).unwrap();
+    let compact = tb64.to_string_compact();
+    assert!(compact.starts_with("SPARSE~~"));
+    assert!(compact.len() < tb64.to_string().len());
+    assert_eq!(TaggedBase64::parse(&compact).unwrap(), tb64);
+    assert!(!TaggedBase64::is_canonical(&compact).unwrap());
+
+    // The timestamp survives compaction.
+    let stamped = TaggedBase64::new_timestamped("SPARSE", &sparse, 1_700_000_000).unwrap();
+    let parsed = TaggedBase64::parse(&stamped.to_string_compact()).unwrap();
+    assert_eq!(parsed, stamped);
+    assert_eq!(parsed.timestamp(), Some(1_700_000_000));
+
+    // Long runs span several packets.
+    let zeros = TaggedBase64::new("Z", &[0; 1000]).unwrap();
+    assert_eq!(
+        TaggedBase64::parse(&zeros.to_string_compact()).unwrap(),
+        zeros
+    );
+
+    // Values which do not shrink are written in plain form.
+    for dense in [&b""[..], b"x", b"abcdefgh", &(0..=255).collect::<Vec<u8>>()] {
+        let tb64 = TaggedBase64::new("DENSE", dense).unwrap();
+        assert_eq!(tb64.to_string_compact(), tb64.to_string());
+    }
+
+    // A truncated run is rejected even under a valid checksum.
+    let invalid_data = (0..=255u8)
+        .filter(|&cs| {
+            let s = format!("T~~{}", TaggedBase64::encode_raw(&[0x02, 0xff, cs]));
+            matches!(TaggedBase64::parse(&s), Err(Tb64Error::InvalidData))
+        })
+        .count();
+    assert_eq!(invalid_data, 1);
+}
+
+#[test]
+fn test_display_cmp() {
+    // 0x68 encodes as "a..." and 0xfc as "_...", and '_' sorts before 'a'.
+    let low = TaggedBase64::new("TAG", &[0x68]).unwrap();
+    let high = TaggedBase64::new("TAG", &[0xfc]).unwrap();
+    assert_eq!(low.canonical_cmp(&high), Ordering::Less);
+    assert_eq!(low.display_cmp(&high), Ordering::Greater);
+    assert_eq!(low.display_cmp(&low), Ordering::Equal);
+
+    let mut list = [low.clone(), high.clone()];
+    list.sort_by(TaggedBase64::display_cmp);
+    let strings: Vec<String> = list.iter().map(|t| t.to_string()).collect();
+    let mut sorted = strings.clone();
+    sorted.sort();
+    assert_eq!(strings, sorted);
+}
+
+#[derive(AsTagged)]
+#[repr(u8)]
+enum Event {
+    Started,
+    Stopped = 7,
+    r#Reset,
+    // The longest name allowed, TB64_MAX_TAG_LEN bytes.
+    TheLongestVariantNameWhichStillFitsInATagIsExactlySixtyFourBytes,
+}
+
+#[test]
+fn test_as_tagged() {
+    assert_eq!(Event::Started.tag(), "Started");
+    assert_eq!(Event::Started.to_tagged().to_string(), "Started~AG0");
+    assert_eq!(Event::Stopped.to_tagged().value(), vec![7]);
+    assert_eq!(Event::Reset.to_tagged().tag(), "Reset");
+    assert_eq!(Event::Reset.to_tagged().value(), vec![8]);
+    for event in [Event::Started, Event::Stopped, Event::Reset] {
+        let tagged = event.to_tagged();
+        assert_eq!(TaggedBase64::parse(&tagged.to_string()).unwrap(), tagged);
+    }
+    assert_eq!(
+        Event::TheLongestVariantNameWhichStillFitsInATagIsExactlySixtyFourBytes
+            .tag()
+            .len(),
+        TB64_MAX_TAG_LEN
+    );
+}
+
+#[test]
+fn test_multi_checked() {
+    let multi = TaggedBase64MultiChecked::new("KEYS", &[b"first", b"", &[0xff; 33]]).unwrap();
+    let s = multi.to_string();
+    assert_eq!(s.matches('~').count(), 3);
+    let parsed: TaggedBase64MultiChecked = s.parse().unwrap();
+    assert_eq!(parsed, multi);
+    assert_eq!(parsed.tag(), "KEYS");
+    assert_eq!(
+        parsed.segments(),
+        vec![b"first".to_vec(), vec![], vec![0xff; 33]]
+    );
+
+    // Each segment is a valid TaggedBase64 value on its own.
+    let first = s.split('~').nth(1).unwrap();
+    assert_eq!(
+        TaggedBase64::parse(&format!("KEYS~{}", first)).unwrap(),
+        TaggedBase64::new("KEYS", b"first").unwrap()
+    );
+
+    // Corrupting a segment reports exactly that segment.
+    for index in 0..3 {
+        let mut parts: Vec<String> = s.split('~').map(String::from).collect();
+        let part = &mut parts[index + 1];
+        let c = if part.starts_with('A') { "B" } else { "A" };
+        part.replace_range(..1, c);
+        assert!(matches!(
+            TaggedBase64MultiChecked::parse(&parts.join("~")),
+            Err(Tb64Error::InvalidSegment { index: i }) if i == index
+        ));
+    }
+    assert!(matches!(
+        TaggedBase64MultiChecked::parse("KEYS~~"),
+        Err(Tb64Error::InvalidSegment { index: 0 })
+    ));
+    assert!(matches!(
+        TaggedBase64MultiChecked::parse("KEYS"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64MultiChecked::new("KEYS", &[]),
+        Err(Tb64Error::EmptyValue)
+    ));
+}
+
+#[test]
+fn test_decoded_len() {
+    for len in [0, 1, 2, 3, 4, 5, 31, 32, 33, 100] {
+        let tb = TaggedBase64::new("LEN", &vec![0xa5; len]).unwrap();
+        let s = tb.to_string();
+        assert_eq!(TaggedBase64::decoded_len(&s).unwrap(), tb.value().len());
+    }
+    let stamped = TaggedBase64::new_timestamped("LEN", b"abc", 1).unwrap();
+    assert_eq!(
+        TaggedBase64::decoded_len(&stamped.to_string()).unwrap(),
+        stamped.value().len()
+    );
+
+    assert!(matches!(
+        TaggedBase64::decoded_len("LEN~AAAAA"),
+        Err(Tb64Error::InvalidLength)
+    ));
+    assert!(matches!(
+        TaggedBase64::decoded_len("LEN~"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::decoded_len("LEN"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+}
+
+#[test]
+fn test_from_str() {
+    let tb = TaggedBase64::new("TX", &[1, 2, 3]).unwrap();
+    let s = tb.to_string();
+    assert_eq!(s.parse::<TaggedBase64>().unwrap(), tb);
+
+    assert!(matches!(
+        "TXAQID".parse::<TaggedBase64>(),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        s.replacen("TX", "T+", 1).parse::<TaggedBase64>(),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '+'
+        })
+    ));
+    assert!(matches!(
+        s.replacen("TX", "TY", 1).parse::<TaggedBase64>(),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_checksum_position() {
+    // The checksum follows the value, both when encoding and decoding.
+    for len in [0, 1, 2, 3, 4, 7, 16, 255] {
+        let value: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let tb = TaggedBase64::new("TX", &value).unwrap();
+        let s = tb.to_string();
+        let region = TaggedBase64::decode_raw(&s[3..]).unwrap();
+        assert_eq!(&region[..len], &value[..]);
+        assert_eq!(region, tb.decoded_region());
+        assert_eq!(region.len(), len + 1);
+        let parsed = TaggedBase64::parse(&s).unwrap();
+        assert_eq!(parsed.value(), value);
+        assert_eq!(parsed, tb);
+    }
+}
+
+#[test]
+fn test_short_payloads() {
+    for s in ["~", "TX~", "TX~~", "~~"] {
+        assert!(matches!(
+            TaggedBase64::parse(s),
+            Err(Tb64Error::MissingChecksum)
+        ));
+    }
+    // A single base64 character cannot encode even the checksum.
+    assert!(matches!(
+        TaggedBase64::parse("TX~A"),
+        Err(Tb64Error::InvalidLength)
+    ));
+
+    // A payload of just the checksum is an empty value.
+    for tag in ["", "TX"] {
+        let s = format!(
+            "{}~{}",
+            tag,
+            TaggedBase64::encode_raw(&[TaggedBase64::tag_checksum(tag)])
+        );
+        let tb = TaggedBase64::parse(&s).unwrap();
+        assert_eq!(tb.tag(), tag);
+        assert!(tb.value().is_empty());
+    }
+    // The checksum is checked before the layout of an extended value, and 0
+    // is not the masked checksum of an empty value tagged TX.
+    assert!(matches!(
+        TaggedBase64::parse(&format!("TX~~{}", TaggedBase64::encode_raw(&[0]))),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_error_display() {
+    fn parse(s: &str) -> Result<TaggedBase64, Box<dyn std::error::Error>> {
+        Ok(s.parse::<TaggedBase64>()?)
+    }
+    let e = parse("AAA~AAA").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "The checksum was truncated or did not match."
+    );
+    let e = parse("AAA").unwrap_err();
+    assert_eq!(e.to_string(), "Missing delimiter.");
+    assert_eq!(
+        Tb64Error::InvalidSegment { index: 2 }.to_string(),
+        "invalid segment 2"
+    );
+}
+
+#[test]
+fn test_try_from_str() {
+    fn convert<S: TryInto<TaggedBase64, Error = Tb64Error>>(s: S) -> TaggedBase64 {
+        s.try_into().unwrap()
+    }
+    let s = TaggedBase64::new("TX", b"owned or borrowed")
+        .unwrap()
+        .to_string();
+    let parsed: TaggedBase64 = s.parse().unwrap();
+    assert_eq!(convert(s.as_str()), parsed);
+    assert_eq!(convert(s.clone()), parsed);
+    assert_eq!(TaggedBase64::try_from(s.as_str()).unwrap(), parsed);
+    assert_eq!(TaggedBase64::try_from(s).unwrap(), parsed);
+
+    assert!(matches!(
+        TaggedBase64::try_from("TX"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::try_from(String::from("TX~AAA")),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    // Both surface the same native error, with no WASM types involved.
+    let bad = parsed.to_string().replacen("TX", "TY", 1);
+    assert_eq!(
+        TaggedBase64::try_from(bad.as_str())
+            .unwrap_err()
+            .to_string(),
+        bad.parse::<TaggedBase64>().unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_borrowing_accessors() {
+    let tb = TaggedBase64::new("TX", &[7; 1000]).unwrap();
+    assert_eq!(tb.tag_ref(), tb.tag());
+    assert_eq!(tb.value_ref(), &tb.value()[..]);
+    // Each call borrows the same storage rather than copying it.
+    assert_eq!(tb.tag_ref().as_ptr(), tb.tag_ref().as_ptr());
+    assert_eq!(tb.value_ref().as_ptr(), tb.value_ref().as_ptr());
+    assert_eq!(tb.value_ref().as_ptr(), tb.as_ref().as_ptr());
+}
+
+#[test]
+fn test_checksum_getter() {
+    assert_eq!(
+        TaggedBase64::new("TX", b"").unwrap().checksum(),
+        TaggedBase64::tag_checksum("TX")
+    );
+    let mut tb = TaggedBase64::new("TX", b"checked").unwrap();
+    assert_eq!(Some(&tb.checksum()), tb.decoded_region().last());
+    let before = tb.checksum();
+    tb.set_value(b"changed");
+    assert_ne!(tb.checksum(), before);
+    assert_eq!(Some(&tb.checksum()), tb.decoded_region().last());
+    assert_eq!(
+        TaggedBase64::parse(&tb.to_string()).unwrap().checksum(),
+        tb.checksum()
+    );
+}
+
+#[test]
+fn test_hash_and_ord() {
+    use std::collections::{BTreeSet, HashMap};
+
+    let values = [
+        TaggedBase64::new("B", b"1").unwrap(),
+        TaggedBase64::new("A", b"2").unwrap(),
+        TaggedBase64::new("A", b"1").unwrap(),
+        TaggedBase64::new("A", b"1").unwrap(),
+    ];
+    let set: BTreeSet<_> = values.iter().cloned().collect();
+    let sorted: Vec<String> = set.iter().map(|t| t.to_string()).collect();
+    assert_eq!(sorted.len(), 3);
+    let mut expected = values.to_vec();
+    expected.sort_by(TaggedBase64::canonical_cmp);
+    expected.dedup();
+    assert_eq!(set.into_iter().collect::<Vec<_>>(), expected);
+
+    let mut map = HashMap::new();
+    for (i, tb) in values.iter().enumerate() {
+        map.insert(tb.clone(), i);
+    }
+    assert_eq!(map.len(), 3);
+    assert_eq!(
+        map[&TaggedBase64::parse(&values[2].to_string()).unwrap()],
+        3
+    );
+}
+
+#[test]
+fn test_new_with_checksum() {
+    let value = b"a value worth protecting".to_vec();
+    for (checksum, overhead) in [
+        (ChecksumKind::Crc8, 0),
+        (ChecksumKind::Crc16, 3),
+        (ChecksumKind::Crc32c, 5),
+    ] {
+        let tb = TaggedBase64::new_with_checksum("TX", &value, checksum).unwrap();
+        assert_eq!(tb.value().len(), value.len() + overhead);
+        assert_eq!(tb.payload(), &value[..]);
+        assert_eq!(tb.timestamp(), None);
+        let s = tb.to_string();
+        let parsed = TaggedBase64::parse(&s).unwrap();
+        assert_eq!(parsed, tb);
+        assert_eq!(parsed.payload(), &value[..]);
+        assert_eq!(TaggedBase64::parse(&tb.to_string_compact()).unwrap(), tb);
+        assert_eq!(TaggedBase64::from_bytes(&tb.to_bytes()).unwrap(), tb);
+
+        // Every single-byte corruption of the encoded bytes is detected.
+        let region = tb.decoded_region();
+        let prefix = &s[..s.len() - tb.encoded_value().len()];
+        for i in 0..region.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut corrupt = region.clone();
+                corrupt[i] ^= flip;
+                let s = format!("{}{}", prefix, TaggedBase64::encode_raw(&corrupt));
+                assert!(TaggedBase64::parse(&s).is_err());
+            }
+        }
+    }
+    assert_eq!(
+        TaggedBase64::new_with_checksum("TX", &value, ChecksumKind::Crc8).unwrap(),
+        TaggedBase64::new("TX", &value).unwrap()
+    );
+    assert!(TaggedBase64::new_with_checksum("T+", &value, ChecksumKind::Crc32c).is_err());
+}
+
+#[test]
+fn test_decode_raw_native() {
+    assert_eq!(TaggedBase64::decode_raw("AQID").unwrap(), vec![1, 2, 3]);
+    match TaggedBase64::decode_raw("AQ+D") {
+        Err(Tb64Error::Base64 { error }) => {
+            assert_eq!(error, base64::DecodeError::InvalidByte(2, b'+'))
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_error_trait() {
+    fn parse(s: &str) -> Result<TaggedBase64, Box<dyn std::error::Error>> {
+        Ok(TaggedBase64::parse(s)?)
+    }
+    let err = parse("TX~AQ+D").unwrap_err();
+    let err = err.downcast_ref::<Tb64Error>().unwrap();
+    assert!(matches!(
+        err,
+        Tb64Error::Base64 {
+            error: base64::DecodeError::InvalidByte(2, b'+')
+        }
+    ));
+    assert!(err.to_string().starts_with("invalid base 64: "));
+    assert_eq!(
+        parse("TX").unwrap_err().to_string(),
+        Tb64Error::MissingDelimiter.to_string()
+    );
+}
+
+#[test]
+fn test_invalid_tag_char() {
+    let e = TaggedBase64::new("TX!ID", b"").unwrap_err();
+    assert!(matches!(
+        e,
+        Tb64Error::InvalidTagChar {
+            offset: 2,
+            character: '!'
+        }
+    ));
+    assert_eq!(e.to_string(), "invalid character '!' at offset 2 in tag");
+    assert!(matches!(
+        TaggedBase64::parse("TX!ID~AAA"),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 2,
+            character: '!'
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::validate_tag("TXID."),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 4,
+            character: '.'
+        })
+    ));
+}
+
+#[test]
+fn test_consuming_accessors() {
+    let tb = TaggedBase64::new("TX", &[9; 1000]).unwrap();
+    let ptr = tb.value_ref().as_ptr();
+    assert_eq!(tb.clone().into_tag(), "TX");
+    let (tag, value) = tb.clone().into_parts();
+    assert_eq!((tag.as_str(), &value[..]), ("TX", &[9; 1000][..]));
+    // The value is moved out rather than copied.
+    let value = tb.into_value();
+    assert_eq!(value.as_ptr(), ptr);
+    assert_eq!(value, vec![9; 1000]);
+
+    let tb = TaggedBase64::new("TX", &[9; 1000]).unwrap();
+    let ptr = tb.value_ref().as_ptr();
+    let value = Vec::<u8>::from(tb);
+    assert_eq!(value.as_ptr(), ptr);
+    assert_eq!(value, vec![9; 1000]);
+}
+
+#[test]
+fn test_owned_into_string() {
+    for tb in [
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("TX", b"owned").unwrap(),
+        TaggedBase64::new_timestamped("TX", b"owned", 42).unwrap(),
+    ] {
+        let borrowed = String::from(&tb);
+        let owned: String = tb.into();
+        assert_eq!(owned, borrowed);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroize() {
+    use zeroize::{Zeroize, ZeroizeOnDrop};
+
+    fn assert_zeroize_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+
+    let mut tb = TaggedBase64::new("KEY", b"secret key bytes").unwrap();
+    assert_zeroize_on_drop(&tb);
+    tb.zeroize();
+    assert!(tb.tag_ref().is_empty());
+    assert!(tb.value_ref().is_empty());
+    assert_eq!(tb.checksum(), 0);
+
+    // Values can still be moved out of an instance which wipes itself.
+    let tb = TaggedBase64::new("KEY", b"secret key bytes").unwrap();
+    assert_eq!(String::from(tb.clone()), tb.to_string());
+    assert_eq!(tb.into_value(), b"secret key bytes");
+}
+
+#[test]
+fn test_parse_lenient() {
+    // The encoding of these bytes uses both characters which differ
+    // between the alphabets, and needs padding.
+    let tb = TaggedBase64::new("TX", &[0xfb, 0xff, 0xbf, 0x10]).unwrap();
+    let canonical = tb.to_string();
+    assert!(canonical.contains('-') && canonical.contains('_'));
+    let standard = canonical.replace('-', "+").replace('_', "/");
+    let padding = "=".repeat((4 - tb.encoded_value().len() % 4) % 4);
+    assert!(!padding.is_empty());
+    let padded = format!("{}{}", canonical, padding);
+    let both = format!("{}{}", standard, padding);
+    for s in [&canonical, &standard, &padded, &both] {
+        let parsed = TaggedBase64::parse_lenient(s).unwrap();
+        assert_eq!(parsed, tb);
+        assert_eq!(parsed.to_string(), canonical);
+    }
+    assert!(TaggedBase64::parse(&standard).is_err());
+    assert!(TaggedBase64::parse(&padded).is_err());
+
+    // Whitespace is ignored, as in a value wrapped by an email client.
+    let long = TaggedBase64::new("TX", &[0xfb; 100]).unwrap().to_string();
+    let wrapped = long
+        .as_bytes()
+        .chunks(40)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    for s in [
+        format!("  {}\n", canonical),
+        format!("\t{} ", both),
+        format!("T X ~ {}", &canonical[3..]),
+        wrapped.clone(),
+    ] {
+        assert!(TaggedBase64::parse(&s).is_err());
+        assert!(TaggedBase64::parse_lenient(&s).is_ok(), "{:?}", s);
+    }
+    assert_eq!(
+        TaggedBase64::parse_lenient(&wrapped).unwrap().to_string(),
+        long
+    );
+
+    // The tag is not rewritten.
+    assert!(TaggedBase64::parse_lenient(&canonical.replacen("TX", "T/", 1)).is_err());
+    assert!(matches!(
+        TaggedBase64::parse_lenient("TX"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+}
+
+#[test]
+fn test_max_tag_len() {
+    let longest = "T".repeat(TB64_MAX_TAG_LEN);
+    let too_long = "T".repeat(TB64_MAX_TAG_LEN + 1);
+    let tb = TaggedBase64::new(&longest, b"v").unwrap();
+    assert_eq!(TaggedBase64::parse(&tb.to_string()).unwrap(), tb);
+    assert!(matches!(
+        TaggedBase64::new(&too_long, b"v"),
+        Err(Tb64Error::TagTooLong { len }) if len == TB64_MAX_TAG_LEN + 1
+    ));
+
+    // A longer limit can be chosen explicitly.
+    let tb = TaggedBase64::new_with_max_tag_len(&too_long, b"v", TB64_MAX_TAG_LEN + 1).unwrap();
+    let s = tb.to_string();
+    assert!(matches!(
+        TaggedBase64::parse(&s),
+        Err(Tb64Error::TagTooLong { .. })
+    ));
+    assert_eq!(
+        TaggedBase64::parse_with_max_tag_len(&s, TB64_MAX_TAG_LEN + 1).unwrap(),
+        tb
+    );
+
+    // So can a shorter one.
+    assert!(TaggedBase64::new_with_max_tag_len("TX", b"v", 2).is_ok());
+    assert!(matches!(
+        TaggedBase64::new_with_max_tag_len("TXN", b"v", 2),
+        Err(Tb64Error::TagTooLong { len: 3 })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_with_max_tag_len("TXN~AAA", 2),
+        Err(Tb64Error::TagTooLong { len: 3 })
+    ));
+
+    // Every other way of building a value keeps to the default limit, so
+    // its string form always parses.
+    let long_tagged =
+        TaggedBase64::new_with_max_tag_len(&too_long, b"v", TB64_MAX_TAG_LEN + 1).unwrap();
+    let mut tb = TaggedBase64::new("TX", b"v").unwrap();
+    tb.set_tag(&longest);
+    assert_eq!(TaggedBase64::parse(&tb.to_string()).unwrap(), tb);
+    let set_too_long = std::panic::catch_unwind(|| {
+        TaggedBase64::new("TX", b"v").unwrap().set_tag(&too_long);
+    });
+    assert!(set_too_long.is_err());
+
+    assert_eq!(TaggedBase64::from_bytes(&tb.to_bytes()).unwrap(), tb);
+    assert!(matches!(
+        TaggedBase64::from_bytes(&long_tagged.to_bytes()),
+        Err(Tb64Error::TagTooLong { len }) if len == TB64_MAX_TAG_LEN + 1
+    ));
+
+    let bytes = bincode::serialize(&tb).unwrap();
+    assert_eq!(bincode::deserialize::<TaggedBase64>(&bytes).unwrap(), tb);
+    let bytes = bincode::serialize(&long_tagged).unwrap();
+    let err = bincode::deserialize::<TaggedBase64>(&bytes).unwrap_err();
+    assert!(err.to_string().contains(
+        &Tb64Error::TagTooLong {
+            len: TB64_MAX_TAG_LEN + 1
+        }
+        .to_string()
+    ));
+}
+
+#[quickcheck]
+fn display_len_quickcheck(tag_len: u8, value: Vec<u8>, secs: Option<u64>) -> bool {
+    let tag = "T".repeat(tag_len as usize % (TB64_MAX_TAG_LEN + 1));
+    let tb = match secs {
+        Some(secs) => TaggedBase64::new_timestamped(&tag, &value, secs).unwrap(),
+        None => TaggedBase64::new(&tag, &value).unwrap(),
+    };
+    tb.display_len() == tb.to_string().len()
+}
+
+#[test]
+fn test_display_len() {
+    for (tag, len) in [("", 0), ("", 1), ("", 2), ("TX", 0), ("TX", 3), ("TX", 100)] {
+        let tb = TaggedBase64::new(tag, &vec![0; len]).unwrap();
+        assert_eq!(tb.display_len(), tb.to_string().len());
+    }
+}
+
+#[test]
+fn test_encode_to() {
+    let mut buf = String::new();
+    let mut bytes = Vec::new();
+    let mut expected = String::new();
+    for len in [0, 1, 2, 3, 767, 768, 769, 2000] {
+        let value: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        for tb in [
+            TaggedBase64::new("TX", &value).unwrap(),
+            TaggedBase64::new_timestamped("TX", &value, 5).unwrap(),
+        ] {
+            let s = format!("{}~{}", tb.tag(), tb.encoded_value());
+            let s = if tb.timestamp().is_some() {
+                s.replacen('~', "~~", 1)
+            } else {
+                s
+            };
+            tb.encode_to(&mut buf).unwrap();
+            tb.write_to(&mut bytes).unwrap();
+            expected.push_str(&s);
+            assert_eq!(tb.to_string(), s);
+            assert_eq!(String::from(&tb), s);
+        }
+    }
+    assert_eq!(buf, expected);
+    assert_eq!(bytes, expected.as_bytes());
+
+    // Errors from the sink are passed on.
+    let tb = TaggedBase64::new("TX", &[0; 100]).unwrap();
+    let mut short = [0u8; 10];
+    let err = tb.write_to(&mut &mut short[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn test_has_tag() {
+    let tb = TaggedBase64::new("TX", b"").unwrap();
+    assert!(tb.has_tag("TX"));
+    assert!(!tb.has_tag("tx"));
+    assert!(!tb.has_tag("TXN"));
+    assert!(!tb.has_tag(""));
+    assert!(tb.has_tag_ignore_ascii_case("tx"));
+    assert!(tb.has_tag_ignore_ascii_case("Tx"));
+    assert!(!tb.has_tag_ignore_ascii_case("tz"));
+
+    let untagged = TaggedBase64::new("", b"").unwrap();
+    assert!(untagged.has_tag(""));
+    assert!(untagged.has_tag_ignore_ascii_case(""));
+    assert!(!untagged.has_tag("TX"));
+}
+
+#[test]
+fn test_checksummed() {
+    for value in [&b""[..], b"x", b"compact token", &[0xff; 40]] {
+        let s = encode_checksummed(value);
+        assert!(s.chars().all(TaggedBase64::is_safe_base64_ascii));
+        assert_eq!(decode_checksummed(&s).unwrap(), value);
+        // The same scheme as a value with an empty tag.
+        assert_eq!(
+            format!("~{}", s),
+            TaggedBase64::new("", value).unwrap().to_string()
+        );
+    }
+
+    let s = encode_checksummed(b"compact token");
+    let mut corrupt = s.clone().into_bytes();
+    corrupt[3] = if corrupt[3] == b'A' { b'B' } else { b'A' };
+    assert!(matches!(
+        decode_checksummed(str::from_utf8(&corrupt).unwrap()),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        decode_checksummed(""),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        decode_checksummed(&format!("{}=", s)),
+        Err(Tb64Error::Base64 { .. })
+    ));
+}
+
+#[test]
+fn test_require_tag() {
+    // Empty tags are accepted by default.
+    let untagged = TaggedBase64::new("", b"value").unwrap();
+    let parsed = TaggedBase64::parse(&untagged.to_string()).unwrap();
+    assert_eq!(parsed, untagged);
+    assert!(matches!(parsed.require_tag(), Err(Tb64Error::EmptyTag)));
+
+    let tagged = TaggedBase64::new("TX", b"value").unwrap();
+    let parsed = TaggedBase64::parse(&tagged.to_string())
+        .unwrap()
+        .require_tag()
+        .unwrap()
+        .clone();
+    assert_eq!(parsed, tagged);
+}
+
+#[test]
+fn test_engine_matches_url_safe_no_pad() {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    for len in 0..64 {
+        let value: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+        let encoded = TaggedBase64::encode_raw(&value);
+        assert_eq!(encoded, URL_SAFE_NO_PAD.encode(&value));
+        assert_eq!(BASE64.encode(&value), encoded);
+        assert_eq!(TaggedBase64::decode_raw(&encoded).unwrap(), value);
+        assert_eq!(URL_SAFE_NO_PAD.decode(&encoded).unwrap(), value);
+    }
+}
+
+#[test]
+fn test_as_ref() {
+    fn byte_sum<T: AsRef<[u8]>>(t: T) -> u32 {
+        t.as_ref().iter().map(|&b| b as u32).sum()
+    }
+    let tb = TaggedBase64::new("TAG", &[1, 2, 3]).unwrap();
+    assert_eq!(byte_sum(&tb), 6);
+    assert_eq!(byte_sum(tb.clone()), 6);
+    let value: &[u8] = tb.as_ref();
+    assert_eq!(value, tb.value_ref());
+}
+
+#[test]
+fn test_from_tag_and_b64() {
+    let tb = TaggedBase64::new("TX", b"split").unwrap();
+    let b64 = tb.encoded_value();
+    assert_eq!(TaggedBase64::from_tag_and_b64("TX", &b64).unwrap(), tb);
+    assert!(matches!(
+        TaggedBase64::from_tag_and_b64("T!", &b64),
+        Err(Tb64Error::InvalidTagChar { .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_tag_and_b64("TX", "c3Bsa+"),
+        Err(Tb64Error::Base64 { .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_tag_and_b64("TY", &b64),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_tag_and_b64("TX", ""),
+        Err(Tb64Error::MissingChecksum)
+    ));
+
+    // Extended values are recognized by their masked checksum.
+    let stamped = TaggedBase64::new_timestamped("TX", b"split", 42).unwrap();
+    let b64 = stamped.encoded_value();
+    assert_eq!(TaggedBase64::from_tag_and_b64("TX", &b64).unwrap(), stamped);
+    assert!(matches!(
+        TaggedBase64::from_tag_and_b64("TY", &b64),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[quickcheck]
+fn url_safe_quickcheck(tag: Vec<u8>, value: Vec<u8>, secs: Option<u64>) -> bool {
+    // Build a valid tag from arbitrary bytes.
+    let tag: String = TaggedBase64::encode_raw(&tag)
+        .chars()
+        .take(TB64_MAX_TAG_LEN)
+        .collect();
+    let tb = match secs {
+        Some(secs) => TaggedBase64::new_timestamped(&tag, &value, secs).unwrap(),
+        None => TaggedBase64::new(&tag, &value).unwrap(),
+    };
+    let s = tb.to_string();
+    let url = url::Url::parse(&format!("https://example.com/{}?id={}#{}", s, s, s)).unwrap();
+    TaggedBase64::is_url_safe_str(&s)
+        && url.path() == format!("/{}", s)
+        && url.query() == Some(&format!("id={}", s)[..])
+        && url.fragment() == Some(&s[..])
+}
+
+#[test]
+fn test_is_url_safe_str() {
+    assert!(TaggedBase64::is_url_safe_str("TX~AZaz09-_"));
+    assert!(TaggedBase64::is_url_safe_str(""));
+    for unsafe_str in ["TX~a+b", "TX~a/b", "TX~a=", "T X~a", "TX~a%20"] {
+        assert!(!TaggedBase64::is_url_safe_str(unsafe_str));
+    }
+}
+
+#[test]
+fn test_builder() {
+    let mut builder = TaggedBase64Builder::new().tag("TX");
+    for chunk in [&b"type"[..], b"", b"payload"] {
+        builder = builder.extend_value(chunk);
+    }
+    let tb = builder.push_byte(0).build().unwrap();
+    assert_eq!(tb, TaggedBase64::new("TX", b"typepayload\0").unwrap());
+
+    assert_eq!(
+        TaggedBase64Builder::new().build().unwrap(),
+        TaggedBase64::new("", b"").unwrap()
+    );
+    assert!(matches!(
+        TaggedBase64Builder::new().tag("T X").push_byte(1).build(),
+        Err(Tb64Error::InvalidTagChar { offset: 1, .. })
+    ));
+
+    // Components can be serialized straight into the value.
+    let mut builder = TaggedBase64Builder::new().tag("TX").push_byte(2);
+    7u32.serialize_compressed(&mut builder).unwrap();
+    std::io::Write::write_all(&mut builder, b"tail").unwrap();
+    let tb = builder.build().unwrap();
+    assert_eq!(tb, TaggedBase64::new("TX", b"\x02\x07\0\0\0tail").unwrap());
+}
+
+#[test]
+fn test_format_version() {
+    // Plain values carry no version and still parse.
+    let plain = TaggedBase64::new("TX", b"v1").unwrap();
+    assert_eq!(TaggedBase64::parse(&plain.to_string()).unwrap(), plain);
+    let stamped = TaggedBase64::new_timestamped("TX", b"v1", 9).unwrap();
+    assert_eq!(TaggedBase64::parse(&stamped.to_string()).unwrap(), stamped);
+
+    // A value from a later version of the extended format is recognized as
+    // such, whatever its flags. Find the checksum which makes each header
+    // otherwise valid.
+    for (header, version) in [(0x11, 1), (0x21, 2), (0xf3, 15)] {
+        let mut bytes = vec![header];
+        bytes.extend_from_slice(&[0; 8]);
+        let errors: Vec<_> = (0..=255u8)
+            .filter_map(|cs| {
+                let mut bytes = bytes.clone();
+                bytes.push(cs);
+                match TaggedBase64::parse(&format!("TX~~{}", TaggedBase64::encode_raw(&bytes))) {
+                    Err(Tb64Error::InvalidChecksum) => None,
+                    other => Some(other),
+                }
+            })
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            Err(Tb64Error::UnsupportedVersion { version: v }) if v == version
+        ));
+    }
+}
+
+#[test]
+fn test_eq_ignore_tag_case() {
+    let upper = TaggedBase64::new("TX", b"same").unwrap();
+    let mixed = TaggedBase64::new("Tx", b"same").unwrap();
+    assert_ne!(upper, mixed);
+    assert!(upper.eq_ignore_tag_case(&mixed));
+    assert_eq!(upper.normalized_tag(), "TX");
+    assert_eq!(mixed.normalized_tag(), upper.normalized_tag());
+
+    assert!(!upper.eq_ignore_tag_case(&TaggedBase64::new("TX", b"other").unwrap()));
+    assert!(!upper.eq_ignore_tag_case(&TaggedBase64::new("TY", b"same").unwrap()));
+}
+
+#[test]
+fn test_decode_streaming() {
+    use rand_chacha::rand_core::{RngCore, SeedableRng};
+
+    let mut value = vec![0; 100_003];
+    rand_chacha::ChaChaRng::from_seed([7; 32]).fill_bytes(&mut value);
+    for value in [&value[..], &value[..1], &[]] {
+        let tb = TaggedBase64::new("BIG", value).unwrap();
+        let b64 = tb.encoded_value();
+        let decoded: Result<Vec<u8>, _> =
+            TaggedBase64::decode_streaming("BIG", b64.as_bytes()).collect();
+        assert_eq!(decoded.unwrap(), value);
+
+        let decoded: Result<Vec<u8>, _> =
+            TaggedBase64::decode_streaming("BIGGER", b64.as_bytes()).collect();
+        assert!(matches!(decoded, Err(Tb64Error::InvalidChecksum)));
+    }
+
+    assert!(matches!(
+        TaggedBase64::decode_streaming("BIG", &b""[..]).collect::<Result<Vec<u8>, _>>(),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::decode_streaming("BIG", &b"AA+A"[..]).collect::<Result<Vec<u8>, _>>(),
+        Err(Tb64Error::Base64 { .. })
+    ));
+}
+
+#[test]
+fn test_borrowed_parse() {
+    let input = format!(
+        "{} {}",
+        TaggedBase64::new("FIRST", b"one").unwrap(),
+        TaggedBase64::new("SECOND", b"two").unwrap()
+    );
+    let range = input.as_bytes().as_ptr_range();
+    for (s, (tag, value)) in input
+        .split(' ')
+        .zip([("FIRST", b"one"), ("SECOND", b"two")])
+    {
+        let parsed = TaggedBase64Ref::parse(s).unwrap();
+        assert_eq!(parsed.tag(), tag);
+        // The tag points into the input rather than a new allocation.
+        assert!(range.contains(&parsed.tag().as_ptr()));
+        assert_eq!(parsed.value(), value);
+        assert_eq!(parsed.into_owned(), TaggedBase64::parse(s).unwrap());
+    }
+
+    let hex = TaggedBase64::new("HEX", b"x").unwrap().to_string_hex_tag();
+    let parsed = TaggedBase64Ref::parse(&hex).unwrap();
+    assert_eq!(parsed.tag(), "HEX");
+    assert!(matches!(
+        TaggedBase64Ref::parse("TX~AAA"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_debug() {
+    let tb = TaggedBase64::new("TX", &[0xab; 1000]).unwrap();
+    let debug = format!("{:?}", tb);
+    assert!(debug.contains("tag: \"TX\""), "{}", debug);
+    assert!(debug.contains("len: 1000"), "{}", debug);
+    assert!(
+        debug.contains(&format!("checksum: {:#04x}", tb.checksum())),
+        "{}",
+        debug
+    );
+    assert!(debug.contains("preview: q6urq6urq6urq6ur..."), "{}", debug);
+    assert!(debug.len() < 100, "{}", debug);
+
+    let short = format!("{:?}", TaggedBase64::new("TX", b"ab").unwrap());
+    assert!(short.contains("preview: YWI }"), "{}", short);
+
+    // The alternate form shows every byte.
+    let full = format!("{:#?}", tb);
+    assert_eq!(full.matches("171,").count(), 1000);
+}
+
+#[test]
+fn test_invalid_length() {
+    // Two bytes and the checksum encode to one whole group.
+    let s = TaggedBase64::new("TX", b"ab").unwrap().to_string();
+    for extra in ["A", "AAAAA", "-"] {
+        let e = TaggedBase64::parse(&format!("{}{}", s, extra)).unwrap_err();
+        assert!(matches!(e, Tb64Error::InvalidLength), "{:?}", e);
+    }
+    // A bad character still takes precedence in the diagnosis.
+    assert_eq!(
+        TaggedBase64::classify_failure("TX~AAAA+"),
+        Some(FailureClass::BadValueChar(7, b'+'))
+    );
+}
+
+#[test]
+fn test_calc_checksum_chunks() {
+    let (a, b, c) = (&b"scatter"[..], &b""[..], &[0x55; 300][..]);
+    let joined = [a, b, c].concat();
+    let expected = TaggedBase64::new("TX", &joined).unwrap().checksum();
+    assert_eq!(
+        TaggedBase64::calc_checksum_chunks("TX", [a, b, c]),
+        expected
+    );
+    assert_eq!(
+        TaggedBase64::calc_checksum_chunks("TX", joined.chunks(7)),
+        expected
+    );
+    assert_eq!(
+        TaggedBase64::calc_checksum_chunks("TX", []),
+        TaggedBase64::tag_checksum("TX")
+    );
+}
+
+#[quickcheck]
+fn single_delimiter_quickcheck(tag: Vec<u8>, value: Vec<u8>) -> bool {
+    let tag: String = TaggedBase64::encode_raw(&tag)
+        .chars()
+        .take(TB64_MAX_TAG_LEN)
+        .collect();
+    let tb = TaggedBase64::new(&tag, &value).unwrap();
+    let s = tb.to_string();
+    let parsed = TaggedBase64::parse(&s).unwrap();
+    !tb.encoded_value().contains(TB64_DELIM)
+        && s.matches(TB64_DELIM).count() == 1
+        && s.find(TB64_DELIM) == Some(tag.len())
+        && parsed.tag() == tag
+        && parsed.value() == value
+}
+
+#[test]
+fn test_delimiter_not_in_alphabet() {
+    assert!(!TaggedBase64::is_safe_base64_ascii(TB64_DELIM));
+    // Every possible base64 character, in every position of a group.
+    let all: Vec<u8> = (0..=255).collect();
+    for shift in 0..3 {
+        assert!(!TaggedBase64::encode_raw(&all[shift..]).contains(TB64_DELIM));
+    }
+}
+
+#[test]
+fn test_try_set_value() {
+    let mut tb = TaggedBase64::new("TAG", &[1]).unwrap();
+    let value = [7u8; 10];
+    let expected = TaggedBase64::new("TAG", &value).unwrap();
+    let max = expected.display_len();
+    assert_eq!(max, expected.to_string().len());
+
+    // Just over the limit: refused, and nothing changes.
+    assert!(matches!(
+        tb.try_set_value(&value, max - 1),
+        Err(Tb64Error::ValueTooLong { len, max: m }) if len == max && m == max - 1
+    ));
+    assert_eq!(tb.value(), vec![1]);
+    assert!(TaggedBase64::parse(&tb.to_string()).is_ok());
+
+    // Exactly at the limit: accepted.
+    tb.try_set_value(&value, max).unwrap();
+    assert_eq!(tb, expected);
+    assert_eq!(tb.to_string().len(), max);
+}
+
+#[test]
+fn test_try_set_value_replaces_extended() {
+    let mut tb = TaggedBase64::new_timestamped("TAG", &[1, 2, 3], 42).unwrap();
+    let max = TaggedBase64::new("TAG", &[4, 5]).unwrap().display_len();
+    tb.try_set_value(&[4, 5], max).unwrap();
+    assert_eq!(tb.to_string().len(), max);
+    assert_eq!(tb.timestamp(), None);
+}
+
+/// Deserializes `tb` with its stored checksum byte replaced by `checksum`.
+fn with_stored_checksum(tb: &TaggedBase64, checksum: u8) -> TaggedBase64 {
+    let mut bytes = Vec::new();
+    tb.serialize_compressed(&mut bytes).unwrap();
+    *bytes.last_mut().unwrap() = checksum;
+    TaggedBase64::deserialize_compressed(&*bytes).unwrap()
+}
+
+#[test]
+fn test_eq_ignores_stored_checksum() {
+    let tb = TaggedBase64::new("TAG", &[1, 2, 3]).unwrap();
+    assert!(tb.verify_checksum());
+
+    let corrupt = with_stored_checksum(&tb, tb.checksum().wrapping_add(1));
+    assert_ne!(corrupt.checksum(), tb.checksum());
+    assert!(!corrupt.verify_checksum());
+    assert_eq!(corrupt, tb);
+    assert_eq!(corrupt.cmp(&tb), Ordering::Equal);
+
+    let mut set = std::collections::HashSet::new();
+    set.insert(tb.clone());
+    assert!(set.contains(&corrupt));
+}
+
+#[test]
+fn test_verify_checksum_extended() {
+    let tb = TaggedBase64::new_timestamped("TAG", &[1, 2, 3], 42).unwrap();
+    assert!(tb.verify_checksum());
+    // An extended value is still distinct from a plain one with the same
+    // bytes.
+    let plain = TaggedBase64::new("TAG", &tb.value()).unwrap();
+    assert_ne!(plain, tb);
+    assert!(plain < tb);
+
+    for checksum in 0..=255u8 {
+        let other = with_stored_checksum(&tb, checksum);
+        if checksum == plain.checksum() {
+            // The checksum of a valid plain value with the same bytes.
+            assert!(other.verify_checksum());
+            assert_eq!(other, plain);
+        } else {
+            assert_eq!(other.verify_checksum(), checksum == tb.checksum());
+        }
+    }
+}
+
+#[test]
+fn test_byte_accessors() {
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(tb.len(), 6);
+    assert!(!tb.is_empty());
+    assert_eq!(tb[0], b'f');
+    assert_eq!(&tb[3..], b"bar");
+    assert_eq!(tb.iter().copied().collect::<Vec<_>>(), b"foobar");
+    assert_eq!((&tb).into_iter().filter(|&&b| b == b'o').count(), 2);
+    assert_eq!(tb.encoded_len(), tb.to_string().len());
+
+    let empty = TaggedBase64::new("TX", b"").unwrap();
+    assert_eq!(empty.len(), 0);
+    assert!(empty.is_empty());
+    assert_eq!(empty.iter().next(), None);
+    assert_eq!(empty.encoded_len(), "TX~tw".len());
+
+    // An extended value counts its header.
+    let stamped = TaggedBase64::new_timestamped("TX", b"foobar", 1).unwrap();
+    assert_eq!(stamped.len(), stamped.value().len());
+    assert_eq!(stamped.encoded_len(), stamped.to_string().len());
+}
+
+#[test]
+fn test_from_parts() {
+    let tb = TaggedBase64::new("TX", &[1, 2, 3]).unwrap();
+    let checksum = tb.checksum();
+    assert_eq!(
+        TaggedBase64::from_parts("TX", &[1, 2, 3], checksum).unwrap(),
+        tb
+    );
+    assert!(matches!(
+        TaggedBase64::from_parts("TX", &[1, 2, 3], checksum ^ 1),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_parts("TY", &[1, 2, 3], checksum),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_parts("T+", &[1, 2, 3], checksum),
+        Err(Tb64Error::InvalidTagChar { .. })
+    ));
+
+    let tb = TaggedBase64::new_timestamped("TX", &[1, 2, 3], 42).unwrap();
+    let checksum = tb.checksum();
+    let (tag, value) = tb.clone().into_parts();
+    let rebuilt = TaggedBase64::from_parts(&tag, &value, checksum).unwrap();
+    assert_eq!(rebuilt, tb);
+    assert_eq!(rebuilt.to_string(), tb.to_string());
+
+    // The extended bit survives every way of rebuilding the value, and
+    // still tells it apart from a plain value with the same bytes.
+    let mut bytes = Vec::new();
+    tb.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(TaggedBase64::deserialize_compressed(&*bytes).unwrap(), tb);
+    assert_eq!(TaggedBase64::from_bytes(&tb.to_bytes()).unwrap(), tb);
+    let plain = TaggedBase64::new(&tag, &value).unwrap();
+    assert_ne!(plain, tb);
+    assert_ne!(plain.cmp(&tb), Ordering::Equal);
+
+    // Mutation keeps the checksum consistent.
+    let mut tb = TaggedBase64::new("TX", &[1]).unwrap();
+    tb.set_tag("TY");
+    tb.set_value(&[2]);
+    assert!(tb.verify_checksum());
+    assert_eq!(tb, TaggedBase64::parse(&tb.to_string()).unwrap());
+}
+
+#[test]
+fn test_parse_with_checksum() {
+    let value = [5u8; 2048];
+    for kind in [
+        ChecksumKind::Crc8,
+        ChecksumKind::Crc16,
+        ChecksumKind::Crc32c,
+    ] {
+        let tb = TaggedBase64::new_with_checksum("PROOF", &value, kind).unwrap();
+        assert_eq!(tb.checksum_kind(), kind);
+        let s = tb.to_string();
+        // Auto-detected by the plain parser.
+        assert_eq!(TaggedBase64::parse(&s).unwrap().payload(), value);
+        assert_eq!(TaggedBase64::parse_with_checksum(&s, kind).unwrap(), tb);
+        assert_eq!(TaggedBase64::parse_detect_checksum(&s).unwrap(), (tb, kind));
+    }
+    assert_eq!(
+        TaggedBase64::new_timestamped("TX", &[1], 42)
+            .unwrap()
+            .checksum_kind(),
+        ChecksumKind::Crc8
+    );
+
+    let plain = TaggedBase64::new("PROOF", &value).unwrap().to_string();
+    assert!(matches!(
+        TaggedBase64::parse_with_checksum(&plain, ChecksumKind::Crc32c),
+        Err(Tb64Error::WrongChecksumKind {
+            expected: ChecksumKind::Crc32c,
+            found: ChecksumKind::Crc8
+        })
+    ));
+
+    // Without a checksum, for plain base64 peers.
+    let tb = TaggedBase64::new_with_checksum("PROOF", &value, ChecksumKind::None).unwrap();
+    assert_eq!(tb, TaggedBase64::new("PROOF", &value).unwrap());
+    let raw = tb.to_string_without_checksum();
+    assert_eq!(
+        TaggedBase64::parse_with_checksum(&raw, ChecksumKind::None).unwrap(),
+        tb
+    );
+    assert_eq!(
+        TaggedBase64::parse_detect_checksum(&raw).unwrap(),
+        (tb.clone(), ChecksumKind::None)
+    );
+    assert_eq!(
+        TaggedBase64::parse_detect_checksum("TX~").unwrap(),
+        (TaggedBase64::new("TX", b"").unwrap(), ChecksumKind::None)
+    );
+    assert!(matches!(
+        TaggedBase64::parse_with_checksum(&raw, ChecksumKind::Crc8),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_detect_checksum("TX~Zm9v*mFy"),
+        Err(Tb64Error::Base64 { .. })
+    ));
+}
+
+#[test]
+fn test_stream_writer_reader() {
+    use std::io::{Read, Write};
+
+    for len in [0, 1, 2, 3, 4, 1000, 10_000] {
+        let value: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        let mut writer = TaggedBase64Writer::new("SNAP", Vec::new()).unwrap();
+        // Write in uneven pieces to exercise the group remainder.
+        for piece in value.chunks(5) {
+            writer.write_all(piece).unwrap();
+        }
+        let out = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let expected = TaggedBase64::new("SNAP", &value).unwrap();
+        assert_eq!(out, expected.to_string());
+
+        let mut reader = TaggedBase64Reader::new(out.as_bytes()).unwrap();
+        assert_eq!(reader.tag(), "SNAP");
+        let mut decoded = Vec::new();
+        reader.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    assert!(TaggedBase64Writer::new("S+", Vec::new()).is_err());
+    assert!(matches!(
+        TaggedBase64Reader::new(&b"SNAP"[..]),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64Reader::new(&b"S+~AA"[..]),
+        Err(Tb64Error::InvalidTagChar { .. })
+    ));
+
+    // A corrupted value is reported once the checksum is reached.
+    let s = TaggedBase64::new("SNAP", &[1; 5000]).unwrap().to_string();
+    let s = s.replacen("SNAP", "SNAQ", 1);
+    let mut reader = TaggedBase64Reader::new(s.as_bytes()).unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // A write which fails takes none of its input, so it can be retried.
+    struct FlakySink {
+        out: Vec<u8>,
+        calls: usize,
+    }
+    impl Write for FlakySink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(20) {
+                return Err(std::io::ErrorKind::Other.into());
+            }
+            // Take a little at a time, so that a failure splits a chunk.
+            let n = buf.len().min(100);
+            self.out.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let value: Vec<u8> = (0..20_000).map(|i| (i * 7) as u8).collect();
+    let sink = FlakySink {
+        out: Vec::new(),
+        calls: 0,
+    };
+    let mut writer = TaggedBase64Writer::new("SNAP", sink).unwrap();
+    let mut failures = 0;
+    for piece in value.chunks(1000) {
+        while let Err(err) = writer.write(piece) {
+            assert_eq!(err.kind(), std::io::ErrorKind::Other);
+            failures += 1;
+        }
+    }
+    while writer.flush().is_err() {
+        failures += 1;
+    }
+    let out = writer.finish().unwrap().out;
+    assert!(failures > 0);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        TaggedBase64::new("SNAP", &value).unwrap().to_string()
+    );
+}
+
+#[cfg(feature = "quickcheck")]
+#[quickcheck]
+fn arbitrary_round_trip_quickcheck(tb: TaggedBase64) -> bool {
+    TaggedBase64::parse(&tb.to_string()).unwrap() == tb
+        && tb.tag().len() <= TB64_MAX_TAG_LEN
+        && tb.verify_checksum()
+}
+
+#[cfg(feature = "quickcheck")]
+#[quickcheck]
+fn arbitrary_corrupted_quickcheck(s: CorruptedString) -> bool {
+    TaggedBase64::parse(&s.0).is_err()
+}
+
+#[cfg(feature = "quickcheck")]
+#[quickcheck]
+fn arbitrary_validate_quickcheck(tb: TaggedBase64, s: CorruptedString) -> bool {
+    TaggedBase64::validate(&tb.to_string()).is_ok()
+        && format!("{:?}", TaggedBase64::validate(&s.0))
+            == format!("{:?}", TaggedBase64::parse(&s.0).map(|_| ()))
+}
+
+#[cfg(feature = "schemars")]
+#[test]
+fn test_json_schema() {
+    let schema = serde_json::to_value(schemars::schema_for!(TaggedBase64)).unwrap();
+    assert_eq!(schema["type"], "string");
+    assert_eq!(schema["format"], "tagged-base64");
+
+    // The pattern accepts serialized values and rejects malformed ones.
+    let pattern = regex::Regex::new(schema["pattern"].as_str().unwrap()).unwrap();
+    for tb in [
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("TX", b"abc").unwrap(),
+        TaggedBase64::new_timestamped("TX", b"abc", 42).unwrap(),
+    ] {
+        let json = serde_json::to_value(&tb).unwrap();
+        assert!(pattern.is_match(json.as_str().unwrap()));
+    }
+    assert!(!pattern.is_match("TX~"));
+    assert!(!pattern.is_match("T+~AAAA"));
+    assert!(!pattern.is_match(&format!("{}~AAAA", "T".repeat(TB64_MAX_TAG_LEN + 1))));
+}
+
+#[cfg(feature = "utoipa")]
+#[test]
+fn test_openapi_schema() {
+    use utoipa::{PartialSchema, ToSchema};
+
+    assert_eq!(TaggedBase64::name(), "TaggedBase64");
+    let schema = serde_json::to_value(TaggedBase64::schema()).unwrap();
+    assert_eq!(schema["type"], "string");
+    assert_eq!(schema["format"], "tagged-base64");
+
+    // The example is a valid value.
+    let example = schema["examples"][0].as_str().unwrap();
+    let tb = TaggedBase64::parse(example).unwrap();
+    assert_eq!(tb.tag(), "TX");
+    assert_eq!(tb.value(), b"foobar");
+
+    let pattern = regex::Regex::new(schema["pattern"].as_str().unwrap()).unwrap();
+    assert!(pattern.is_match(example));
+    assert!(!pattern.is_match("TX~"));
+}
+
+#[cfg(feature = "async-graphql")]
+#[test]
+fn test_graphql_scalar() {
+    use async_graphql::{InputType, OutputType, ScalarType, Value};
+
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(ScalarType::to_value(&tb), Value::String(tb.to_string()));
+    assert_eq!(
+        <TaggedBase64 as ScalarType>::parse(Value::String(tb.to_string())).unwrap(),
+        tb
+    );
+    assert_eq!(<TaggedBase64 as OutputType>::type_name(), "TaggedBase64");
+
+    let bad = tb.to_string().replacen("TX", "TY", 1);
+    let err = <TaggedBase64 as InputType>::parse(Some(Value::String(bad))).unwrap_err();
+    assert!(err
+        .into_server_error(Default::default())
+        .message
+        .contains(&Tb64Error::InvalidChecksum.to_string()));
+    assert!(<TaggedBase64 as ScalarType>::parse(Value::Boolean(true)).is_err());
+}
+
+#[cfg(all(feature = "sqlx", not(target_arch = "wasm32")))]
+#[tokio::test]
+async fn test_sqlx_column() {
+    use sqlx::{Connection, SqliteConnection};
+
+    let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+    sqlx::query("CREATE TABLE ids (id TEXT NOT NULL)")
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+    sqlx::query("INSERT INTO ids VALUES (?)")
+        .bind(&tb)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let (text,): (String,) = sqlx::query_as("SELECT id FROM ids")
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(text, tb.to_string());
+    let (read,): (TaggedBase64,) = sqlx::query_as("SELECT id FROM ids")
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(read, tb);
+
+    // A corrupted column fails to decode.
+    sqlx::query("UPDATE ids SET id = ?")
+        .bind(tb.to_string().replacen("TX", "TY", 1))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let result: Result<(TaggedBase64,), _> = sqlx::query_as("SELECT id FROM ids")
+        .fetch_one(&mut conn)
+        .await;
+    assert!(matches!(result, Err(sqlx::Error::ColumnDecode { .. })));
+}
+
+#[cfg(all(feature = "diesel-sqlite", not(target_arch = "wasm32")))]
+mod diesel_tables {
+    diesel::table! {
+        ids (rowid) {
+            rowid -> Integer,
+            id -> Text,
+        }
+    }
+
+    diesel::table! {
+        parts (rowid) {
+            rowid -> Integer,
+            tag -> Text,
+            value -> Binary,
+        }
+    }
+}
+
+#[cfg(all(feature = "diesel-sqlite", not(target_arch = "wasm32")))]
+#[test]
+fn test_diesel_columns() {
+    use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
+    use diesel_tables::{ids, parts};
+
+    let mut conn = SqliteConnection::establish(":memory:").unwrap();
+    diesel::sql_query("CREATE TABLE ids (id TEXT NOT NULL)")
+        .execute(&mut conn)
+        .unwrap();
+    diesel::sql_query("CREATE TABLE parts (tag TEXT NOT NULL, value BLOB NOT NULL)")
+        .execute(&mut conn)
+        .unwrap();
+
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+    diesel::insert_into(ids::table)
+        .values(ids::id.eq(&tb))
+        .execute(&mut conn)
+        .unwrap();
+    let text: String = ids::table.select(ids::id).first(&mut conn).unwrap();
+    assert_eq!(text, tb.to_string());
+    let read: TaggedBase64 = ids::table.select(ids::id).first(&mut conn).unwrap();
+    assert_eq!(read, tb);
+    let found: i64 = ids::table
+        .filter(ids::id.eq(&tb))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(found, 1);
+
+    // A corrupted column fails to load.
+    diesel::update(ids::table)
+        .set(ids::id.eq(tb.to_string().replacen("TX", "TY", 1)))
+        .execute(&mut conn)
+        .unwrap();
+    assert!(ids::table
+        .select(ids::id)
+        .first::<TaggedBase64>(&mut conn)
+        .is_err());
+
+    diesel::insert_into(parts::table)
+        .values((parts::tag.eq(tb.tag_ref()), parts::value.eq(tb.value_ref())))
+        .execute(&mut conn)
+        .unwrap();
+    let TaggedColumns(read) = parts::table
+        .select((parts::tag, parts::value))
+        .first(&mut conn)
+        .unwrap();
+    assert_eq!(read, tb);
+}
+
+#[cfg(feature = "rocket")]
+mod rocket_routes {
+    use super::TransactionId;
+    use tagged_base64::{TaggedBase64, Tb64Error};
+
+    #[rocket::get("/value/<id>")]
+    pub fn value(id: TaggedBase64) -> String {
+        format!("{}:{}", id.tag(), id.value().len())
+    }
+
+    #[rocket::get("/checked/<id>")]
+    pub fn checked(id: Result<TaggedBase64, Tb64Error>) -> (rocket::http::Status, String) {
+        match id {
+            Ok(id) => (rocket::http::Status::Ok, id.tag()),
+            Err(err) => (rocket::http::Status::BadRequest, err.to_string()),
+        }
+    }
+
+    #[rocket::get("/tx?<id>")]
+    pub fn tx(id: TransactionId) -> String {
+        format!("{:?}", id.0)
+    }
+}
+
+#[cfg(feature = "rocket")]
+#[test]
+fn test_rocket_params() {
+    use rocket::{http::Status, local::blocking::Client};
+    use rocket_routes::*;
+
+    let client =
+        Client::untracked(rocket::build().mount("/", rocket::routes![value, checked, tx])).unwrap();
+    let tb = TaggedBase64::new("TX", b"foobar").unwrap();
+
+    let uri = rocket::uri!(value(&tb));
+    assert_eq!(uri.to_string(), format!("/value/{}", tb));
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), "TX:6");
+
+    let bad = tb.to_string().replacen("TX", "TY", 1);
+    let response = client.get(format!("/value/{}", bad)).dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let response = client.get(format!("/checked/{}", bad)).dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(
+        response.into_string().unwrap(),
+        Tb64Error::InvalidChecksum.to_string()
+    );
+
+    // Types declared with #[tagged] work too, and check their tag.
+    let id = TransactionId(vec![1, 2, 3]);
+    let uri = rocket::uri!(tx(id = &id));
+    assert_eq!(uri.to_string(), format!("/tx?id={}", id));
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.into_string().unwrap(), "[1, 2, 3]");
+    let other = TaggedBase64::new("LA", &TaggedBase64::from(&id).value()).unwrap();
+    let response = client.get(format!("/tx?id={}", other)).dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    // Parameters are untrusted, so a short string which expands to a long
+    // value is rejected.
+    let long = TaggedBase64::new("TX", &vec![0; TB64_UNTRUSTED_MAX_DECODED_LEN + 1]).unwrap();
+    let compact = long.to_string_compact();
+    assert!(compact.len() < 10_000);
+    let response = client.get(format!("/value/{}", compact)).dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let response = client.get(format!("/checked/{}", compact)).dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(
+        response.into_string().unwrap(),
+        TaggedBase64::parse_untrusted(&compact)
+            .unwrap_err()
+            .to_string()
+    );
+    let response = client.get(format!("/tx?id={}", compact)).dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[cfg(all(feature = "axum", not(target_arch = "wasm32")))]
+#[tokio::test]
+async fn test_axum_extractors() {
+    use axum::{
+        body::{to_bytes, Body},
+        extract::{Path, Query},
+        http::{Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    #[derive(serde::Deserialize)]
+    struct Params {
+        id: TaggedBase64,
+    }
+
+    let app = Router::new()
+        .route(
+            "/tx/{id}",
+            get(
+                |TaggedBase64Path(id): TaggedBase64Path<TransactionId>| async move {
+                    format!("{:?}", id.0)
+                },
+            ),
+        )
+        .route(
+            "/plain/{id}",
+            get(|Path(id): Path<TaggedBase64>| async move { id.tag() }),
+        )
+        .route(
+            "/query",
+            get(|Query(params): Query<Params>| async move { params.id.tag() }),
+        );
+    let request = |uri: String| async {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    };
+
+    let id = TransactionId(vec![1, 2, 3]);
+    assert_eq!(
+        request(format!("/tx/{}", id)).await,
+        (StatusCode::OK, "[1, 2, 3]".to_string())
+    );
+    let other = TaggedBase64::new("LA", &TaggedBase64::from(&id).value()).unwrap();
+    assert_eq!(
+        request(format!("/tx/{}", other)).await,
+        (
+            StatusCode::BAD_REQUEST,
+            "expected tag \"TX\", found \"LA\"".to_string()
+        )
+    );
+    let bad = id.to_string().replacen("TX", "TY", 1);
+    assert_eq!(
+        request(format!("/tx/{}", bad)).await,
+        (
+            StatusCode::BAD_REQUEST,
+            Tb64Error::InvalidChecksum.to_string()
+        )
+    );
+
+    assert_eq!(
+        request(format!("/plain/{}", other)).await,
+        (StatusCode::OK, "LA".to_string())
+    );
+    assert_eq!(
+        request(format!("/query?id={}", other)).await,
+        (StatusCode::OK, "LA".to_string())
+    );
+    assert_eq!(
+        request(format!("/plain/{}", bad)).await.0,
+        StatusCode::BAD_REQUEST
+    );
+
+    // A short string which expands to a long value is rejected.
+    let long = TaggedBase64::new("TX", &vec![0; TB64_UNTRUSTED_MAX_DECODED_LEN + 1]).unwrap();
+    let compact = long.to_string_compact();
+    assert_eq!(
+        request(format!("/tx/{}", compact)).await,
+        (
+            StatusCode::BAD_REQUEST,
+            TaggedBase64::parse_untrusted(&compact)
+                .unwrap_err()
+                .to_string()
+        )
+    );
+}
+
+#[cfg(feature = "clap")]
+#[test]
+fn test_clap_value_parser() {
+    use clap::{error::ErrorKind, Parser};
+
+    #[derive(Parser)]
+    struct Opt {
+        #[arg(long)]
+        value: Option<TaggedBase64>,
+        #[arg(long, value_parser = TaggedBase64Parser::with_tag("TX"))]
+        tx: Option<TaggedBase64>,
+    }
+
+    let tx = TaggedBase64::new("TX", b"foobar").unwrap();
+    let other = TaggedBase64::new("LA", b"foobar").unwrap();
+    let opt = Opt::try_parse_from([
+        "app",
+        "--value",
+        &other.to_string(),
+        "--tx",
+        &tx.to_string(),
+    ])
+    .unwrap();
+    assert_eq!(opt.value, Some(other.clone()));
+    assert_eq!(opt.tx, Some(tx.clone()));
+
+    let err = Opt::try_parse_from(["app", "--tx", &other.to_string()])
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::ValueValidation);
+    assert!(err
+        .to_string()
+        .contains("expected tag \"TX\", found \"LA\""));
+
+    let bad = tx.to_string().replacen("TX", "TY", 1);
+    let err = Opt::try_parse_from(["app", "--value", &bad]).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::ValueValidation);
+    assert!(err.to_string().contains(&format!(
+        "invalid value '{}' for '--value <VALUE>': {}",
+        bad,
+        Tb64Error::InvalidChecksum
+    )));
+}
+
+#[cfg(feature = "serde")]
+mod serde_bytes_fields {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    const LEDGER_TAG: &str = "LA";
+
+    tagged_base64::tagged_bytes!(mod tx_bytes, "TX");
+    tagged_base64::tagged_bytes!(pub(crate) mod ledger_bytes, LEDGER_TAG);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Transfer {
+        #[serde(with = "tx_bytes")]
+        id: [u8; 6],
+        #[serde(with = "ledger_bytes")]
+        to: Vec<u8>,
+    }
+
+    #[test]
+    fn test_tagged_bytes_round_trip() {
+        let transfer = Transfer {
+            id: *b"foobar",
+            to: vec![1, 2, 3],
+        };
+        let json = serde_json::to_value(&transfer).unwrap();
+        assert_eq!(json["id"], "TX~Zm9vYmFy1w");
+        assert_eq!(
+            json["to"],
+            TaggedBase64::new("LA", &[1, 2, 3]).unwrap().to_string()
+        );
+        assert_eq!(
+            serde_json::from_value::<Transfer>(json.clone()).unwrap(),
+            transfer
+        );
+        assert_eq!(
+            bincode::deserialize::<Transfer>(&bincode::serialize(&transfer).unwrap()).unwrap(),
+            transfer
+        );
+
+        // The binary form is that of TaggedBase64.
+        let mut expected =
+            bincode::serialize(&TaggedBase64::new("TX", b"foobar").unwrap()).unwrap();
+        expected.extend(bincode::serialize(&TaggedBase64::new("LA", &[1, 2, 3]).unwrap()).unwrap());
+        assert_eq!(bincode::serialize(&transfer).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_tagged_bytes_rejects() {
+        let valid = serde_json::to_value(Transfer {
+            id: *b"foobar",
+            to: vec![],
+        })
+        .unwrap();
+
+        // Wrong tag.
+        let mut json = valid.clone();
+        json["id"] = TaggedBase64::new("LA", b"foobar")
+            .unwrap()
+            .to_string()
+            .into();
+        let err = serde_json::from_value::<Transfer>(json).unwrap_err();
+        assert!(err.to_string().contains(&Tb64Error::InvalidTag.to_string()));
+
+        // Wrong length for the array.
+        let mut json = valid.clone();
+        json["id"] = TaggedBase64::new("TX", b"foo").unwrap().to_string().into();
+        let err = serde_json::from_value::<Transfer>(json).unwrap_err();
+        assert!(err.to_string().contains("unexpected value length 3"));
+
+        // Extended values contribute their payload.
+        let mut json = valid;
+        json["id"] = TaggedBase64::new_timestamped("TX", b"foobar", 17)
+            .unwrap()
+            .to_string()
+            .into();
+        assert_eq!(
+            serde_json::from_value::<Transfer>(json).unwrap().id,
+            *b"foobar"
+        );
+    }
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi() {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+    use tagged_base64::ffi::*;
+
+    unsafe {
+        let s = CString::new("TX~Zm9vYmFy1w").unwrap();
+        let mut tb64 = ptr::null_mut();
+        assert_eq!(tb64_parse(s.as_ptr(), &mut tb64), Tb64Status::Ok);
+        assert!(!tb64.is_null());
+
+        let tag = tb64_tag(tb64);
+        assert_eq!(CStr::from_ptr(tag).to_str().unwrap(), "TX");
+        tb64_string_free(tag);
+        let mut len = 0;
+        let value = tb64_value(tb64, &mut len);
+        assert_eq!(std::slice::from_raw_parts(value, len), b"foobar");
+        let encoded = tb64_encode(tb64);
+        assert_eq!(CStr::from_ptr(encoded).to_str().unwrap(), "TX~Zm9vYmFy1w");
+        tb64_string_free(encoded);
+        tb64_free(tb64);
+
+        let tag = CString::new("TX").unwrap();
+        let mut tb64 = ptr::null_mut();
+        assert_eq!(
+            tb64_new(tag.as_ptr(), b"foobar".as_ptr(), 6, &mut tb64),
+            Tb64Status::Ok
+        );
+        assert_eq!(*tb64, TaggedBase64::new("TX", b"foobar").unwrap());
+        tb64_free(tb64);
+        assert_eq!(
+            tb64_new(tag.as_ptr(), ptr::null(), 0, &mut tb64),
+            Tb64Status::Ok
+        );
+        assert_eq!(*tb64, TaggedBase64::new("TX", b"").unwrap());
+        tb64_free(tb64);
+
+        // Failures store null and report the kind of error.
+        let s = CString::new("TY~Zm9vYmFy1w").unwrap();
+        let mut tb64 = ptr::null_mut();
+        assert_eq!(
+            tb64_parse(s.as_ptr(), &mut tb64),
+            Tb64Status::InvalidChecksum
+        );
+        assert!(tb64.is_null());
+        let long = TaggedBase64::new("TX", &vec![0; TB64_UNTRUSTED_MAX_DECODED_LEN + 1]).unwrap();
+        let long = CString::new(long.to_string()).unwrap();
+        assert_eq!(
+            tb64_parse(long.as_ptr(), &mut tb64),
+            Tb64Status::DecodedTooLong
+        );
+        assert!(tb64.is_null());
+        let bad_tag = CString::new("T~X").unwrap();
+        assert_eq!(
+            tb64_new(bad_tag.as_ptr(), ptr::null(), 0, &mut tb64),
+            Tb64Status::InvalidTagChar
+        );
+        assert_eq!(
+            tb64_new(tag.as_ptr(), ptr::null(), 1, &mut tb64),
+            Tb64Status::NullPointer
+        );
+        assert_eq!(tb64_parse(ptr::null(), &mut tb64), Tb64Status::NullPointer);
+        assert_eq!(
+            tb64_parse(s.as_ptr(), ptr::null_mut()),
+            Tb64Status::NullPointer
+        );
+        let invalid = CString::new(vec![0xff, b'~']).unwrap();
+        assert_eq!(
+            tb64_parse(invalid.as_ptr(), &mut tb64),
+            Tb64Status::InvalidUtf8
+        );
+
+        assert!(tb64_encode(ptr::null()).is_null());
+        assert!(tb64_tag(ptr::null()).is_null());
+        assert!(tb64_value(ptr::null(), &mut len).is_null());
+        assert_eq!(len, 0);
+        tb64_free(ptr::null_mut());
+        tb64_string_free(ptr::null_mut());
+        assert_eq!(
+            CStr::from_ptr(tb64_status_message(Tb64Status::InvalidChecksum)),
+            c"invalid checksum"
+        );
+    }
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn test_borsh() {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct Record {
+        id: TaggedBase64,
+        amount: u64,
+    }
+
+    for id in [
+        TaggedBase64::new("TX", b"foobar").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap(),
+        TaggedBase64::new_with_checksum("TX", &[7; 300], ChecksumKind::Crc32c).unwrap(),
+    ] {
+        let record = Record {
+            id: id.clone(),
+            amount: 5,
+        };
+        let bytes = borsh::to_vec(&record).unwrap();
+        let decoded: Record = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, record);
+        assert!(decoded.id.verify_checksum());
+        assert_eq!(decoded.id.to_string(), id.to_string());
+    }
+
+    // Tag, then value, then the extended flag.
+    let bytes = borsh::to_vec(&TaggedBase64::new("TX", b"foobar").unwrap()).unwrap();
+    let expected = borsh::to_vec(&("TX".to_string(), b"foobar".to_vec(), false)).unwrap();
+    assert_eq!(bytes, expected);
+
+    // Invalid tags and extended headers are rejected.
+    let bytes = borsh::to_vec(&("T~X".to_string(), b"foobar".to_vec(), false)).unwrap();
+    assert!(borsh::from_slice::<TaggedBase64>(&bytes).is_err());
+    let bytes = borsh::to_vec(&("TX".to_string(), b"foobar".to_vec(), true)).unwrap();
+    assert!(borsh::from_slice::<TaggedBase64>(&bytes).is_err());
+}
+
+#[cfg(feature = "rkyv")]
+#[test]
+fn test_rkyv() {
+    use rkyv::rancor::Error;
+    use tagged_base64::ArchivedTaggedBase64;
+
+    for tb64 in [
+        TaggedBase64::new("TX", b"foobar").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("TX", &[7; 100]).unwrap(),
+        TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap(),
+        TaggedBase64::new_with_checksum("TX", &[7; 300], ChecksumKind::Crc32c).unwrap(),
+    ] {
+        let bytes = rkyv::to_bytes::<Error>(&tb64).unwrap();
+        let archived = rkyv::access::<ArchivedTaggedBase64, Error>(&bytes).unwrap();
+        assert_eq!(archived.tag().unwrap(), tb64.tag());
+        assert_eq!(archived.value(), tb64.value_ref());
+        assert_eq!(archived.checksum(), tb64.checksum());
+        assert!(archived.verify_checksum());
+        let decoded = rkyv::deserialize::<TaggedBase64, Error>(archived).unwrap();
+        assert_eq!(decoded, tb64);
+        assert_eq!(decoded.to_string(), tb64.to_string());
+    }
+
+    // An invalid tag is reported on access, and the value can't be
+    // deserialized.
+    let mut bytes = rkyv::to_bytes::<Error>(&TaggedBase64::new("TX", b"foobar").unwrap())
+        .unwrap()
+        .to_vec();
+    let pos = bytes.windows(2).position(|w| w == b"TX").unwrap();
+    bytes[pos + 1] = b'~';
+    let mut aligned = rkyv::util::AlignedVec::<16>::new();
+    aligned.extend_from_slice(&bytes);
+    let archived = rkyv::access::<ArchivedTaggedBase64, Error>(&aligned).unwrap();
+    assert!(matches!(
+        archived.tag(),
+        Err(Tb64Error::InvalidTagChar {
+            offset: 1,
+            character: '~'
+        })
+    ));
+    assert!(!archived.verify_checksum());
+    assert!(rkyv::deserialize::<TaggedBase64, Error>(archived).is_err());
+
+    // So is a checksum which doesn't match.
+    bytes[pos + 1] = b'Y';
+    let mut aligned = rkyv::util::AlignedVec::<16>::new();
+    aligned.extend_from_slice(&bytes);
+    let archived = rkyv::access::<ArchivedTaggedBase64, Error>(&aligned).unwrap();
+    assert_eq!(archived.tag().unwrap(), "TY");
+    assert!(!archived.verify_checksum());
+    assert!(rkyv::deserialize::<TaggedBase64, Error>(archived).is_err());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_random() {
+    use rand::{distributions::Distribution, SeedableRng};
+    use rand_chacha::ChaChaRng;
+    use tagged_base64::TaggedBase64Distribution;
+
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    for len in [0, 1, 32, 100] {
+        let tb64 = TaggedBase64::random("TX", len, &mut rng).unwrap();
+        assert_eq!(tb64.tag(), "TX");
+        assert_eq!(tb64.value().len(), len);
+        assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
+    }
+    assert_ne!(
+        TaggedBase64::random("TX", 32, &mut rng).unwrap(),
+        TaggedBase64::random("TX", 32, &mut rng).unwrap()
+    );
+    assert!(matches!(
+        TaggedBase64::random("T~X", 32, &mut rng),
+        Err(Tb64Error::InvalidTagChar { .. })
+    ));
+
+    // The same seed gives the same values.
+    let dist = TaggedBase64Distribution::new("KEY", 16).unwrap();
+    assert_eq!((dist.tag(), dist.value_len()), ("KEY", 16));
+    let sample = |seed| -> Vec<TaggedBase64> {
+        (&dist)
+            .sample_iter(ChaChaRng::seed_from_u64(seed))
+            .take(5)
+            .collect()
+    };
+    assert_eq!(sample(1), sample(1));
+    assert_ne!(sample(1), sample(2));
+    assert!(sample(1)
+        .iter()
+        .all(|key| key.tag() == "KEY" && key.value().len() == 16));
+    assert!(TaggedBase64Distribution::new("K Y", 16).is_err());
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_test_utils() {
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use tagged_base64::test_utils::*;
+
+    let chars = tag_chars().collect::<String>();
+    assert_eq!(chars.len(), 64);
+    assert!(chars.starts_with("-0123456789ABC"));
+    assert!(chars.ends_with("xyz"));
+    assert_eq!(invalid_tag_chars().count(), 128 - 64);
+    assert!(invalid_tag_chars().any(|c| c == TB64_DELIM));
+    for c in invalid_tag_chars() {
+        assert!(TaggedBase64::validate_tag(&c.to_string()).is_err());
+    }
+
+    let tags = all_tags(2).collect::<Vec<_>>();
+    assert_eq!(tags.len(), 1 + 64 + 64 * 64);
+    assert_eq!(tags[0], "");
+    assert_eq!(
+        tags.iter().collect::<std::collections::HashSet<_>>().len(),
+        tags.len()
+    );
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    for tag in tags.iter().step_by(37) {
+        let tb64 = TaggedBase64::new(tag, b"foobar").unwrap();
+        assert_roundtrip(&tb64);
+        let corrupted = corrupt_one_char(&tb64.to_string(), &mut rng);
+        assert_eq!(corrupted.chars().count(), tb64.to_string().len());
+        assert!(TaggedBase64::parse(&corrupted).is_err());
+    }
+    assert_roundtrip(&TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap());
+
+    assert_tagged_roundtrip(&Blob(vec![1, 2, 3]));
+}
+
+#[cfg(feature = "subtle")]
+#[test]
+fn test_ct_eq() {
+    use subtle::ConstantTimeEq;
+
+    let tb = TaggedBase64::new("TAG", &[1, 2, 3]).unwrap();
+    let ct_eq = |a: &TaggedBase64, b: &TaggedBase64| bool::from(a.ct_eq(b));
+    let values = [
+        tb.clone(),
+        TaggedBase64::new("TAH", &[1, 2, 3]).unwrap(),
+        TaggedBase64::new("TA", &[1, 2, 3]).unwrap(),
+        TaggedBase64::new("TAG", &[1, 2, 4]).unwrap(),
+        TaggedBase64::new("TAG", &[1, 2]).unwrap(),
+        TaggedBase64::new("TAG", &[]).unwrap(),
+        TaggedBase64::new_timestamped("TAG", &[1, 2, 3], 42).unwrap(),
+        TaggedBase64::new("TAG", &[7; 100]).unwrap(),
+    ];
+    // Agrees with `==` on every pair.
+    for a in &values {
+        for b in &values {
+            assert_eq!(ct_eq(a, b), a == b, "{:?} {:?}", a, b);
+        }
+    }
+    // An extended value differs from a plain one with the same bytes.
+    let extended = &values[6];
+    let plain = TaggedBase64::new("TAG", extended.value_ref()).unwrap();
+    assert!(!ct_eq(extended, &plain));
+    assert!(ct_eq(&tb, &TaggedBase64::parse(&tb.to_string()).unwrap()));
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn test_urls() {
+    use tagged_base64::urls::*;
+    use url::Url;
+
+    let tb64 = TaggedBase64::new("TX", b"foobar").unwrap();
+    let extended = TaggedBase64::new_timestamped("TX", b"foobar", 42).unwrap();
+
+    let mut url = Url::parse("https://example.com/tx").unwrap();
+    append_query_param(&mut url, "id", &tb64);
+    append_query_param(&mut url, "a key", &extended);
+    assert_eq!(
+        url.as_str(),
+        format!("https://example.com/tx?id={}&a+key={}", tb64, extended)
+    );
+    assert_eq!(from_query(&url, "id").unwrap(), tb64);
+    assert_eq!(from_query(&url, "a key").unwrap(), extended);
+    assert!(matches!(
+        from_query(&url, "missing"),
+        Err(Tb64Error::MissingQueryParam { key }) if key == "missing"
+    ));
+
+    // The delimiter as encoded by a form serializer or a proxy.
+    let mut url = Url::parse("https://example.com/tx").unwrap();
+    url.query_pairs_mut().append_pair("id", &tb64.to_string());
+    assert!(url.as_str().ends_with("?id=TX%7EZm9vYmFy1w"));
+    assert_eq!(from_query(&url, "id").unwrap(), tb64);
+    let url = Url::parse("https://example.com/tx?id=TX%7eZm9vYmFy1w").unwrap();
+    assert_eq!(from_query(&url, "id").unwrap(), tb64);
+    let long = TaggedBase64::new("TX", &vec![0; TB64_UNTRUSTED_MAX_DECODED_LEN + 1]).unwrap();
+    let url = Url::parse(&format!(
+        "https://example.com/tx?id={}",
+        long.to_string_compact()
+    ))
+    .unwrap();
+    assert!(matches!(
+        from_query(&url, "id"),
+        Err(Tb64Error::DecodedTooLong { .. })
+    ));
+
+    assert_eq!(decode_component("TX~Zm9vYmFy1w").unwrap(), tb64);
+    assert_eq!(decode_component("TX%7EZm9vYmFy1w").unwrap(), tb64);
+    assert_eq!(
+        decode_component(&extended.to_string().replace('~', "%7E")).unwrap(),
+        extended
+    );
+    assert!(decode_component("TX%7EZm9vYmFz1w").is_err());
+    assert!(decode_component("TX%FFZm9vYmFy1w").is_err());
+    let url = Url::parse("https://example.com/tx/TX%7EZm9vYmFy1w").unwrap();
+    let segment = url.path_segments().unwrap().next_back().unwrap();
+    assert_eq!(decode_component(segment).unwrap(), tb64);
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_qr() {
+    /// Decodes the PNG and reads the code in it.
+    fn scan(png: &[u8]) -> String {
+        let mut reader = png::Decoder::new(png).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| {
+            pixels[y * width + x]
+        });
+        let grids = image.detect_grids();
+        assert_eq!(grids.len(), 1);
+        grids[0].decode().unwrap().1
+    }
+
+    let tb64 = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(scan(&tb64.to_qr_png(4).unwrap()), tb64.to_string());
+    let scanned = scan(&tb64.to_qr_png_base32(4).unwrap());
+    assert_eq!(scanned, tb64.to_string_base32().unwrap().to_uppercase());
+    assert_eq!(TaggedBase64::parse_any(&scanned).unwrap(), tb64);
+
+    let svg = tb64.to_qr_svg().unwrap();
+    assert!(svg.starts_with("<?xml") && svg.contains("<svg"));
+    assert!(tb64.to_qr_svg_base32().unwrap().contains("<svg"));
+
+    // Base32 is encoded in the denser alphanumeric mode, so a larger value
+    // gets a smaller code despite the longer string.
+    let tb64 = TaggedBase64::new("TX", &[7; 300]).unwrap();
+    let side = |png: &[u8]| png::Decoder::new(png).read_info().unwrap().info().width;
+    assert!(side(&tb64.to_qr_png_base32(1).unwrap()) < side(&tb64.to_qr_png(1).unwrap()));
+
+    assert!(matches!(
+        TaggedBase64::new("tx", b"foobar")
+            .unwrap()
+            .to_qr_svg_base32(),
+        Err(Tb64Error::InvalidTagChar { .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::new("TX", &[7; 2000]).unwrap().to_qr_svg(),
+        Err(Tb64Error::ValueTooLong { max: 2331, .. })
+    ));
+    assert!(TaggedBase64::new("TX", &[7; 2000])
+        .unwrap()
+        .to_qr_svg_base32()
+        .is_ok());
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_compressed() {
+    let doc: Vec<u8> = (0..200)
+        .flat_map(|i| format!(r#"{{"id":{i},"kind":"memo","note":"lunch"}},"#).into_bytes())
+        .collect();
+    for tb64 in [
+        TaggedBase64::new("DOC", &doc).unwrap(),
+        TaggedBase64::new_timestamped("DOC", &doc, 9).unwrap(),
+        TaggedBase64::new_with_checksum("DOC", &doc, ChecksumKind::Crc32c).unwrap(),
+    ] {
+        let s = tb64.to_string_compressed();
+        assert!(s.len() * 5 < tb64.to_string().len(), "{}", s.len());
+        assert!(s.starts_with("DOC~~"));
+        assert_eq!(TaggedBase64::parse(&s).unwrap(), tb64);
+        assert!(!TaggedBase64::is_canonical(&s).unwrap());
+        assert_eq!(TaggedBase64Ref::parse(&s).unwrap().into_owned(), tb64);
+    }
+
+    // Incompressible values are written as usual.
+    let tb64 = TaggedBase64::new("TX", b"foobar").unwrap();
+    assert_eq!(tb64.to_string_compressed(), tb64.to_string());
+
+    // The declared length is checked against the limits before inflating,
+    // even by plain parse.
+    let full = TaggedBase64::new("TX", &vec![0; TB64_MAX_EXPANDED_LEN]).unwrap();
+    let s = full.to_string_compressed();
+    assert!(s.len() < 2000);
+    assert!(matches!(
+        TaggedBase64::parse_untrusted(&s),
+        Err(Tb64Error::DecodedTooLong { len, max })
+            if len == TB64_MAX_EXPANDED_LEN && max == TB64_UNTRUSTED_MAX_DECODED_LEN
+    ));
+    assert_eq!(TaggedBase64::parse(&s).unwrap(), full);
+    let bomb = TaggedBase64::new("TX", &vec![0; 10_000_000]).unwrap();
+    let s = bomb.to_string_compressed();
+    assert!(s.len() < 20_000);
+    for err in [
+        TaggedBase64::parse(&s).unwrap_err(),
+        TaggedBase64Ref::parse(&s).unwrap_err(),
+        TaggedBase64::parse_bytes(s.as_bytes()).unwrap_err(),
+    ] {
+        assert!(matches!(
+            err,
+            Tb64Error::DecodedTooLong { len, max }
+                if len == 10_000_000 && max == TB64_MAX_EXPANDED_LEN
+        ));
+    }
 }